@@ -0,0 +1,66 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nes_emulator_rust::cpu::{StepOutcome, CPU};
+
+/// A loop of loads, stores, arithmetic, compares, branches and subroutine calls, run 255 times.
+const MIXED_LOOP: [u8; 20] = [
+  0xA2, 0x00, // LDX #$00
+  0x20, 0x0D, 0x06, // loop: JSR add
+  0xE8, // INX
+  0xE0, 0xFF, // CPX #$FF
+  0xD0, 0xF8, // BNE loop
+  0x00, // BRK
+  0xEA, 0xEA, // (padding)
+  0xA9, 0x01, // add: LDA #$01
+  0x65, 0x10, // ADC $10
+  0x85, 0x10, // STA $10
+  0x60, // RTS
+];
+
+/// An instruction repeated until the BRK at the end.
+fn straight_line(instruction: &[u8], count: usize) -> Vec<u8> {
+  let mut program = instruction.repeat(count);
+  program.push(0x00);
+  program
+}
+
+fn cpu_with_program(program: &[u8]) -> CPU {
+  let mut cpu = CPU::new();
+  cpu.load(program.to_vec()).unwrap();
+  cpu.reset();
+  cpu
+}
+
+fn instruction_count(program: &[u8]) -> u64 {
+  let mut cpu = cpu_with_program(program);
+  let mut count = 0;
+  while cpu.step().unwrap() == StepOutcome::Executed {
+    count += 1;
+  }
+  count
+}
+
+fn bench_program(c: &mut Criterion, name: &str, program: &[u8]) {
+  let mut group = c.benchmark_group("cpu");
+  group.throughput(Throughput::Elements(instruction_count(program)));
+  group.bench_function(name, |b| {
+    let mut cpu = cpu_with_program(program);
+    b.iter(|| {
+      cpu.reset();
+      cpu.run().unwrap();
+    })
+  });
+  group.finish();
+}
+
+fn instructions_per_second(c: &mut Criterion) {
+  bench_program(c, "mixed loop", &MIXED_LOOP);
+  bench_program(c, "inx", &straight_line(&[0xE8], 1000));
+  bench_program(
+    c,
+    "lda absolute,x",
+    &straight_line(&[0xBD, 0x00, 0x02], 1000),
+  );
+}
+
+criterion_group!(benches, instructions_per_second);
+criterion_main!(benches);
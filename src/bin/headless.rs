@@ -0,0 +1,358 @@
+use nes_emulator_rust::capture::Recorder;
+use nes_emulator_rust::cartridge::Cartridge;
+use nes_emulator_rust::cpu::{BreakPolicy, CPU};
+use nes_emulator_rust::loader::Program;
+use nes_emulator_rust::palette::Palette;
+use nes_emulator_rust::ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+use nes_emulator_rust::region::{Region, RegionSetting};
+use nes_emulator_rust::screenshot::save_png;
+use nes_emulator_rust::script::ScriptHost;
+use nes_emulator_rust::symbols::SymbolTable;
+use nes_emulator_rust::test_roms::run_blargg_rom;
+use nes_emulator_rust::tracer::Tracer;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: headless <rom.nes|program> [--load-address ADDR] [--frames N] \
+  [--cheat CODE]... [--toggle-cheat FRAME:INDEX]... [--trace FILE] [--trace-last N] \
+  [--symbols FILE]... [--blargg] [--screenshot-at-frame N]... [--screenshot-scale N] [--record PATH] \
+  [--record-from N] [--script FILE] [--region ntsc|pal|dendy|auto]";
+/// How long a blargg test ROM may run: a minute of NTSC CPU time.
+const BLARGG_MAX_CYCLES: u64 = 60 * 1_789_773;
+
+struct Options {
+  rom: String,
+  /// Where to load a raw binary (other programs say where they go).
+  load_address: Option<u16>,
+  frames: u64,
+  cheats: Vec<String>,
+  /// (frame, cheat index) pairs: the cheat is toggled when that frame starts.
+  cheat_toggles: Vec<(u64, usize)>,
+  trace: Option<String>,
+  /// Keep only the last N traced instructions, dumped if the CPU crashes.
+  trace_last: Option<usize>,
+  /// Label files for the trace.
+  symbols: Vec<String>,
+  /// Run a blargg test ROM until it reports its result.
+  blargg: bool,
+  /// Frames to save as `<rom>-frame<N>.png`.
+  screenshot_frames: Vec<u64>,
+  screenshot_scale: usize,
+  /// Record the frames to `PATH.y4m` and `PATH.wav`.
+  record: Option<String>,
+  /// Number of frames to run before recording.
+  record_from: u64,
+  /// A Rhai script to run alongside the ROM.
+  script: Option<String>,
+  /// The console region, taken from the ROM header unless chosen.
+  region: RegionSetting,
+}
+
+fn parse_options() -> Result<Options, String> {
+  let mut args = std::env::args().skip(1);
+  let mut options = Options {
+    rom: String::new(),
+    load_address: None,
+    frames: 60,
+    cheats: Vec::new(),
+    cheat_toggles: Vec::new(),
+    trace: None,
+    trace_last: None,
+    symbols: Vec::new(),
+    blargg: false,
+    screenshot_frames: Vec::new(),
+    screenshot_scale: 1,
+    record: None,
+    record_from: 0,
+    script: None,
+    region: RegionSetting::Auto,
+  };
+
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+    match arg.as_str() {
+      "--load-address" => {
+        let addr = value()?;
+        let hex = addr.trim_start_matches('$').trim_start_matches("0x");
+        options.load_address = Some(
+          u16::from_str_radix(hex, 16).map_err(|_| format!("invalid --load-address {:?}", addr))?,
+        )
+      }
+      "--frames" => options.frames = value()?.parse().map_err(|_| "invalid --frames")?,
+      "--cheat" => options.cheats.push(value()?),
+      "--toggle-cheat" => {
+        let toggle = value()?;
+        let (frame, index) = toggle
+          .split_once(':')
+          .and_then(|(frame, index)| Some((frame.parse().ok()?, index.parse().ok()?)))
+          .ok_or_else(|| format!("invalid --toggle-cheat {:?}", toggle))?;
+        options.cheat_toggles.push((frame, index));
+      }
+      "--trace" => options.trace = Some(value()?),
+      "--symbols" => options.symbols.push(value()?),
+      "--trace-last" => {
+        options.trace_last = Some(value()?.parse().map_err(|_| "invalid --trace-last")?)
+      }
+      "--blargg" => options.blargg = true,
+      "--screenshot-at-frame" => options.screenshot_frames.push(
+        value()?
+          .parse()
+          .map_err(|_| "invalid --screenshot-at-frame")?,
+      ),
+      "--screenshot-scale" => {
+        options.screenshot_scale = value()?.parse().map_err(|_| "invalid --screenshot-scale")?
+      }
+      "--record" => options.record = Some(value()?),
+      "--record-from" => {
+        options.record_from = value()?.parse().map_err(|_| "invalid --record-from")?
+      }
+      "--script" => options.script = Some(value()?),
+      "--region" => options.region = RegionSetting::parse(&value()?)?,
+      _ if options.rom.is_empty() && !arg.starts_with("--") => options.rom = arg,
+      _ => return Err(format!("unexpected argument {:?}", arg)),
+    }
+  }
+
+  if options.rom.is_empty() {
+    return Err("missing ROM".to_string());
+  }
+  let is_rom = options.rom.to_ascii_lowercase().ends_with(".nes");
+  if options.record.is_some() && !is_rom {
+    return Err("recording needs a NES ROM (programs have no PPU)".to_string());
+  }
+  if !options.screenshot_frames.is_empty() {
+    if !is_rom {
+      return Err("screenshots need a NES ROM (programs have no PPU)".to_string());
+    }
+    // run long enough to take them all
+    let last = options.screenshot_frames.iter().max().copied();
+    options.frames = options.frames.max(last.unwrap_or(0));
+  }
+  Ok(options)
+}
+
+/// The frame the PPU just finished, as RGB24.
+fn frame_rgb(ppu: &PPU, palette: &Palette) -> Vec<u8> {
+  let mut rgb = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+  palette.frame_to_rgb(&ppu.frame, &mut rgb);
+  rgb
+}
+
+/// Completes the video and audio files, if recording.
+fn finish_recording(recorder: Option<Recorder>) {
+  if let Some(recorder) = recorder {
+    let frames = recorder.frames();
+    match recorder.finish() {
+      Ok(()) => println!("recorded {} frames", frames),
+      Err(err) => eprintln!("could not finish the recording: {}", err),
+    }
+  }
+}
+
+/// Reports an error of the script and stops, keeping what was recorded.
+fn script_failed(err: &str, recorder: &mut Option<Recorder>) -> ! {
+  eprintln!("script: {}", err);
+  finish_recording(recorder.take());
+  process::exit(1);
+}
+
+/// Saves the frame the PPU just finished as `<rom>-frame<N>.png` in the current directory.
+fn save_screenshot(rgb: &[u8], options: &Options, frame: u64) {
+  let stem = Path::new(&options.rom)
+    .file_stem()
+    .map_or("screenshot".into(), |stem| stem.to_string_lossy());
+  let path = format!("{}-frame{}.png", stem, frame);
+  match save_png(
+    &path,
+    rgb,
+    SCREEN_WIDTH,
+    SCREEN_HEIGHT,
+    options.screenshot_scale,
+  ) {
+    Ok(()) => println!("frame {}: saved {}", frame, path),
+    Err(err) => eprintln!("frame {}: could not save the screenshot: {}", frame, err),
+  }
+}
+
+/// Runs a ROM without any window or sound, for automated testing.
+fn main() {
+  let options = parse_options().unwrap_or_else(|err| {
+    eprintln!("{}\n{}", err, USAGE);
+    process::exit(2);
+  });
+
+  if options.blargg {
+    match run_blargg_rom(&options.rom, BLARGG_MAX_CYCLES) {
+      Ok(result) => {
+        println!("{}", result.message);
+        if !result.passed() {
+          eprintln!("failed with status {}", result.status);
+          process::exit(1);
+        }
+        process::exit(0);
+      }
+      Err(err) => {
+        eprintln!("{}", err);
+        process::exit(1);
+      }
+    }
+  }
+
+  let mut cpu = CPU::new();
+  let loaded = if options.rom.to_ascii_lowercase().ends_with(".nes") {
+    // games need the whole official opcode set, and BRK is an interrupt on the console
+    cpu.use_cycle_core();
+    cpu.break_policy = BreakPolicy::Interrupt;
+    Cartridge::load_with_region(&options.rom, options.region)
+      .map(|cartridge| cpu.bus.insert_cartridge(cartridge))
+  } else {
+    // a bare 6502 program, which runs until BRK as there is no PPU to count frames
+    Program::from_file(&options.rom, options.load_address)
+      .and_then(|program| cpu.load_program(&program).map_err(|err| err.to_string()))
+  };
+  if let Err(err) = loaded {
+    eprintln!("{}", err);
+    process::exit(1);
+  }
+  for code in &options.cheats {
+    if let Err(err) = cpu.bus.cheats.add(code) {
+      eprintln!("{}", err);
+      process::exit(2);
+    }
+  }
+
+  let mut tracer = match (&options.trace, options.trace_last) {
+    (None, None) => None,
+    (path, last) => {
+      let mut tracer = match path {
+        Some(path) => Tracer::to_file(path).unwrap_or_else(|err| {
+          eprintln!("{}: {}", path, err);
+          process::exit(1);
+        }),
+        None => Tracer::new(),
+      };
+      if let Some(last) = last {
+        tracer.set_ring_buffer(last);
+      }
+      for path in &options.symbols {
+        let symbols = SymbolTable::load(path).unwrap_or_else(|err| {
+          eprintln!("{}", err);
+          process::exit(1);
+        });
+        tracer.symbols.extend(&symbols);
+      }
+      Some(tracer)
+    }
+  };
+
+  let palette = Palette::default();
+  let mut recorder = options.record.as_ref().map(|path| {
+    let region = cpu
+      .bus
+      .ppu
+      .as_ref()
+      .map_or_else(Region::default, |ppu| ppu.region);
+    Recorder::create(path, SCREEN_WIDTH, SCREEN_HEIGHT, region.frame_rate()).unwrap_or_else(|err| {
+      eprintln!("{}: {}", path, err);
+      process::exit(1);
+    })
+  });
+
+  cpu.reset();
+  let mut script = options.script.as_ref().map(|path| {
+    ScriptHost::load(path, &mut cpu).unwrap_or_else(|err| {
+      eprintln!("{}", err);
+      process::exit(1);
+    })
+  });
+  let mut frame = 0;
+  let result = cpu.run_with_callback(|cpu| {
+    if let Some(Err(err)) = script.as_mut().map(|script| script.before_instruction(cpu)) {
+      script_failed(&err, &mut recorder);
+    }
+    if let Some(tracer) = &mut tracer {
+      tracer.trace(cpu);
+    }
+
+    let frame_count = cpu.bus.ppu.as_ref().map_or(0, |ppu| ppu.frame_count);
+    if frame_count == frame {
+      return;
+    }
+    frame = frame_count;
+
+    for (_, index) in options.cheat_toggles.iter().filter(|(at, _)| *at == frame) {
+      match cpu.bus.cheats.toggle(*index) {
+        Some(enabled) => println!(
+          "frame {}: cheat {} {}",
+          frame,
+          index,
+          if enabled { "on" } else { "off" }
+        ),
+        None => eprintln!("frame {}: there is no cheat {}", frame, index),
+      }
+    }
+
+    if let Some(script) = &mut script {
+      if let Err(err) = script.end_frame(cpu) {
+        script_failed(&err, &mut recorder);
+      }
+      // a state loaded by the script sets the frame count back
+      frame = cpu.bus.ppu.as_ref().map_or(0, |ppu| ppu.frame_count);
+    }
+
+    let wants_screenshot = options.screenshot_frames.contains(&frame);
+    let wants_recording = recorder.is_some() && frame > options.record_from;
+    if let (Some(ppu), true) = (&cpu.bus.ppu, wants_screenshot || wants_recording) {
+      let mut rgb = frame_rgb(ppu, &palette);
+      if let Some(script) = &script {
+        script.draw_overlay(&mut rgb, SCREEN_WIDTH, SCREEN_HEIGHT);
+      }
+      if wants_screenshot {
+        save_screenshot(&rgb, &options, frame);
+      }
+      if let (Some(recorder), true) = (&mut recorder, wants_recording) {
+        if let Err(err) = recorder.record_frame(&rgb, &[]) {
+          eprintln!("frame {}: could not record: {}", frame, err);
+          process::exit(1);
+        }
+      }
+    }
+
+    if frame >= options.frames {
+      println!("ran {} frames ({} CPU cycles)", frame, cpu.cycles());
+      if let Some(Err(err)) = cpu
+        .bus
+        .cartridge
+        .as_mut()
+        .map(|cartridge| cartridge.flush_save())
+      {
+        eprintln!("could not write the save file: {}", err);
+      }
+      if let Some(tracer) = &mut tracer {
+        tracer.flush();
+      }
+      finish_recording(recorder.take());
+      process::exit(0);
+    }
+  });
+
+  if let Some(tracer) = &mut tracer {
+    if let Err(err) = &result {
+      tracer.dump_ring_buffer(&err.to_string());
+    }
+    tracer.flush();
+  }
+  finish_recording(recorder);
+
+  match result {
+    Ok(()) => println!(
+      "stopped after {} frames ({} CPU cycles)",
+      frame,
+      cpu.cycles()
+    ),
+    Err(err) => {
+      eprintln!("{} (frame {}, {} CPU cycles)", err, frame, cpu.cycles());
+      process::exit(1);
+    }
+  }
+}
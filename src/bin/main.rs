@@ -1,85 +1,126 @@
-use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::capture::Recorder;
+use nes_emulator_rust::cpu::StepOutcome;
+use nes_emulator_rust::easy6502::{Easy6502Machine, SCREEN_HEIGHT, SCREEN_WIDTH};
 use nes_emulator_rust::games;
-use rand::Rng;
+use nes_emulator_rust::region::{Region, RegionSetting};
+use nes_emulator_rust::screenshot::save_png;
+use nes_emulator_rust::script::ScriptHost;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::render::{Canvas};
 use sdl2::video::Window;
 use sdl2::{EventPump};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-fn color(byte: u8) -> Color {
-  match byte {
-    0 => Color::BLACK,
-    1 => Color::WHITE,
-    2 | 9 => Color::GREY,
-    3 | 10 => Color::RED,
-    4 | 11 => Color::GREEN,
-    5 | 12 => Color::BLUE,
-    6 | 13 => Color::MAGENTA,
-    7 | 14 => Color::YELLOW,
-    _ => Color::CYAN,
+const USAGE: &str =
+  "usage: main [--cheat CODE]... [--script FILE] [--region ntsc|pal|dendy|auto]";
+
+/// Screenshots are the size of the window.
+const SCREENSHOT_SCALE: usize = 10;
+
+/// Saves the screen (as shown, with the script overlay) to the first free `screenshot-N.png` of
+/// the current directory.
+fn save_screenshot(frame: &[u8]) {
+  let path = (1..)
+    .map(|n| format!("screenshot-{}.png", n))
+    .find(|path| !Path::new(path).exists())
+    .unwrap();
+  match save_png(&path, frame, SCREEN_WIDTH, SCREEN_HEIGHT, SCREENSHOT_SCALE) {
+    Ok(()) => println!("saved {}", path),
+    Err(err) => eprintln!("could not save the screenshot: {}", err),
   }
 }
 
-fn read_screen_state(cpu: &CPU, frame: &mut [u8; 32 * 3 * 32]) -> bool {
-  let mut frame_idx = 0;
-  let mut update = false;
-  for addr in 0x0200..0x0600 {
-    // get the byte of color at the address
-    let color_idx = cpu.mem_read_u16(addr);
-    // and translates it into a Color
-    let (b1, b2, b3) = color(color_idx as u8).rgb();
-
-    if frame[frame_idx] != b1 || frame[frame_idx + 1] != b2 || frame[frame_idx + 2] != b3 {
-      frame[frame_idx] = b1;
-      frame[frame_idx + 1] = b2;
-      frame[frame_idx + 2] = b3;
-      update = true;
+/// Starts recording to the first free `capture-N.y4m`/`.wav` of the current directory, or stops
+/// the current recording.
+fn toggle_recording(recorder: &mut Option<Recorder>, region: Region) {
+  if let Some(recording) = recorder.take() {
+    let frames = recording.frames();
+    match recording.finish() {
+      Ok(()) => println!("recorded {} frames", frames),
+      Err(err) => eprintln!("could not finish the recording: {}", err),
     }
-
-    frame_idx += 3;
+    return;
   }
 
-  update
+  let path = (1..)
+    .map(|n| format!("capture-{}", n))
+    .find(|path| !Path::new(path).with_extension("y4m").exists())
+    .unwrap();
+  match Recorder::create(&path, SCREEN_WIDTH, SCREEN_HEIGHT, region.frame_rate()) {
+    Ok(recording) => {
+      println!("recording to {}.y4m and {}.wav", path, path);
+      *recorder = Some(recording);
+    }
+    Err(err) => eprintln!("could not start recording: {}", err),
+  }
 }
 
-/// This function will run at every loop routine (after every instruction) and will expect some key press event
-/// from the user.
+/// This function will run at every frame and will expect some key press event from the user.
 /// It writes to the 0xFF memory (which is where we're gathering our user inputs) the
-/// ASCII code of the key (WASD) pressed.
-fn handle_user_input(cpu: &mut CPU, event_pump: &mut EventPump) {
+/// ASCII code of the key (WASD) pressed. C turns the cheats on and off, F12 takes a
+/// screenshot and F9 starts or stops recording.
+fn handle_user_input(
+  machine: &mut Easy6502Machine,
+  frame: &[u8],
+  recorder: &mut Option<Recorder>,
+  region: Region,
+  event_pump: &mut EventPump,
+) {
   for event in event_pump.poll_iter() {
     match event {
       Event::Quit { .. }
       | Event::KeyDown {
         keycode: Some(Keycode::Escape),
         ..
-      } => std::process::exit(0),
+      } => {
+        if recorder.is_some() {
+          toggle_recording(recorder, region);
+        }
+        std::process::exit(0)
+      }
       Event::KeyDown {
         keycode: Some(Keycode::W),
         ..
       } => {
-        cpu.mem_write(0xFF, 0x77);
+        machine.press_key(b'w');
       }
       Event::KeyDown {
         keycode: Some(Keycode::S),
         ..
       } => {
-        cpu.mem_write(0xFF, 0x73);
+        machine.press_key(b's');
       }
       Event::KeyDown {
         keycode: Some(Keycode::A),
         ..
       } => {
-        cpu.mem_write(0xFF, 0x61);
+        machine.press_key(b'a');
       }
       Event::KeyDown {
         keycode: Some(Keycode::D),
         ..
       } => {
-        cpu.mem_write(0xFF, 0x64);
+        machine.press_key(b'd');
       }
+      Event::KeyDown {
+        keycode: Some(Keycode::C),
+        ..
+      } => {
+        let cheats = &mut machine.cpu.bus.cheats;
+        let enabled = !cheats.cheats.iter().any(|cheat| cheat.enabled);
+        cheats.set_all_enabled(enabled);
+      }
+      Event::KeyDown {
+        keycode: Some(Keycode::F12),
+        ..
+      } => save_screenshot(frame),
+      Event::KeyDown {
+        keycode: Some(Keycode::F9),
+        ..
+      } => toggle_recording(recorder, region),
       _ => { /* do nothing */ }
     }
   }
@@ -94,7 +135,7 @@ fn init_sdl2() -> (Canvas<Window>, EventPump) {
     .build()
     .unwrap();
 
-  let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+  let mut canvas = window.into_canvas().build().unwrap();
   let event_pump = sdl_context.event_pump().unwrap();
   canvas.set_scale(10.0, 10.0).unwrap();
 
@@ -102,32 +143,106 @@ fn init_sdl2() -> (Canvas<Window>, EventPump) {
 }
 
 fn main() {
+  let mut machine = Easy6502Machine::new();
+  let mut script_path = None;
+  let mut region_setting = RegionSetting::Auto;
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let mut value = || {
+      args.next().unwrap_or_else(|| {
+        eprintln!("{} needs a value\n{}", arg, USAGE);
+        std::process::exit(2);
+      })
+    };
+    match arg.as_str() {
+      "--cheat" => {
+        if let Err(err) = machine.cpu.bus.cheats.add(&value()) {
+          eprintln!("{}", err);
+          std::process::exit(2);
+        }
+      }
+      "--script" => script_path = Some(value()),
+      "--region" => {
+        region_setting = RegionSetting::parse(&value()).unwrap_or_else(|err| {
+          eprintln!("{}", err);
+          std::process::exit(2);
+        })
+      }
+      _ => {
+        eprintln!("unexpected argument {:?}\n{}", arg, USAGE);
+        std::process::exit(2);
+      }
+    }
+  }
+
+  // the program has no header to detect the region from
+  let region = region_setting.resolve(None);
+  let frame_duration = Duration::from_secs_f64(1.0 / region.frames_per_second());
+
   let (mut canvas, mut event_pump) = init_sdl2();
 
   let creator = canvas.texture_creator();
   let mut texture = creator
-    .create_texture_target(PixelFormatEnum::RGB24, 32, 32)
+    .create_texture_target(
+      PixelFormatEnum::RGB24,
+      SCREEN_WIDTH as u32,
+      SCREEN_HEIGHT as u32,
+    )
     .unwrap();
 
-  let mut screen_state = [0u8; 32 * 3 * 32];
-  let mut rng = rand::thread_rng();
-
-  let mut cpu = CPU::new();
-  let snake_game_code: &Vec<u8> = &(*games::snake::SNAKE_GAME_CODE);
+  let snake_game_code: &Vec<u8> = &games::snake::SNAKE_GAME_CODE;
   // let snake_game_code: &Vec<u8> = &(*games::example::SNAKE_GAME_CODE); // example
-  cpu.load(snake_game_code.to_vec());
-  cpu.reset();
-  cpu.run_with_callback(move |cpu| {
-    handle_user_input(cpu, &mut event_pump);
-    cpu.mem_write(0xFE, rng.gen_range(1..16)); // writes random value to the byte responsible for randomic things
-
-    if read_screen_state(cpu, &mut screen_state) { // if something changed since last iteration
-      texture.update(None, &screen_state, 32 * 3).unwrap();
-      canvas.copy(&texture, None, None).unwrap();
-      canvas.present();
+  machine.load(snake_game_code).unwrap();
+  let mut script = script_path.map(|path| {
+    ScriptHost::load(path, &mut machine.cpu).unwrap_or_else(|err| {
+      eprintln!("{}", err);
+      std::process::exit(1);
+    })
+  });
+  let mut recorder = None;
+  let mut frame = machine.frame().to_vec();
+  loop {
+    let start = Instant::now();
+    handle_user_input(&mut machine, &frame, &mut recorder, region, &mut event_pump);
+    let outcome = machine.run_frame_with_callback(|cpu| {
+      if let Some(Err(err)) = script.as_mut().map(|script| script.before_instruction(cpu)) {
+        eprintln!("script: {}", err);
+        std::process::exit(1);
+      }
+    });
+    match outcome {
+      Ok(StepOutcome::Executed) => {}
+      Ok(_) => break,
+      Err(err) => {
+        eprintln!("{}", err);
+        std::process::exit(1);
+      }
     }
 
-    ::std::thread::sleep(std::time::Duration::new(0, 70_000));
+    frame.copy_from_slice(machine.frame());
+    if let Some(script) = &mut script {
+      if let Err(err) = script.end_frame(&mut machine.cpu) {
+        eprintln!("script: {}", err);
+        std::process::exit(1);
+      }
+      // the frame callbacks may have drawn on the screen memory too
+      machine.update_frame();
+      frame.copy_from_slice(machine.frame());
+      script.draw_overlay(&mut frame, SCREEN_WIDTH, SCREEN_HEIGHT);
+    }
+    if let Some(recording) = &mut recorder {
+      if let Err(err) = recording.record_frame(&frame, &[]) {
+        eprintln!("could not record: {}", err);
+        toggle_recording(&mut recorder, region);
+      }
+    }
 
-  });
+    texture.update(None, &frame, SCREEN_WIDTH * 3).unwrap();
+    canvas.copy(&texture, None, None).unwrap();
+    canvas.present();
+    std::thread::sleep(frame_duration.saturating_sub(start.elapsed()));
+  }
+  if recorder.is_some() {
+    toggle_recording(&mut recorder, region);
+  }
 }
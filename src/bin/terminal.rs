@@ -0,0 +1,140 @@
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use nes_emulator_rust::cpu::StepOutcome;
+use nes_emulator_rust::easy6502::{Easy6502Machine, SCREEN_HEIGHT, SCREEN_WIDTH};
+use nes_emulator_rust::games;
+use nes_emulator_rust::terminal::{half_block_lines, register_lines};
+
+const USAGE: &str = "usage: terminal [program.bin] [--cheat CODE]...";
+const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+/// Columns between the screen and the register panel.
+const PANEL_GAP: u16 = 2;
+
+/// Reads the keys pressed since the last frame, passing them to the program. Returns false when
+/// the user quits (Esc or Ctrl-C).
+fn handle_user_input(machine: &mut Easy6502Machine) -> io::Result<bool> {
+  while event::poll(Duration::ZERO)? {
+    let Event::Key(key) = event::read()? else {
+      continue;
+    };
+    if key.kind == KeyEventKind::Release {
+      continue;
+    }
+    match key.code {
+      KeyCode::Esc => return Ok(false),
+      KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(false),
+      KeyCode::Char(c) if c.is_ascii() => machine.press_key(c as u8),
+      _ => {}
+    }
+  }
+  Ok(true)
+}
+
+/// Blocks until a key is pressed.
+fn wait_for_key() -> io::Result<()> {
+  loop {
+    if let Event::Key(key) = event::read()? {
+      if key.kind != KeyEventKind::Release {
+        return Ok(());
+      }
+    }
+  }
+}
+
+/// Draws the screen and the registers, with `status` under the registers.
+fn draw(out: &mut impl Write, machine: &Easy6502Machine, status: &str) -> io::Result<()> {
+  let screen = half_block_lines(machine.frame(), SCREEN_WIDTH, SCREEN_HEIGHT);
+  for (row, line) in screen.iter().enumerate() {
+    queue!(out, MoveTo(0, row as u16), Print(line))?;
+  }
+  let column = SCREEN_WIDTH as u16 + PANEL_GAP;
+  for (row, line) in register_lines(&machine.cpu).iter().enumerate() {
+    queue!(
+      out,
+      MoveTo(column, row as u16),
+      Print(format!("{:<20}", line))
+    )?;
+  }
+  queue!(
+    out,
+    MoveTo(column, screen.len() as u16 - 1),
+    Print(format!("{:<32}", status))
+  )?;
+  out.flush()
+}
+
+/// Runs the program until the user quits, or until it stops and the user pressed a key to look
+/// at its last screen.
+fn run(out: &mut impl Write, machine: &mut Easy6502Machine) -> Result<(), String> {
+  loop {
+    let start = Instant::now();
+    if !handle_user_input(machine).map_err(|err| err.to_string())? {
+      return Ok(());
+    }
+    let outcome = machine.run_frame().map_err(|err| err.to_string())?;
+    if outcome != StepOutcome::Executed {
+      draw(out, machine, "program finished, press a key").map_err(|err| err.to_string())?;
+      return wait_for_key().map_err(|err| err.to_string());
+    }
+    draw(out, machine, "Esc to quit").map_err(|err| err.to_string())?;
+    std::thread::sleep(FRAME_DURATION.saturating_sub(start.elapsed()));
+  }
+}
+
+/// Plays an easy6502 program (the snake game by default) in the terminal, for machines without
+/// a display.
+fn main() {
+  let mut path = None;
+  let mut cheats = Vec::new();
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--cheat" => cheats.extend(args.next()),
+      _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+      _ => {
+        eprintln!("unexpected argument {:?}\n{}", arg, USAGE);
+        process::exit(2);
+      }
+    }
+  }
+  let program = match &path {
+    Some(path) => std::fs::read(path).unwrap_or_else(|err| {
+      eprintln!("{}: {}", path, err);
+      process::exit(1);
+    }),
+    None => games::snake::SNAKE_GAME_CODE.to_vec(),
+  };
+
+  let mut machine = Easy6502Machine::new();
+  for code in &cheats {
+    if let Err(err) = machine.cpu.bus.cheats.add(code) {
+      eprintln!("{}", err);
+      process::exit(2);
+    }
+  }
+  if let Err(err) = machine.load(&program) {
+    eprintln!("{}", err);
+    process::exit(1);
+  }
+
+  let mut out = io::stdout();
+  let started = terminal::enable_raw_mode()
+    .and_then(|_| execute!(out, EnterAlternateScreen, Hide))
+    .map_err(|err| err.to_string());
+  let result = started.and_then(|_| run(&mut out, &mut machine));
+  // the terminal is put back even when the program failed
+  let _ = execute!(out, Show, LeaveAlternateScreen);
+  let _ = terminal::disable_raw_mode();
+
+  if let Err(err) = result {
+    eprintln!("{}", err);
+    process::exit(1);
+  }
+}
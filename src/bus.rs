@@ -0,0 +1,268 @@
+use crate::cartridge::{Cartridge, PRG_RAM_START};
+use crate::cheats::Cheats;
+use crate::joypad::Joypad;
+use crate::ppu::PPU;
+use std::path::Path;
+
+const MEMORY_SIZE: usize = 0x10000;
+const PPU_REGISTERS_START: u16 = 0x2000;
+const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
+const OAM_DMA_ADDR: u16 = 0x4014;
+const OAM_DMA_BYTES: u16 = 256;
+const JOYPAD_1_ADDR: u16 = 0x4016;
+/// How often (in frames) the battery-backed RAM of the cartridge is written to its save file.
+const SAVE_FLUSH_INTERVAL_FRAMES: u64 = 60;
+
+/// Everything the CPU can reach through its address lines.
+///
+/// Without any device attached the whole address space is plain RAM (which is what the easy6502
+/// programs expect). Attached devices take over their part of the NES memory map:
+/// - PPU: registers at $2000-$2007 (mirrored up to $3FFF), OAM DMA at $4014 and the joypad at
+///   $4016.
+/// - Cartridge: PRG RAM at $6000-$7FFF and PRG ROM at $8000-$FFFF.
+///
+/// Enabled cheats patch what the CPU reads, and the freezes are applied at the start of every
+/// frame.
+///
+/// Tools can watch addresses: the bus then records every read and write of them, for the tool to
+/// collect with `take_accesses`.
+///
+/// The bus also keeps the clock: it counts CPU cycles (some devices, like the DMA, depend on
+/// them) and runs the attached devices for the same amount of time.
+#[derive(Clone)]
+pub struct Bus {
+  memory: [u8; MEMORY_SIZE],
+  pub ppu: Option<PPU>,
+  pub cartridge: Option<Cartridge>,
+  pub cheats: Cheats,
+  pub joypad: Joypad,
+  cycles: u64,
+  ppu_master_clocks: u32,
+  oam_dma_page: Option<u8>,
+  watched: Vec<u16>,
+  accesses: Vec<MemoryAccess>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+  Read,
+  Write,
+}
+
+/// A read or write of a watched address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+  pub addr: u16,
+  /// The value read (with the cheats applied) or written.
+  pub value: u8,
+  pub kind: AccessKind,
+}
+
+impl Default for Bus {
+  fn default() -> Self {
+    Self {
+      memory: [0; MEMORY_SIZE],
+      ppu: None,
+      cartridge: None,
+      cheats: Cheats::new(),
+      joypad: Joypad::new(),
+      cycles: 0,
+      ppu_master_clocks: 0,
+      oam_dma_page: None,
+      watched: Vec::new(),
+      accesses: Vec::new(),
+    }
+  }
+}
+
+impl Bus {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Inserts a cartridge, connecting a PPU to its CHR memory and switching to its region.
+  pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+    let mut ppu = PPU::new_with_chr(cartridge.chr.clone(), cartridge.mirroring);
+    ppu.region = cartridge.region;
+    ppu.scanline = cartridge.region.pre_render_scanline();
+
+    self.ppu = Some(ppu);
+    self.cartridge = Some(cartridge);
+  }
+
+  pub fn mem_read(&mut self, addr: u16) -> u8 {
+    let data = match (addr, &mut self.ppu) {
+      (PPU_REGISTERS_START..=PPU_REGISTERS_MIRRORS_END, Some(ppu)) => match addr & 0b111 {
+        2 => ppu.read_status(),
+        4 => ppu.read_oam_data(),
+        7 => ppu.read_data(),
+        _ => 0, // write-only registers
+      },
+      (JOYPAD_1_ADDR, Some(_)) => self.joypad.read(),
+      _ => match (addr, &self.cartridge) {
+        (PRG_RAM_START..=0xFFFF, Some(cartridge)) => cartridge.read_prg(addr),
+        _ => self.memory[addr as usize],
+      },
+    };
+
+    let data = self.cheats.patch_read(addr, data);
+    self.record_access(addr, data, AccessKind::Read);
+    data
+  }
+
+  /// Reads memory without side effects, for debugging tools: device registers read as 0 and
+  /// cheats are not applied.
+  pub fn peek(&self, addr: u16) -> u8 {
+    match (addr, &self.ppu, &self.cartridge) {
+      (PPU_REGISTERS_START..=PPU_REGISTERS_MIRRORS_END, Some(_), _) => 0,
+      (JOYPAD_1_ADDR, Some(_), _) => 0,
+      (PRG_RAM_START..=0xFFFF, _, Some(cartridge)) => cartridge.read_prg(addr),
+      _ => self.memory[addr as usize],
+    }
+  }
+
+  pub fn mem_write(&mut self, addr: u16, data: u8) {
+    self.record_access(addr, data, AccessKind::Write);
+    match (addr, &mut self.ppu) {
+      (PPU_REGISTERS_START..=PPU_REGISTERS_MIRRORS_END, Some(ppu)) => match addr & 0b111 {
+        0 => ppu.write_to_ctrl(data),
+        1 => ppu.write_to_mask(data),
+        3 => ppu.write_to_oam_addr(data),
+        4 => ppu.write_to_oam_data(data),
+        5 => ppu.write_to_scroll(data),
+        6 => ppu.write_to_ppu_addr(data),
+        7 => ppu.write_to_data(data),
+        _ => {}
+      },
+      (OAM_DMA_ADDR, Some(_)) => self.oam_dma_page = Some(data),
+      (JOYPAD_1_ADDR, Some(_)) => self.joypad.write(data),
+      _ => match (addr, &mut self.cartridge) {
+        (PRG_RAM_START..=0xFFFF, Some(cartridge)) => cartridge.write_prg(addr, data),
+        _ => self.memory[addr as usize] = data,
+      },
+    }
+  }
+
+  /// Records the reads and writes of `addr` from now on.
+  pub fn watch(&mut self, addr: u16) {
+    if !self.watched.contains(&addr) {
+      self.watched.push(addr);
+    }
+  }
+
+  /// Stops recording accesses, dropping the ones not taken yet.
+  pub fn unwatch_all(&mut self) {
+    self.watched.clear();
+    self.accesses.clear();
+  }
+
+  /// Returns the accesses to the watched addresses since the last call, oldest first.
+  pub fn take_accesses(&mut self) -> Vec<MemoryAccess> {
+    std::mem::take(&mut self.accesses)
+  }
+
+  fn record_access(&mut self, addr: u16, value: u8, kind: AccessKind) {
+    if !self.watched.is_empty() && self.watched.contains(&addr) {
+      self.accesses.push(MemoryAccess { addr, value, kind });
+    }
+  }
+
+  /// Hands the tools attached to `current` (cheats and watched addresses) and the save file of
+  /// its cartridge over to this bus, which replaces it when a state is loaded. The save file gets
+  /// the PRG RAM of this bus on the next flush.
+  pub(crate) fn take_tools_from(&mut self, current: &mut Bus) {
+    self.cheats = std::mem::take(&mut current.cheats);
+    self.watched = std::mem::take(&mut current.watched);
+    self.accesses.clear();
+    if let (Some(cartridge), Some(current)) = (&mut self.cartridge, &mut current.cartridge) {
+      cartridge.set_save_path(current.save_path().map(Path::to_path_buf));
+      current.set_save_path(None);
+      cartridge.mark_save_dirty();
+    }
+  }
+
+  /// Copies `data` into RAM, starting at `start`.
+  pub fn load(&mut self, start: u16, data: &[u8]) {
+    self.memory[start as usize..(start as usize + data.len())].copy_from_slice(data);
+  }
+
+  /// Number of CPU cycles elapsed since power up.
+  pub fn cycles(&self) -> u64 {
+    self.cycles
+  }
+
+  /// Advances the clock by `cycles` CPU cycles.
+  ///
+  /// The PPU runs for as many dots as fit in the same number of master clocks: exactly 3 dots per
+  /// CPU cycle on NTSC and Dendy, 3.2 on PAL (the leftover master clocks are carried over).
+  /// Every `SAVE_FLUSH_INTERVAL_FRAMES` frames, the battery-backed RAM is flushed to disk.
+  pub fn tick(&mut self, cycles: u16) {
+    self.cycles += cycles as u64;
+    if let Some(ppu) = &mut self.ppu {
+      let region = ppu.region;
+      let frame_count = ppu.frame_count;
+      self.ppu_master_clocks += cycles as u32 * region.cpu_clock_divider();
+      let dots = self.ppu_master_clocks / region.ppu_clock_divider();
+      self.ppu_master_clocks %= region.ppu_clock_divider();
+      ppu.tick(dots as u16);
+
+      let new_frame_count = ppu.frame_count;
+      if new_frame_count != frame_count {
+        self.apply_freeze_cheats();
+      }
+
+      let flush_due =
+        new_frame_count / SAVE_FLUSH_INTERVAL_FRAMES != frame_count / SAVE_FLUSH_INTERVAL_FRAMES;
+      if let (true, Some(cartridge)) = (flush_due, &mut self.cartridge) {
+        if let Err(err) = cartridge.flush_save() {
+          eprintln!("could not write the save file: {}", err);
+        }
+      }
+    }
+  }
+
+  /// Writes the values of the enabled freeze cheats. The bus does it on every new frame; without
+  /// a PPU there are no frames, so it's up to the caller.
+  pub fn apply_freeze_cheats(&mut self) {
+    let freezes: Vec<(u16, u8)> = self.cheats.freezes().collect();
+    for (addr, data) in freezes {
+      self.mem_write(addr, data);
+    }
+  }
+
+  /// Returns (and acknowledges) a pending NMI.
+  pub fn poll_nmi(&mut self) -> bool {
+    match &mut self.ppu {
+      Some(ppu) => ppu.poll_nmi(),
+      None => false,
+    }
+  }
+
+  /// Runs the OAM DMA requested by the last write to $4014, if any.
+  ///
+  /// The CPU is halted while the 256 bytes of page $XX00-$XXFF are copied to OAM (through
+  /// OAMDATA, so the copy starts at the current OAMADDR). It takes 1 wait cycle, 1 more if the
+  /// DMA starts on an odd CPU cycle, and then 256 read/write pairs: 513 or 514 cycles.
+  /// The DMC DMA is not emulated (there is no APU yet), so it never steals cycles from this one.
+  ///
+  /// Returns how many cycles the CPU was stalled for.
+  pub fn run_pending_oam_dma(&mut self) -> u16 {
+    let page = match self.oam_dma_page.take() {
+      Some(page) => page,
+      None => return 0,
+    };
+
+    let alignment_cycles = if self.cycles % 2 == 1 { 2 } else { 1 };
+    let start = (page as u16) << 8;
+    for offset in 0..OAM_DMA_BYTES {
+      let data = self.mem_read(start + offset);
+      if let Some(ppu) = &mut self.ppu {
+        ppu.write_to_oam_data(data);
+      }
+    }
+
+    let stall_cycles = alignment_cycles + OAM_DMA_BYTES * 2;
+    self.tick(stall_cycles);
+    stall_cycles
+  }
+}
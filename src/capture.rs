@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Sample rate of the recorded audio.
+pub const AUDIO_SAMPLE_RATE: u32 = 44_100;
+const WAV_HEADER_SIZE: u32 = 44;
+
+/// Writes frames to a YUV4MPEG2 (`.y4m`) video: uncompressed 4:4:4 frames (no chroma
+/// subsampling) that players and encoders like ffmpeg read directly.
+pub struct Y4mWriter<W: Write> {
+  out: W,
+  width: usize,
+  height: usize,
+}
+
+impl<W: Write> Y4mWriter<W> {
+  /// Writes the header. The frame rate is a fraction (numerator, denominator).
+  pub fn new(mut out: W, width: usize, height: usize, frame_rate: (u64, u64)) -> io::Result<Self> {
+    writeln!(
+      out,
+      "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444",
+      width, height, frame_rate.0, frame_rate.1
+    )?;
+    Ok(Y4mWriter { out, width, height })
+  }
+
+  /// Adds an RGB24 frame, converted to BT.601 YCbCr.
+  pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+    if rgb.len() != self.width * self.height * 3 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+          "the frame is {} bytes, not {}",
+          rgb.len(),
+          self.width * self.height * 3
+        ),
+      ));
+    }
+
+    let mut planes: [Vec<u8>; 3] = std::array::from_fn(|_| Vec::with_capacity(rgb.len() / 3));
+    for pixel in rgb.chunks_exact(3) {
+      let (r, g, b) = (pixel[0] as i32, pixel[1] as i32, pixel[2] as i32);
+      planes[0].push((((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8);
+      planes[1].push((((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8);
+      planes[2].push((((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8);
+    }
+
+    self.out.write_all(b"FRAME\n")?;
+    for plane in planes {
+      self.out.write_all(&plane)?;
+    }
+    Ok(())
+  }
+
+  pub fn finish(mut self) -> io::Result<W> {
+    self.out.flush()?;
+    Ok(self.out)
+  }
+}
+
+/// Writes 16-bit mono samples to a `.wav` file. The sizes in the header are filled in by `finish`.
+pub struct WavWriter<W: Write + Seek> {
+  out: W,
+  data_size: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+  pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+    out.write_all(b"RIFF")?;
+    out.write_all(&0u32.to_le_bytes())?; // size of the rest of the file
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&1u16.to_le_bytes())?; // mono
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * 2).to_le_bytes())?; // bytes per second
+    out.write_all(&2u16.to_le_bytes())?; // bytes per sample
+    out.write_all(&16u16.to_le_bytes())?; // bits per sample
+    out.write_all(b"data")?;
+    out.write_all(&0u32.to_le_bytes())?; // size of the samples
+    Ok(WavWriter { out, data_size: 0 })
+  }
+
+  pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+    for sample in samples {
+      self.out.write_all(&sample.to_le_bytes())?;
+    }
+    self.data_size += samples.len() as u32 * 2;
+    Ok(())
+  }
+
+  /// Fills in the sizes in the header.
+  pub fn finish(mut self) -> io::Result<W> {
+    self.out.seek(SeekFrom::Start(4))?;
+    self
+      .out
+      .write_all(&(WAV_HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+    self.out.seek(SeekFrom::Start(WAV_HEADER_SIZE as u64 - 4))?;
+    self.out.write_all(&self.data_size.to_le_bytes())?;
+    self.out.seek(SeekFrom::End(0))?;
+    self.out.flush()?;
+    Ok(self.out)
+  }
+}
+
+/// Records gameplay to `<path>.y4m` (video) and `<path>.wav` (audio), with the audio kept exactly
+/// as long as the frames recorded so far.
+pub struct Recorder {
+  video: Y4mWriter<BufWriter<File>>,
+  audio: WavWriter<BufWriter<File>>,
+  frame_rate: (u64, u64),
+  frames: u64,
+  samples: u64,
+}
+
+impl Recorder {
+  /// Starts a recording of `width` x `height` frames at `frame_rate` (numerator, denominator)
+  /// frames per second.
+  pub fn create(
+    path: impl AsRef<Path>,
+    width: usize,
+    height: usize,
+    frame_rate: (u64, u64),
+  ) -> io::Result<Self> {
+    let path = path.as_ref();
+    let video = BufWriter::new(File::create(path.with_extension("y4m"))?);
+    let audio = BufWriter::new(File::create(path.with_extension("wav"))?);
+    Ok(Recorder {
+      video: Y4mWriter::new(video, width, height, frame_rate)?,
+      audio: WavWriter::new(audio, AUDIO_SAMPLE_RATE)?,
+      frame_rate,
+      frames: 0,
+      samples: 0,
+    })
+  }
+
+  /// Adds an RGB24 frame and the audio played during it. The audio is padded with silence to the
+  /// length of the frame (there is no APU yet, so the frontends give none).
+  pub fn record_frame(&mut self, rgb: &[u8], samples: &[i16]) -> io::Result<()> {
+    self.video.write_frame(rgb)?;
+    self.audio.write_samples(samples)?;
+    self.frames += 1;
+    self.samples += samples.len() as u64;
+
+    let (numerator, denominator) = self.frame_rate;
+    let expected = self.frames * AUDIO_SAMPLE_RATE as u64 * denominator / numerator;
+    if self.samples < expected {
+      let silence = vec![0; (expected - self.samples) as usize];
+      self.audio.write_samples(&silence)?;
+      self.samples = expected;
+    }
+    Ok(())
+  }
+
+  /// Number of frames recorded.
+  pub fn frames(&self) -> u64 {
+    self.frames
+  }
+
+  /// Ends the recording, completing both files.
+  pub fn finish(self) -> io::Result<()> {
+    self.video.finish()?;
+    self.audio.finish()?;
+    Ok(())
+  }
+}
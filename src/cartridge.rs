@@ -0,0 +1,209 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ppu::Mirroring;
+use crate::region::{Region, RegionSetting};
+
+const NES_TAG: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+const PRG_ROM_PAGE_SIZE: usize = 0x4000;
+const CHR_ROM_PAGE_SIZE: usize = 0x2000;
+const CHR_RAM_SIZE: usize = 0x2000;
+pub const PRG_RAM_SIZE: usize = 0x2000;
+pub const PRG_RAM_START: u16 = 0x6000;
+pub const PRG_ROM_START: u16 = 0x8000;
+
+/// A game cartridge, parsed from an iNES file.
+///
+/// Only NROM (mapper 0) is supported: 16 or 32KiB of PRG ROM at $8000-$FFFF (16KiB ones are
+/// mirrored) and 8KiB of PRG RAM at $6000-$7FFF.
+///
+/// When the header has the battery flag, the PRG RAM is what the game saves its progress in.
+/// It is kept in a `.sav` file next to the ROM: read when the cartridge is loaded and written
+/// back by `flush_save` (the bus calls it periodically) and when the cartridge is dropped.
+/// As only mapper 0 loads, saves only work for NROM images: nearly every game that saves uses a
+/// mapper (MMC1 for The Legend of Zelda or Final Fantasy), and `from_ines` rejects it.
+pub struct Cartridge {
+  pub prg_rom: Vec<u8>,
+  /// CHR ROM, or 8KiB of CHR RAM when the cartridge has none.
+  pub chr: Vec<u8>,
+  pub mapper: u8,
+  pub mirroring: Mirroring,
+  pub region: Region,
+  pub has_battery: bool,
+  prg_ram: [u8; PRG_RAM_SIZE],
+  prg_ram_dirty: bool,
+  save_path: Option<PathBuf>,
+}
+
+impl Cartridge {
+  /// Parses the content of an iNES (or NES 2.0) file, taking the region from its header.
+  pub fn from_ines(bytes: &[u8]) -> Result<Self, String> {
+    Self::from_ines_with_region(bytes, RegionSetting::Auto)
+  }
+
+  /// Same as `from_ines`, with the region chosen by `region` (a manual one overrides the header).
+  pub fn from_ines_with_region(bytes: &[u8], region: RegionSetting) -> Result<Self, String> {
+    if bytes.len() < HEADER_SIZE || bytes[0..4] != NES_TAG {
+      return Err("not an iNES file".to_string());
+    }
+
+    let mapper = (bytes[7] & 0b1111_0000) | (bytes[6] >> 4);
+    if mapper != 0 {
+      return Err(format!("mapper {} is not supported", mapper));
+    }
+
+    let mirroring = match (bytes[6] & 0b1000 != 0, bytes[6] & 0b1 != 0) {
+      (true, _) => Mirroring::FourScreen,
+      (false, true) => Mirroring::Vertical,
+      (false, false) => Mirroring::Horizontal,
+    };
+    let has_battery = bytes[6] & 0b10 != 0;
+    let has_trainer = bytes[6] & 0b100 != 0;
+
+    let prg_rom_size = bytes[4] as usize * PRG_ROM_PAGE_SIZE;
+    let chr_rom_size = bytes[5] as usize * CHR_ROM_PAGE_SIZE;
+    let prg_rom_start = HEADER_SIZE + if has_trainer { TRAINER_SIZE } else { 0 };
+    let chr_rom_start = prg_rom_start + prg_rom_size;
+    if prg_rom_size == 0 || bytes.len() < chr_rom_start + chr_rom_size {
+      return Err("the file is smaller than what its header says".to_string());
+    }
+
+    let chr = if chr_rom_size == 0 {
+      vec![0; CHR_RAM_SIZE]
+    } else {
+      bytes[chr_rom_start..chr_rom_start + chr_rom_size].to_vec()
+    };
+
+    Ok(Self {
+      prg_rom: bytes[prg_rom_start..chr_rom_start].to_vec(),
+      chr,
+      mapper,
+      mirroring,
+      region: region.resolve(Some(bytes)),
+      has_battery,
+      prg_ram: [0; PRG_RAM_SIZE],
+      prg_ram_dirty: false,
+      save_path: None,
+    })
+  }
+
+  /// Loads a ROM file, and the `<rom>.sav` file next to it if the cartridge has a battery.
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+    Self::load_with_region(path, RegionSetting::Auto)
+  }
+
+  /// Same as `load`, with the region chosen by `region`.
+  pub fn load_with_region(path: impl AsRef<Path>, region: RegionSetting) -> Result<Self, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut cartridge = Self::from_ines_with_region(&bytes, region)?;
+
+    if cartridge.has_battery {
+      let save_path = path.with_extension("sav");
+      cartridge
+        .load_save(&save_path)
+        .map_err(|err| format!("{}: {}", save_path.display(), err))?;
+      cartridge.save_path = Some(save_path);
+    }
+
+    Ok(cartridge)
+  }
+
+  /// Reads a save file into PRG RAM. A missing file leaves the RAM as it is (a new game).
+  pub fn load_save(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+    match fs::read(path) {
+      Ok(save) => {
+        let len = save.len().min(PRG_RAM_SIZE);
+        self.prg_ram[..len].copy_from_slice(&save[..len]);
+        self.prg_ram_dirty = false;
+        Ok(())
+      }
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+      Err(err) => Err(err),
+    }
+  }
+
+  /// Where the battery-backed RAM is saved, if anywhere.
+  pub fn save_path(&self) -> Option<&Path> {
+    self.save_path.as_deref()
+  }
+
+  pub fn set_save_path(&mut self, path: Option<PathBuf>) {
+    self.save_path = path;
+  }
+
+  /// Writes the battery-backed RAM to the save file if it changed since the last flush.
+  /// Returns whether the file was written.
+  pub fn flush_save(&mut self) -> io::Result<bool> {
+    let path = match &self.save_path {
+      Some(path) if self.has_battery && self.prg_ram_dirty => path,
+      _ => return Ok(false),
+    };
+
+    fs::write(path, self.prg_ram)?;
+    self.prg_ram_dirty = false;
+    Ok(true)
+  }
+
+  pub fn prg_ram(&self) -> &[u8; PRG_RAM_SIZE] {
+    &self.prg_ram
+  }
+
+  /// Mutable access to PRG RAM, for tools that edit saves. The RAM is flushed on the next
+  /// `flush_save`.
+  pub fn prg_ram_mut(&mut self) -> &mut [u8; PRG_RAM_SIZE] {
+    self.prg_ram_dirty = true;
+    &mut self.prg_ram
+  }
+
+  /// Makes the next `flush_save` write the PRG RAM even if it didn't change.
+  pub fn mark_save_dirty(&mut self) {
+    self.prg_ram_dirty = true;
+  }
+
+  /// Reads $6000-$FFFF.
+  pub fn read_prg(&self, addr: u16) -> u8 {
+    match addr {
+      PRG_RAM_START..=0x7FFF => self.prg_ram[(addr - PRG_RAM_START) as usize],
+      PRG_ROM_START..=0xFFFF => self.prg_rom[(addr - PRG_ROM_START) as usize % self.prg_rom.len()],
+      _ => 0,
+    }
+  }
+
+  /// Writes $6000-$FFFF. Writes to ROM are ignored.
+  pub fn write_prg(&mut self, addr: u16, data: u8) {
+    if let PRG_RAM_START..=0x7FFF = addr {
+      let ram = &mut self.prg_ram[(addr - PRG_RAM_START) as usize];
+      self.prg_ram_dirty |= *ram != data;
+      *ram = data;
+    }
+  }
+}
+
+/// The copy has no save file (see `set_save_path`): only one cartridge should write to it.
+impl Clone for Cartridge {
+  fn clone(&self) -> Self {
+    Self {
+      prg_rom: self.prg_rom.clone(),
+      chr: self.chr.clone(),
+      mapper: self.mapper,
+      mirroring: self.mirroring,
+      region: self.region,
+      has_battery: self.has_battery,
+      prg_ram: self.prg_ram,
+      prg_ram_dirty: self.prg_ram_dirty,
+      save_path: None,
+    }
+  }
+}
+
+impl Drop for Cartridge {
+  fn drop(&mut self) {
+    if let Err(err) = self.flush_save() {
+      eprintln!("could not write the save file: {}", err);
+    }
+  }
+}
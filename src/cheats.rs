@@ -0,0 +1,174 @@
+/// Letters of the Game Genie alphabet, in the order of the nibble they encode.
+const GAME_GENIE_LETTERS: &str = "APZLGITYEOXUKSVN";
+/// Addresses below this one are RAM (or registers), above it is the cartridge ROM.
+const ROM_START: u16 = 0x8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+  /// Replaces the value the CPU reads from `address` (only when the original value equals
+  /// `compare`, if there is one). That's how the Game Genie patches ROM.
+  ReadPatch,
+  /// Writes the value to `address` every frame, so the game can't change it for long.
+  Freeze,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+  /// The code the cheat was parsed from.
+  pub code: String,
+  pub kind: CheatKind,
+  pub address: u16,
+  pub value: u8,
+  pub compare: Option<u8>,
+  pub enabled: bool,
+}
+
+impl Cheat {
+  /// Parses a cheat code:
+  /// - a 6 or 8-letter Game Genie code (`SXIOPO`, `ZEXPYGLA`);
+  /// - `ADDR:VAL`, in hex: a freeze for RAM addresses, a read patch for ROM addresses;
+  /// - `ADDR?CMP:VAL`, in hex: a read patch with a compare value.
+  pub fn parse(code: &str) -> Result<Self, String> {
+    let code = code.trim();
+    let (kind, address, value, compare) = if code.contains(':') {
+      Self::decode_raw(code)?
+    } else {
+      let (address, value, compare) = decode_game_genie(code)?;
+      (CheatKind::ReadPatch, address, value, compare)
+    };
+
+    Ok(Self {
+      code: code.to_uppercase(),
+      kind,
+      address,
+      value,
+      compare,
+      enabled: true,
+    })
+  }
+
+  /// A cheat that keeps `address` at `value`.
+  pub fn freeze(address: u16, value: u8) -> Self {
+    Self {
+      code: format!("{:04X}:{:02X}", address, value),
+      kind: CheatKind::Freeze,
+      address,
+      value,
+      compare: None,
+      enabled: true,
+    }
+  }
+
+  fn decode_raw(code: &str) -> Result<(CheatKind, u16, u8, Option<u8>), String> {
+    let invalid = || format!("invalid cheat code {:?}", code);
+    let (target, value) = code.split_once(':').ok_or_else(invalid)?;
+    let (address, compare) = match target.split_once('?') {
+      Some((address, compare)) => (address, Some(compare)),
+      None => (target, None),
+    };
+
+    let address = u16::from_str_radix(address, 16).map_err(|_| invalid())?;
+    let value = u8::from_str_radix(value, 16).map_err(|_| invalid())?;
+    let compare = match compare {
+      Some(compare) => Some(u8::from_str_radix(compare, 16).map_err(|_| invalid())?),
+      None => None,
+    };
+    let kind = if address < ROM_START && compare.is_none() {
+      CheatKind::Freeze
+    } else {
+      CheatKind::ReadPatch
+    };
+
+    Ok((kind, address, value, compare))
+  }
+}
+
+/// Decodes a Game Genie code into its address, value and (8-letter codes only) compare value.
+/// See <https://www.nesdev.org/wiki/Game_Genie> for more info.
+pub fn decode_game_genie(code: &str) -> Result<(u16, u8, Option<u8>), String> {
+  let n = code
+    .chars()
+    .map(|letter| {
+      GAME_GENIE_LETTERS
+        .find(letter.to_ascii_uppercase())
+        .map(|nibble| nibble as u16)
+    })
+    .collect::<Option<Vec<u16>>>()
+    .filter(|n| n.len() == 6 || n.len() == 8)
+    .ok_or_else(|| format!("invalid Game Genie code {:?}", code))?;
+
+  let address = ROM_START
+    | ((n[3] & 7) << 12)
+    | ((n[4] & 8) << 8)
+    | ((n[5] & 7) << 8)
+    | ((n[1] & 8) << 4)
+    | ((n[2] & 7) << 4)
+    | (n[3] & 8)
+    | (n[4] & 7);
+
+  if n.len() == 6 {
+    let value = ((n[0] & 8) << 4) | ((n[1] & 7) << 4) | (n[5] & 8) | (n[0] & 7);
+    Ok((address, value as u8, None))
+  } else {
+    let value = ((n[0] & 8) << 4) | ((n[1] & 7) << 4) | (n[7] & 8) | (n[0] & 7);
+    let compare = ((n[6] & 8) << 4) | ((n[7] & 7) << 4) | (n[5] & 8) | (n[6] & 7);
+    Ok((address, value as u8, Some(compare as u8)))
+  }
+}
+
+/// The cheats applied by the bus: read patches on every CPU read, freezes once per frame.
+#[derive(Debug, Default, Clone)]
+pub struct Cheats {
+  pub cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Parses and enables a cheat code. Returns its index.
+  pub fn add(&mut self, code: &str) -> Result<usize, String> {
+    self.cheats.push(Cheat::parse(code)?);
+    Ok(self.cheats.len() - 1)
+  }
+
+  pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+    (index < self.cheats.len()).then(|| self.cheats.remove(index))
+  }
+
+  /// Enables or disables a cheat. Returns whether it is now enabled.
+  pub fn toggle(&mut self, index: usize) -> Option<bool> {
+    let cheat = self.cheats.get_mut(index)?;
+    cheat.enabled = !cheat.enabled;
+    Some(cheat.enabled)
+  }
+
+  /// Enables or disables every cheat at once.
+  pub fn set_all_enabled(&mut self, enabled: bool) {
+    for cheat in &mut self.cheats {
+      cheat.enabled = enabled;
+    }
+  }
+
+  /// The value the CPU sees when reading `value` from `address`.
+  pub fn patch_read(&self, address: u16, value: u8) -> u8 {
+    self
+      .cheats
+      .iter()
+      .filter(|cheat| {
+        cheat.enabled && cheat.kind == CheatKind::ReadPatch && cheat.address == address
+      })
+      .find(|cheat| cheat.compare.is_none_or(|compare| compare == value))
+      .map_or(value, |cheat| cheat.value)
+  }
+
+  /// The enabled freezes, as (address, value) pairs.
+  pub fn freezes(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+    self
+      .cheats
+      .iter()
+      .filter(|cheat| cheat.enabled && cheat.kind == CheatKind::Freeze)
+      .map(|cheat| (cheat.address, cheat.value))
+  }
+}
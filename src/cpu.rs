@@ -1,15 +1,29 @@
-use core::panic;
-use std::collections::HashMap;
+use std::fmt;
 
+use crate::bus::Bus;
+use crate::cycle_cpu::CycleCpu;
+use crate::loader::Program;
 use crate::opcodes;
 
-const MEMORY_SIZE: u16 = 0xFFFF;
 const PROGRAM_ROM_MEMORY_ADDRESS_START: u16 = 0x0600;
+const NMI_INTERRUPT_ADDR: u16 = 0xFFFA;
 const RESET_INTERRUPT_ADDR: u16 = 0xFFFC;
+const IRQ_INTERRUPT_ADDR: u16 = 0xFFFE;
+const STACK_PAGE: u16 = 0x0100;
 const STACK_STARTING_POINTER: u8 = 0xFF;
+const BREAK_FLAG: u8 = 0b0001_0000;
+const UNUSED_FLAG: u8 = 0b0010_0000;
+const INTERRUPT_DISABLE_FLAG: u8 = 0b0000_0100;
+const JSR_OPCODE: u8 = 0x20;
+/// The reset vector sits at the end of memory, so programs must end before it.
+const MAX_PROGRAM_SIZE: usize = (RESET_INTERRUPT_ADDR - PROGRAM_ROM_MEMORY_ADDRESS_START) as usize;
+/// Opcodes that lock up the NMOS 6502 (also known as KIL or HLT).
+const JAM_OPCODES: [u8; 12] = [
+  0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+];
 
 /// See `studies/addressing.asm` for more info.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressingMode {
   Accumulator,
   Relative,
@@ -31,6 +45,106 @@ enum MathematicalOperation {
   Sub,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+  /// The byte at `pc` is not an opcode the CPU knows.
+  UnknownOpcode { opcode: u8, pc: u16 },
+  /// The instruction at `pc` can't use `mode` (a bug in the opcode table).
+  InvalidAddressingMode { mode: AddressingMode, pc: u16 },
+  /// The program doesn't fit between the load address and the vectors.
+  ProgramTooLarge { size: usize, max: usize },
+  /// A segment of the program goes past the end of memory.
+  SegmentOutOfRange { address: u16, size: usize },
+  /// The CPU hit a jam opcode and `JamPolicy::Error` is set.
+  Jammed { opcode: u8, pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CpuError::UnknownOpcode { opcode, pc } => {
+        write!(f, "OP code {:x} not found at ${:04X}", opcode, pc)
+      }
+      CpuError::InvalidAddressingMode { mode, pc } => {
+        write!(f, "addressing mode {:?} is not valid for the instruction at ${:04X}", mode, pc)
+      }
+      CpuError::ProgramTooLarge { size, max } => {
+        write!(f, "the program has {} bytes, but at most {} fit in memory", size, max)
+      }
+      CpuError::SegmentOutOfRange { address, size } => {
+        write!(f, "{} bytes at ${:04X} go past the end of memory", size, address)
+      }
+      CpuError::Jammed { opcode, pc } => write!(f, "the CPU jammed on opcode {:02x} at ${:04X}", opcode, pc),
+    }
+  }
+}
+
+impl std::error::Error for CpuError {}
+
+/// What happened during a `step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+  Executed,
+  /// The CPU reached a BRK, which ends the program.
+  Break,
+  /// The CPU is stuck on a jam opcode (with `JamPolicy::Halt`).
+  Jammed,
+}
+
+/// Why one of the `run_*` methods returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+  /// `run_for_cycles` ran for the requested number of cycles.
+  CyclesElapsed,
+  /// `run_until_pc` reached the address.
+  PcReached,
+  /// `run_until_frame` saw the PPU finish a frame.
+  FrameCompleted,
+  /// The predicate of `run_until` held.
+  ConditionMet,
+  /// The CPU reached a BRK.
+  Break,
+  /// The CPU is stuck on a jam opcode (with `JamPolicy::Halt`).
+  Jammed,
+}
+
+/// What BRK does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakPolicy {
+  /// End the program (`StepOutcome::Break`), like easy6502 does.
+  #[default]
+  Stop,
+  /// Software interrupt, like the real CPU: push the return address and the status (with the B
+  /// flag set) and jump to the address at $FFFE.
+  Interrupt,
+}
+
+/// A return address found on the stack by `CPU::call_stack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackFrame {
+  /// Where the (low byte of the) return address is on the stack.
+  pub stack_addr: u16,
+  /// The JSR that pushed it.
+  pub caller: u16,
+  /// The subroutine the JSR called.
+  pub subroutine: u16,
+  /// Where RTS returns to.
+  pub return_addr: u16,
+}
+
+/// What to do with the jam (KIL) opcodes, which lock up the real CPU until a reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JamPolicy {
+  /// Fail with `CpuError::Jammed`.
+  #[default]
+  Error,
+  /// Stay on the opcode, like the hardware does: every step returns `StepOutcome::Jammed`.
+  Halt,
+  /// Treat it as a 1-byte NOP.
+  Ignore,
+}
+
+#[derive(Clone)]
 pub struct CPU {
   pub program_counter: u16,
   pub status: u8,
@@ -38,12 +152,15 @@ pub struct CPU {
   pub stack_pointer: u8,
   pub register_x: u8,
   pub register_y: u8,
-  memory: [u8; MEMORY_SIZE as usize],
+  pub bus: Bus,
+  pub break_policy: BreakPolicy,
+  pub jam_policy: JamPolicy,
+  /// Runs the instructions instead of the fixed opcode table, see `use_cycle_core`.
+  cycle_core: Option<CycleCpu>,
 }
 
 impl Default for CPU {
   fn default() -> Self {
-    let memory: [u8; MEMORY_SIZE as usize] = [0; MEMORY_SIZE as usize];
     Self {
       program_counter: 0,
       status: 0,
@@ -51,7 +168,10 @@ impl Default for CPU {
       stack_pointer: STACK_STARTING_POINTER,
       register_x: 0,
       register_y: 0,
-      memory,
+      bus: Bus::new(),
+      break_policy: BreakPolicy::default(),
+      jam_policy: JamPolicy::default(),
+      cycle_core: None,
     }
   }
 }
@@ -61,19 +181,29 @@ impl CPU {
     Self::default()
   }
 
-  pub fn mem_read(&self, addr: u16) -> u8 {
-    self.memory[addr as usize]
+  /// Runs the instructions on a `CycleCpu`, which has every official opcode and makes the bus
+  /// accesses of the real CPU, instead of the opcode table, which only has what the easy6502
+  /// programs use. The registers, the bus and the BRK and jam policies stay the ones of this CPU.
+  /// Decimal mode is off, as the 2A03 has none: this is the core for cartridges.
+  pub fn use_cycle_core(&mut self) {
+    let mut core = CycleCpu::new();
+    core.decimal_mode = false;
+    self.cycle_core = Some(core);
+  }
+
+  pub fn mem_read(&mut self, addr: u16) -> u8 {
+    self.bus.mem_read(addr)
   }
 
   pub fn mem_write(&mut self, addr: u16, data: u8) {
-    self.memory[addr as usize] = data;
+    self.bus.mem_write(addr, data);
   }
 
-  pub fn mem_read_u16(&self, addr: u16) -> u16 {
+  pub fn mem_read_u16(&mut self, addr: u16) -> u16 {
     let lsb = self.mem_read(addr) as u16;
-    let hsb = self.mem_read(addr + 1) as u16;
+    let hsb = self.mem_read(addr.wrapping_add(1)) as u16;
 
-    (hsb << 8) | (lsb as u16)
+    (hsb << 8) | lsb
   }
 
   pub fn mem_write_u16(&mut self, addr: u16, data: u16) {
@@ -81,7 +211,12 @@ impl CPU {
     let hsb = (data >> 8) as u8;
 
     self.mem_write(addr, lsb);
-    self.mem_write(addr + 1, hsb);
+    self.mem_write(addr.wrapping_add(1), hsb);
+  }
+
+  /// Number of CPU cycles elapsed since power up.
+  pub fn cycles(&self) -> u64 {
+    self.bus.cycles()
   }
 
   pub fn reset(&mut self) {
@@ -91,14 +226,52 @@ impl CPU {
     self.register_y = 0;
     self.status = 0b0000_0000;
     self.program_counter = self.mem_read_u16(RESET_INTERRUPT_ADDR);
+    if self.cycle_core.is_some() {
+      // drops the interrupts the core had latched
+      self.use_cycle_core();
+    }
   }
 
-  pub fn load(&mut self, program: Vec<u8>) {
-    self.memory[PROGRAM_ROM_MEMORY_ADDRESS_START as usize
-      ..(PROGRAM_ROM_MEMORY_ADDRESS_START as usize + program.len())]
-      .copy_from_slice(&program[..]); // puts the program into memory
+  pub fn load(&mut self, program: Vec<u8>) -> Result<(), CpuError> {
+    if program.len() > MAX_PROGRAM_SIZE {
+      return Err(CpuError::ProgramTooLarge {
+        size: program.len(),
+        max: MAX_PROGRAM_SIZE,
+      });
+    }
+
+    self.bus.load(PROGRAM_ROM_MEMORY_ADDRESS_START, &program[..]); // puts the program into memory
 
     self.mem_write_u16(RESET_INTERRUPT_ADDR, PROGRAM_ROM_MEMORY_ADDRESS_START);
+    Ok(())
+  }
+
+  /// Puts each segment of the program in memory at its address, then writes the vectors the
+  /// program sets (the others are left alone). Call `reset` to start it.
+  pub fn load_program(&mut self, program: &Program) -> Result<(), CpuError> {
+    for segment in &program.segments {
+      if segment.end() > 0x10000 {
+        return Err(CpuError::SegmentOutOfRange {
+          address: segment.address,
+          size: segment.data.len(),
+        });
+      }
+    }
+
+    for segment in &program.segments {
+      self.bus.load(segment.address, &segment.data);
+    }
+    let vectors = [
+      (NMI_INTERRUPT_ADDR, program.nmi_vector),
+      (RESET_INTERRUPT_ADDR, program.reset_vector),
+      (IRQ_INTERRUPT_ADDR, program.irq_vector),
+    ];
+    for (addr, vector) in vectors {
+      if let Some(vector) = vector {
+        self.mem_write_u16(addr, vector);
+      }
+    }
+    Ok(())
   }
 
   fn set_zero_flag(&mut self) {
@@ -167,15 +340,15 @@ impl CPU {
     }
   }
 
-  fn get_indirect_lookup(&self, lookup_addr: u16) -> u16 {
+  fn get_indirect_lookup(&mut self, lookup_addr: u16) -> u16 {
     let lsb = self.mem_read(lookup_addr);
     let hsb = self.mem_read(lookup_addr.wrapping_add(1));
 
     (hsb as u16) << 8 | (lsb as u16)
   }
 
-  fn get_operand_addr(&self, mode: &AddressingMode) -> u16 {
-    match mode {
+  fn get_operand_addr(&mut self, mode: &AddressingMode) -> Result<u16, CpuError> {
+    let addr = match mode {
       AddressingMode::Immediate => self.program_counter,
       AddressingMode::ZeroPage => self.mem_read(self.program_counter) as u16,
       AddressingMode::ZeroPageX => {
@@ -189,11 +362,11 @@ impl CPU {
       AddressingMode::Absolute => self.mem_read_u16(self.program_counter),
       AddressingMode::AbsoluteX => {
         let pos = self.mem_read_u16(self.program_counter);
-        pos.wrapping_add(self.register_x as u16) as u16
+        pos.wrapping_add(self.register_x as u16)
       }
       AddressingMode::AbsoluteY => {
         let pos = self.mem_read_u16(self.program_counter);
-        pos.wrapping_add(self.register_y as u16) as u16
+        pos.wrapping_add(self.register_y as u16)
       }
       AddressingMode::IndirectX => {
         let base = self.mem_read(self.program_counter);
@@ -211,59 +384,140 @@ impl CPU {
         addr.wrapping_add(self.register_y as u16)
       }
       AddressingMode::Relative => self.program_counter,
-      AddressingMode::NoneAddressing => panic!("Mode not known"),
-      _ => panic!("Mode not known"),
+      AddressingMode::Accumulator | AddressingMode::NoneAddressing => {
+        return Err(CpuError::InvalidAddressingMode {
+          mode: *mode,
+          pc: self.program_counter.wrapping_sub(1),
+        })
+      }
+    };
+
+    Ok(addr)
+  }
+
+  /// Indexed addressing modes take one extra cycle when the index makes the effective address
+  /// cross into another page (the high byte had to be fixed up).
+  fn page_crossed(&mut self, mode: &AddressingMode) -> Result<bool, CpuError> {
+    let base = match mode {
+      AddressingMode::AbsoluteX | AddressingMode::AbsoluteY => self.mem_read_u16(self.program_counter),
+      AddressingMode::IndirectY => {
+        let lookup_addr = self.mem_read(self.program_counter);
+        self.get_indirect_lookup(lookup_addr as u16)
+      }
+      _ => return Ok(false),
+    };
+
+    Ok(base & 0xFF00 != self.get_operand_addr(mode)? & 0xFF00)
+  }
+
+  /// Reads the operand of instructions that only read from memory, accounting for the page
+  /// crossing penalty (stores and read-modify-write instructions always take the extra cycle,
+  /// so it is already part of their base cycles).
+  fn read_operand(&mut self, mode: &AddressingMode) -> Result<u8, CpuError> {
+    if self.page_crossed(mode)? {
+      self.bus.tick(1);
     }
+
+    let operand_addr = self.get_operand_addr(mode)?;
+    Ok(self.mem_read(operand_addr))
   }
 
-  fn get_address_from_stack(&self) -> u16 {
-    let absolute_stack_pointer = format!("01{:X}", self.stack_pointer);
-    u16::from_str_radix(&absolute_stack_pointer, 16).unwrap()
+  /// Pushes a byte on the stack (page 1). The stack pointer wraps around, like on the 6502.
+  pub fn push(&mut self, data: u8) {
+    self.mem_write(STACK_PAGE | self.stack_pointer as u16, data);
+    self.stack_pointer = self.stack_pointer.wrapping_sub(1);
   }
 
-  fn insert_address_into_stack(&mut self) -> u16 {
-    let address = self.get_address_from_stack();
-    self.stack_pointer -= 1;
-    address
+  pub fn pop(&mut self) -> u8 {
+    self.stack_pointer = self.stack_pointer.wrapping_add(1);
+    self.mem_read(STACK_PAGE | self.stack_pointer as u16)
   }
 
-  fn remove_address_from_stack(&mut self) -> u16 {
-    self.stack_pointer += 1;
-    self.get_address_from_stack()
+  /// Pushes a word, high byte first (so it sits little-endian in memory).
+  pub fn push_u16(&mut self, data: u16) {
+    self.push((data >> 8) as u8);
+    self.push((data & 0xFF) as u8);
   }
 
-  fn add_relative_displacement_to_program_counter(&mut self, step: u8) {
-    let mut lsb_program_counter = (self.program_counter & 0xFF) as u8;
-    let mut msb_program_counter = (self.program_counter >> 8) as u8;
+  pub fn pop_u16(&mut self) -> u16 {
+    let lsb = self.pop() as u16;
+    let hsb = self.pop() as u16;
 
-    let step_as_i8 = step as i8;
-    let step_as_i8_mult_minus_one = -step_as_i8;
-    let positive = step_as_i8.signum() == 1;
+    (hsb << 8) | lsb
+  }
 
-    if positive {
-      let wrap = lsb_program_counter.checked_add(step);
-      lsb_program_counter = lsb_program_counter.wrapping_add(step);
+  /// The bytes on the stack, from the top (the last pushed) to the bottom ($01FF).
+  pub fn stack_contents(&self) -> Vec<u8> {
+    (self.stack_pointer as u16 + 1..=0xFF)
+      .map(|offset| self.bus.peek(STACK_PAGE | offset))
+      .collect()
+  }
 
-      match wrap {
-        Some(_) => (),
-        None => msb_program_counter = msb_program_counter.wrapping_add(1),
-      }
-    } else {
-      let wrap = lsb_program_counter.checked_sub(step_as_i8_mult_minus_one as u8);
-      lsb_program_counter = lsb_program_counter.wrapping_sub(step_as_i8_mult_minus_one as u8);
+  /// The return addresses on the stack, innermost call first.
+  ///
+  /// The stack doesn't say what its bytes are, so a pair of bytes is taken as a return address
+  /// when the instruction right before where it returns to is a JSR.
+  pub fn call_stack(&self) -> Vec<StackFrame> {
+    let mut frames = Vec::new();
+    let mut offset = self.stack_pointer as u16 + 1;
+    while offset < 0xFF {
+      let stack_addr = STACK_PAGE | offset;
+      let pushed = u16::from_le_bytes([self.bus.peek(stack_addr), self.bus.peek(stack_addr + 1)]);
+      let caller = pushed.wrapping_sub(2);
 
-      match wrap {
-        Some(_) => (),
-        None => msb_program_counter = msb_program_counter.wrapping_sub(1),
+      if self.bus.peek(caller) == JSR_OPCODE {
+        let subroutine = u16::from_le_bytes([
+          self.bus.peek(caller.wrapping_add(1)),
+          self.bus.peek(caller.wrapping_add(2)),
+        ]);
+        frames.push(StackFrame {
+          stack_addr,
+          caller,
+          subroutine,
+          return_addr: pushed.wrapping_add(1),
+        });
+        offset += 2;
+      } else {
+        offset += 1;
       }
     }
 
-    self.program_counter = (msb_program_counter as u16) << 8 | (lsb_program_counter as u16);
+    frames
   }
 
-  fn adc(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
+  /// Moves the program counter (which is on the operand) to the next instruction or, when the
+  /// branch is taken, to the one `offset` bytes after it.
+  fn branch(&mut self, mode: &AddressingMode, taken: bool) -> Result<(), CpuError> {
+    let operand_addr = self.get_operand_addr(mode)?;
+    let offset = self.mem_read(operand_addr);
+    let next_instruction = self.program_counter.wrapping_add(1);
+    if !taken {
+      self.program_counter = next_instruction;
+      return Ok(());
+    }
+    self.program_counter = next_instruction.wrapping_add(offset as i8 as u16);
+
+    // a taken branch takes one extra cycle, and one more if it lands on another page
+    self.bus.tick(1);
+    if next_instruction & 0xFF00 != self.program_counter & 0xFF00 {
+      self.bus.tick(1);
+    }
+    Ok(())
+  }
+
+  /// Non-maskable interrupt: saves the program counter and the status (with the B flag clear)
+  /// on the stack and jumps to the address at $FFFA. Takes 7 cycles.
+  fn interrupt_nmi(&mut self) {
+    self.push_u16(self.program_counter);
+    self.push((self.status & !BREAK_FLAG) | UNUSED_FLAG);
+    self.status |= INTERRUPT_DISABLE_FLAG;
+
+    self.bus.tick(7);
+    self.program_counter = self.mem_read_u16(NMI_INTERRUPT_ADDR);
+  }
+
+  pub(crate) fn adc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(mode)?;
 
     let old_accumulator = self.accumulator;
 
@@ -271,18 +525,21 @@ impl CPU {
 
     self.update_negative_and_zero_flags(self.accumulator);
     self.update_carry_and_overflow_flags(old_accumulator.checked_add(param), MathematicalOperation::Add);
+
+    Ok(())
   }
 
-  fn and(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn and(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(mode)?;
 
     self.accumulator &= param;
 
     self.update_negative_and_zero_flags(self.accumulator);
+
+    Ok(())
   }
 
-  fn asl(&mut self, mode: &AddressingMode) {
+  pub(crate) fn asl(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
     let seventh_bit = format!("{:08b}", self.accumulator)
       .chars()
       .collect::<Vec<char>>()[0];
@@ -290,7 +547,7 @@ impl CPU {
     if *mode == AddressingMode::Accumulator {
       self.accumulator <<= 1;
     } else {
-      let operand_addr = self.get_operand_addr(mode);
+      let operand_addr = self.get_operand_addr(mode)?;
       let param = self.mem_read(operand_addr);
 
       self.accumulator = param << 1;
@@ -301,71 +558,36 @@ impl CPU {
     let bits = format!("0000000{}", seventh_bit);
     let new_bits = u8::from_str_radix(&bits, 2).unwrap();
     self.status |= new_bits;
-  }
 
-  fn bcc(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
-
-    let step = param + 1;
-
-    if self.status & 0b0000_0001 == 0 {
-      self.add_relative_displacement_to_program_counter(step);
-    }
+    Ok(())
   }
 
-  fn bcs(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
-
-    let step = param + 1;
-
-    if self.status & 0b0000_0001 == 1 {
-      self.add_relative_displacement_to_program_counter(step);
-    }
+  pub(crate) fn bcc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    self.branch(mode, self.status & 0b0000_0001 == 0)
   }
 
-  fn beq(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
-
-    let step = param + 1;
-
-    if self.status & 0b0000_0010 == 2 {
-      // if zero flag is set
-      self.add_relative_displacement_to_program_counter(step);
-    }
+  pub(crate) fn bcs(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    self.branch(mode, self.status & 0b0000_0001 == 1)
   }
 
-  fn bne(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
-
-    let step = param + 1;
-
-    if self.status & 0b0000_0010 == 0 {
-      self.add_relative_displacement_to_program_counter(step);
-    }
+  pub(crate) fn beq(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    self.branch(mode, self.status & 0b0000_0010 == 2)
   }
 
-  fn bpl(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
-
-    let step = param + 1;
+  pub(crate) fn bne(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    self.branch(mode, self.status & 0b0000_0010 == 0)
+  }
 
-    if self.status & 0b1000_0000 == 0 {
-      self.add_relative_displacement_to_program_counter(step);
-    }
+  pub(crate) fn bpl(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    self.branch(mode, self.status & 0b1000_0000 == 0)
   }
 
-  fn clc(&mut self) {
+  pub(crate) fn clc(&mut self) {
     self.clear_carry_flag();
   }
 
-  fn cmp(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn cmp(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(mode)?;
 
     if self.accumulator >= param {
       self.set_carry_flag();
@@ -374,11 +596,12 @@ impl CPU {
     }
 
     self.update_negative_and_zero_flags(self.accumulator.wrapping_sub(param));
+
+    Ok(())
   }
 
-  fn cpx(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn cpx(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(mode)?;
 
     if self.register_x >= param {
       self.set_carry_flag();
@@ -387,74 +610,103 @@ impl CPU {
     }
 
     self.update_negative_and_zero_flags(self.register_x.wrapping_sub(param));
+
+    Ok(())
   }
 
-  fn dex(&mut self) {
+  pub(crate) fn dex(&mut self) {
     self.register_x = self.register_x.wrapping_sub(1);
     self.update_negative_and_zero_flags(self.register_x);
   }
 
-  fn inx(&mut self) {
+  pub(crate) fn inx(&mut self) {
     self.register_x = self.register_x.wrapping_add(1);
     self.update_negative_and_zero_flags(self.register_x);
   }
 
-  fn jsr(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
+  pub(crate) fn jsr(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let operand_addr = self.get_operand_addr(mode)?;
 
-    // save old program counter to stack
-    let old_program_counter = self.program_counter + 1;
-    let lsb = (old_program_counter & 0xFF) as u8;
-    let hsb = (old_program_counter >> 8) as u8;
-    self.memory[self.insert_address_into_stack() as usize] = hsb;
-    self.memory[self.insert_address_into_stack() as usize] = lsb;
+    // save old program counter (the last byte of the JSR) to stack
+    self.push_u16(self.program_counter.wrapping_add(1));
 
     // update program counter (to jump to a subroutine)
     self.program_counter = operand_addr;
+
+    Ok(())
   }
 
-  fn lda(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn lda(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(addressing_mode)?;
 
     self.accumulator = param;
     self.update_negative_and_zero_flags(self.accumulator);
+
+    Ok(())
   }
 
-  fn ldx(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn ldx(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(addressing_mode)?;
 
     self.register_x = param;
     self.update_negative_and_zero_flags(self.register_x);
+
+    Ok(())
   }
 
-  fn ldy(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn ldy(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(addressing_mode)?;
 
     self.register_y = param;
     self.update_negative_and_zero_flags(self.register_y);
+
+    Ok(())
+  }
+
+  pub(crate) fn rts(&mut self) {
+    // return from subroutine
+    self.program_counter = self.pop_u16().wrapping_add(1);
   }
 
-  fn rts(&mut self) {
-    // retrieve program counter from stack
-    let lsb_address = self.memory[self.remove_address_from_stack() as usize];
-    let msb_address = self.memory[self.remove_address_from_stack() as usize];
+  pub(crate) fn rti(&mut self) {
+    self.plp();
+    self.program_counter = self.pop_u16();
+  }
 
-    let absolute_address = (msb_address as u16) << 8 | (lsb_address as u16);
+  pub(crate) fn brk(&mut self) {
+    // the byte after BRK is padding, skipped on return
+    self.push_u16(self.program_counter.wrapping_add(1));
+    self.push(self.status | BREAK_FLAG | UNUSED_FLAG);
+    self.status |= INTERRUPT_DISABLE_FLAG;
 
-    // return from subroutine
-    self.program_counter = absolute_address + 1;
+    self.program_counter = self.mem_read_u16(IRQ_INTERRUPT_ADDR);
   }
 
-  fn sec(&mut self) {
+  pub(crate) fn pha(&mut self) {
+    self.push(self.accumulator);
+  }
+
+  pub(crate) fn php(&mut self) {
+    // the status is always pushed with the B flag set
+    self.push(self.status | BREAK_FLAG | UNUSED_FLAG);
+  }
+
+  pub(crate) fn pla(&mut self) {
+    self.accumulator = self.pop();
+    self.update_negative_and_zero_flags(self.accumulator);
+  }
+
+  pub(crate) fn plp(&mut self) {
+    // the B flag doesn't exist in the register, only on the stack
+    self.status = (self.pop() & !BREAK_FLAG) | UNUSED_FLAG;
+  }
+
+  pub(crate) fn sec(&mut self) {
     self.set_carry_flag();
   }
 
-  fn sbc(&mut self, mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(mode);
-    let param = self.mem_read(operand_addr);
+  pub(crate) fn sbc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+    let param = self.read_operand(mode)?;
 
     let old_accumulator = self.accumulator;
 
@@ -462,120 +714,226 @@ impl CPU {
 
     self.update_negative_and_zero_flags(self.accumulator);
     self.update_carry_and_overflow_flags(old_accumulator.checked_sub(param), MathematicalOperation::Sub);
+
+    Ok(())
   }
 
-  fn sta(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
+  pub(crate) fn sta(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let operand_addr = self.get_operand_addr(addressing_mode)?;
     self.mem_write(operand_addr, self.accumulator);
+
+    Ok(())
   }
 
-  fn stx(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
+  pub(crate) fn stx(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let operand_addr = self.get_operand_addr(addressing_mode)?;
     self.mem_write(operand_addr, self.register_x);
+
+    Ok(())
   }
 
-  fn sty(&mut self, addressing_mode: &AddressingMode) {
-    let operand_addr = self.get_operand_addr(addressing_mode);
+  pub(crate) fn sty(&mut self, addressing_mode: &AddressingMode) -> Result<(), CpuError> {
+    let operand_addr = self.get_operand_addr(addressing_mode)?;
     self.mem_write(operand_addr, self.register_y);
+
+    Ok(())
   }
 
-  fn tax(&mut self) {
+  pub(crate) fn tax(&mut self) {
     self.register_x = self.accumulator;
     self.update_negative_and_zero_flags(self.register_x);
   }
 
-  fn txa(&mut self) {
+  pub(crate) fn txa(&mut self) {
     self.accumulator = self.register_x;
     self.update_negative_and_zero_flags(self.accumulator);
   }
 
-  pub fn run_with_callback<F>(&mut self, mut callback: F)
+  /// Runs one instruction, after servicing a pending NMI.
+  pub fn step(&mut self) -> Result<StepOutcome, CpuError> {
+    self.service_interrupts();
+    self.execute_instruction()
+  }
+
+  /// The cycle core polls the NMI itself, on the cycle the real CPU does.
+  fn service_interrupts(&mut self) {
+    if self.cycle_core.is_none() && self.bus.poll_nmi() {
+      self.interrupt_nmi();
+    }
+  }
+
+  /// Runs the instruction at the program counter. On error, the program counter is left on it.
+  fn execute_instruction(&mut self) -> Result<StepOutcome, CpuError> {
+    let opcode_addr = self.program_counter;
+    let code = self.mem_read(opcode_addr);
+    if JAM_OPCODES.contains(&code) {
+      return self.jam(code);
+    }
+    if let Some(core) = self.cycle_core.take() {
+      return self.execute_on_cycle_core(core, code);
+    }
+
+    let unknown_opcode = CpuError::UnknownOpcode {
+      opcode: code,
+      pc: opcode_addr,
+    };
+    let current_opcode = opcodes::decode(code).ok_or(unknown_opcode)?;
+    self.program_counter = opcode_addr.wrapping_add(1);
+    let current_program_counter_state = self.program_counter;
+
+    if code == 0x00 && self.break_policy == BreakPolicy::Stop {
+      return Ok(StepOutcome::Break);
+    }
+
+    // Devices are read/written on the last cycle of the instruction, so the clock is brought
+    // up to it before executing, keeping the PPU in sync when its registers are accessed.
+    self.bus.tick(current_opcode.cycles as u16 - 1);
+
+    if let Err(err) = current_opcode.execute(self) {
+      self.program_counter = opcode_addr;
+      return Err(err);
+    }
+
+    // branches always move the program counter themselves, as they can land on their operand
+    if current_opcode.addressing_mode != AddressingMode::Relative
+      && current_program_counter_state == self.program_counter
+    {
+      self.program_counter = self.program_counter.wrapping_add((current_opcode.bytes - 1) as u16);
+    }
+
+    self.bus.tick(1);
+    self.bus.run_pending_oam_dma();
+    Ok(StepOutcome::Executed)
+  }
+
+  /// Runs the next instruction (or interrupt sequence) on the cycle core, then puts it back.
+  fn execute_on_cycle_core(&mut self, mut core: CycleCpu, code: u8) -> Result<StepOutcome, CpuError> {
+    if code == 0x00 && self.break_policy == BreakPolicy::Stop {
+      self.program_counter = self.program_counter.wrapping_add(1);
+      self.cycle_core = Some(core);
+      return Ok(StepOutcome::Break);
+    }
+
+    core.accumulator = self.accumulator;
+    core.register_x = self.register_x;
+    core.register_y = self.register_y;
+    core.status = self.status;
+    core.stack_pointer = self.stack_pointer;
+    core.program_counter = self.program_counter;
+    let result = core.step(&mut self.bus);
+    self.accumulator = core.accumulator;
+    self.register_x = core.register_x;
+    self.register_y = core.register_y;
+    self.status = core.status;
+    self.stack_pointer = core.stack_pointer;
+    self.program_counter = core.program_counter;
+    self.cycle_core = Some(core);
+
+    result.map(|_| StepOutcome::Executed)
+  }
+
+  fn jam(&mut self, code: u8) -> Result<StepOutcome, CpuError> {
+    match self.jam_policy {
+      JamPolicy::Error => Err(CpuError::Jammed {
+        opcode: code,
+        pc: self.program_counter,
+      }),
+      JamPolicy::Halt => {
+        self.bus.tick(1); // the rest of the console keeps running
+        Ok(StepOutcome::Jammed)
+      }
+      JamPolicy::Ignore => {
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.bus.tick(2);
+        Ok(StepOutcome::Executed)
+      }
+    }
+  }
+
+  /// Runs the program until it reaches a BRK (or jams), calling `callback` before every
+  /// instruction.
+  pub fn run_with_callback<F>(&mut self, mut callback: F) -> Result<(), CpuError>
   where
     F: FnMut(&mut CPU),
   {
-    let all_op_codes: &HashMap<u8, &'static opcodes::Opcode> = &(*opcodes::OPCODES_MAP);
-
     loop {
+      self.service_interrupts();
+
       callback(self);
 
-      let code = self.mem_read(self.program_counter);
-      self.program_counter += 1;
-      let current_program_counter_state = self.program_counter;
+      match self.execute_instruction()? {
+        StepOutcome::Executed => {}
+        StepOutcome::Break | StepOutcome::Jammed => return Ok(()),
+      }
+    }
+  }
 
-      let current_opcode = all_op_codes
-        .get(&code)
-        .unwrap_or_else(|| panic!("OP code {:x} not found", code));
+  pub fn run(&mut self) -> Result<(), CpuError> {
+    self.run_with_callback(|_| {})
+  }
 
-      match code {
-        0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71 => {
-          self.adc(&current_opcode.addressing_mode);
-        }
-        0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31 => {
-          self.and(&current_opcode.addressing_mode);
-        }
-        0x0A | 0x06 | 0x16 | 0x0E | 0x1E => {
-          self.asl(&current_opcode.addressing_mode);
-        }
-        0x90 => self.bcc(&current_opcode.addressing_mode),
-        0xB0 => self.bcs(&current_opcode.addressing_mode),
-        0xF0 => self.beq(&current_opcode.addressing_mode),
-        0xD0 => self.bne(&current_opcode.addressing_mode),
-        0x10 => self.bpl(&current_opcode.addressing_mode),
-        0x18 => self.clc(),
-        0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1 => {
-          self.cmp(&current_opcode.addressing_mode);
-        }
-        0xE0 | 0xE4 | 0xEC => {
-          self.cpx(&current_opcode.addressing_mode);
-        }
-        0xCA => self.dex(),
-        0xE8 => self.inx(),
-        0x20 => self.jsr(&current_opcode.addressing_mode),
-        0xA9 | 0xA5 | 0xB5 | 0xAD | 0xBD | 0xB9 | 0xA1 | 0xB1 => {
-          self.lda(&current_opcode.addressing_mode);
-        }
-        0xA2 | 0xA6 | 0xB6 | 0xAE | 0xBE => {
-          self.ldx(&current_opcode.addressing_mode);
-        }
-        0xA0 | 0xA4 | 0xB4 | 0xAC | 0xBC => {
-          self.ldy(&current_opcode.addressing_mode);
-        }
-        0x60 => self.rts(),
-        0x38 => self.sec(),
-        0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1 => {
-          self.sbc(&current_opcode.addressing_mode);
-        }
-        0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91 => {
-          self.sta(&current_opcode.addressing_mode);
-        }
-        0x86 | 0x96 | 0x8E => {
-          self.stx(&current_opcode.addressing_mode);
-        }
-        0x84 | 0x94 | 0x8C => {
-          self.sty(&current_opcode.addressing_mode);
-        }
-        0xAA => self.tax(),
-        0x8A => self.txa(),
-        0x00 => {
-          println!("Reached break: {:x}", self.program_counter);
-          return;
-        }
-        _ => return,
-      }
+  /// Runs until at least `cycles` more CPU cycles have elapsed (instructions are not split, so
+  /// it may run a few cycles over).
+  pub fn run_for_cycles(&mut self, cycles: u64) -> Result<StopReason, CpuError> {
+    let end = self.cycles().saturating_add(cycles);
+    self.run_until_with_reason(|cpu| cpu.cycles() >= end, StopReason::CyclesElapsed)
+  }
 
-      if current_program_counter_state == self.program_counter {
-        self.program_counter += (current_opcode.bytes - 1) as u16;
-      }
+  /// Runs until the program counter gets to `addr`, without running the instruction there. If
+  /// it is already there, nothing runs: step past it first to get to the next time it is reached.
+  pub fn run_until_pc(&mut self, addr: u16) -> Result<StopReason, CpuError> {
+    if self.program_counter == addr {
+      return Ok(StopReason::PcReached);
     }
+    self.run_until_with_reason(|cpu| cpu.program_counter == addr, StopReason::PcReached)
+  }
+
+  /// Runs until the PPU finishes the current frame. Without a PPU there are no frames, so it
+  /// only stops at a BRK (or on a jam or error).
+  pub fn run_until_frame(&mut self) -> Result<StopReason, CpuError> {
+    let frame_count = |cpu: &CPU| cpu.bus.ppu.as_ref().map(|ppu| ppu.frame_count);
+    let start = frame_count(self);
+    self.run_until_with_reason(|cpu| frame_count(cpu) != start, StopReason::FrameCompleted)
+  }
+
+  /// Runs until `predicate` holds. It is checked after every instruction, so at least one runs.
+  pub fn run_until<F>(&mut self, predicate: F) -> Result<StopReason, CpuError>
+  where
+    F: FnMut(&CPU) -> bool,
+  {
+    self.run_until_with_reason(predicate, StopReason::ConditionMet)
   }
 
-  fn run(&mut self) {
-    self.run_with_callback(|_| {});
+  fn run_until_with_reason<F>(
+    &mut self,
+    mut predicate: F,
+    reason: StopReason,
+  ) -> Result<StopReason, CpuError>
+  where
+    F: FnMut(&CPU) -> bool,
+  {
+    loop {
+      match self.step()? {
+        StepOutcome::Executed => {}
+        StepOutcome::Break => return Ok(StopReason::Break),
+        StepOutcome::Jammed => return Ok(StopReason::Jammed),
+      }
+
+      if predicate(self) {
+        return Ok(reason);
+      }
+    }
   }
 
+  /// Loads and runs a program. Meant for tests: it panics if the program fails.
   pub fn load_and_run(&mut self, program: Vec<u8>) {
-    self.load(program);
-    self.reset();
-    self.run();
+    let result = self.load(program).and_then(|_| {
+      self.reset();
+      self.run()
+    });
+
+    if let Err(err) = result {
+      panic!("{}", err);
+    }
   }
 }
@@ -0,0 +1,954 @@
+use crate::bus::Bus;
+use crate::cpu::CpuError;
+
+const STACK_PAGE: u16 = 0x0100;
+const NMI_VECTOR: u16 = 0xFFFA;
+const RESET_VECTOR: u16 = 0xFFFC;
+const IRQ_VECTOR: u16 = 0xFFFE;
+
+const CARRY: u8 = 0b0000_0001;
+const ZERO: u8 = 0b0000_0010;
+const INTERRUPT_DISABLE: u8 = 0b0000_0100;
+const DECIMAL: u8 = 0b0000_1000;
+const BREAK: u8 = 0b0001_0000;
+const UNUSED: u8 = 0b0010_0000;
+const OVERFLOW: u8 = 0b0100_0000;
+const NEGATIVE: u8 = 0b1000_0000;
+
+/// Opcodes that lock up the NMOS 6502.
+const JAM_OPCODES: [u8; 12] = [
+  0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+];
+
+/// What the cycle-stepped CPU is connected to. Every call is one CPU cycle.
+pub trait CpuBus {
+  fn read(&mut self, addr: u16) -> u8;
+  fn write(&mut self, addr: u16, data: u8);
+
+  /// Whether an NMI was signalled since the last call (the CPU detects the edge).
+  fn poll_nmi(&mut self) -> bool {
+    false
+  }
+
+  /// Whether the IRQ line is asserted.
+  fn irq(&mut self) -> bool {
+    false
+  }
+}
+
+/// The console bus: the rest of the console runs for a cycle after each access, and an OAM DMA
+/// started by a write runs right after it.
+impl CpuBus for Bus {
+  fn read(&mut self, addr: u16) -> u8 {
+    let data = self.mem_read(addr);
+    self.tick(1);
+    data
+  }
+
+  fn write(&mut self, addr: u16, data: u8) {
+    self.mem_write(addr, data);
+    self.tick(1);
+    self.run_pending_oam_dma();
+  }
+
+  fn poll_nmi(&mut self) -> bool {
+    Bus::poll_nmi(self)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusAccess {
+  Read,
+  Write,
+}
+
+/// One cycle of bus activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusCycle {
+  pub addr: u16,
+  pub data: u8,
+  pub access: BusAccess,
+}
+
+impl BusCycle {
+  pub fn read(addr: u16, data: u8) -> Self {
+    BusCycle {
+      addr,
+      data,
+      access: BusAccess::Read,
+    }
+  }
+
+  pub fn write(addr: u16, data: u8) -> Self {
+    BusCycle {
+      addr,
+      data,
+      access: BusAccess::Write,
+    }
+  }
+}
+
+/// 64 KiB of RAM and nothing else, logging every access, for checking the bus activity of the
+/// CPU.
+pub struct RamBus {
+  pub memory: Vec<u8>,
+  pub cycles: Vec<BusCycle>,
+  /// Set to signal an NMI (taken on the next cycle).
+  pub nmi: bool,
+  pub irq: bool,
+}
+
+impl Default for RamBus {
+  fn default() -> Self {
+    RamBus {
+      memory: vec![0; 0x10000],
+      cycles: Vec::new(),
+      nmi: false,
+      irq: false,
+    }
+  }
+}
+
+impl RamBus {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn load(&mut self, start: u16, data: &[u8]) {
+    let start = start as usize;
+    self.memory[start..start + data.len()].copy_from_slice(data);
+  }
+}
+
+impl CpuBus for RamBus {
+  fn read(&mut self, addr: u16) -> u8 {
+    let data = self.memory[addr as usize];
+    self.cycles.push(BusCycle::read(addr, data));
+    data
+  }
+
+  fn write(&mut self, addr: u16, data: u8) {
+    self.memory[addr as usize] = data;
+    self.cycles.push(BusCycle::write(addr, data));
+  }
+
+  fn poll_nmi(&mut self) -> bool {
+    std::mem::take(&mut self.nmi)
+  }
+
+  fn irq(&mut self) -> bool {
+    self.irq
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+  Implied,
+  Accumulator,
+  Immediate,
+  ZeroPage,
+  ZeroPageX,
+  ZeroPageY,
+  Absolute,
+  AbsoluteX,
+  AbsoluteY,
+  Indirect,
+  IndirectX,
+  IndirectY,
+  Relative,
+}
+
+#[rustfmt::skip]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+  Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Brk, Bvc, Bvs, Clc, Cld, Cli, Clv, Cmp, Cpx,
+  Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp, Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora, Pha, Php, Pla,
+  Plp, Rol, Ror, Rti, Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
+}
+
+/// How an instruction with an operand in memory uses it, which decides its bus cycles.
+enum Access {
+  Read,
+  Write,
+  ReadModifyWrite,
+}
+
+impl Instruction {
+  fn access(self) -> Access {
+    match self {
+      Instruction::Sta | Instruction::Stx | Instruction::Sty => Access::Write,
+      Instruction::Asl
+      | Instruction::Lsr
+      | Instruction::Rol
+      | Instruction::Ror
+      | Instruction::Inc
+      | Instruction::Dec => Access::ReadModifyWrite,
+      _ => Access::Read,
+    }
+  }
+}
+
+use Instruction::*;
+use Mode::*;
+
+/// The official NMOS 6502 opcodes.
+#[rustfmt::skip]
+const OPCODES: &[(u8, Instruction, Mode)] = &[
+  (0x69, Adc, Immediate), (0x65, Adc, ZeroPage), (0x75, Adc, ZeroPageX), (0x6D, Adc, Absolute),
+  (0x7D, Adc, AbsoluteX), (0x79, Adc, AbsoluteY), (0x61, Adc, IndirectX), (0x71, Adc, IndirectY),
+  (0x29, And, Immediate), (0x25, And, ZeroPage), (0x35, And, ZeroPageX), (0x2D, And, Absolute),
+  (0x3D, And, AbsoluteX), (0x39, And, AbsoluteY), (0x21, And, IndirectX), (0x31, And, IndirectY),
+  (0x0A, Asl, Accumulator), (0x06, Asl, ZeroPage), (0x16, Asl, ZeroPageX), (0x0E, Asl, Absolute),
+  (0x1E, Asl, AbsoluteX),
+  (0x90, Bcc, Relative), (0xB0, Bcs, Relative), (0xF0, Beq, Relative), (0x30, Bmi, Relative),
+  (0xD0, Bne, Relative), (0x10, Bpl, Relative), (0x50, Bvc, Relative), (0x70, Bvs, Relative),
+  (0x24, Bit, ZeroPage), (0x2C, Bit, Absolute),
+  (0x00, Brk, Implied),
+  (0x18, Clc, Implied), (0xD8, Cld, Implied), (0x58, Cli, Implied), (0xB8, Clv, Implied),
+  (0xC9, Cmp, Immediate), (0xC5, Cmp, ZeroPage), (0xD5, Cmp, ZeroPageX), (0xCD, Cmp, Absolute),
+  (0xDD, Cmp, AbsoluteX), (0xD9, Cmp, AbsoluteY), (0xC1, Cmp, IndirectX), (0xD1, Cmp, IndirectY),
+  (0xE0, Cpx, Immediate), (0xE4, Cpx, ZeroPage), (0xEC, Cpx, Absolute),
+  (0xC0, Cpy, Immediate), (0xC4, Cpy, ZeroPage), (0xCC, Cpy, Absolute),
+  (0xC6, Dec, ZeroPage), (0xD6, Dec, ZeroPageX), (0xCE, Dec, Absolute), (0xDE, Dec, AbsoluteX),
+  (0xCA, Dex, Implied), (0x88, Dey, Implied),
+  (0x49, Eor, Immediate), (0x45, Eor, ZeroPage), (0x55, Eor, ZeroPageX), (0x4D, Eor, Absolute),
+  (0x5D, Eor, AbsoluteX), (0x59, Eor, AbsoluteY), (0x41, Eor, IndirectX), (0x51, Eor, IndirectY),
+  (0xE6, Inc, ZeroPage), (0xF6, Inc, ZeroPageX), (0xEE, Inc, Absolute), (0xFE, Inc, AbsoluteX),
+  (0xE8, Inx, Implied), (0xC8, Iny, Implied),
+  (0x4C, Jmp, Absolute), (0x6C, Jmp, Indirect),
+  (0x20, Jsr, Absolute),
+  (0xA9, Lda, Immediate), (0xA5, Lda, ZeroPage), (0xB5, Lda, ZeroPageX), (0xAD, Lda, Absolute),
+  (0xBD, Lda, AbsoluteX), (0xB9, Lda, AbsoluteY), (0xA1, Lda, IndirectX), (0xB1, Lda, IndirectY),
+  (0xA2, Ldx, Immediate), (0xA6, Ldx, ZeroPage), (0xB6, Ldx, ZeroPageY), (0xAE, Ldx, Absolute),
+  (0xBE, Ldx, AbsoluteY),
+  (0xA0, Ldy, Immediate), (0xA4, Ldy, ZeroPage), (0xB4, Ldy, ZeroPageX), (0xAC, Ldy, Absolute),
+  (0xBC, Ldy, AbsoluteX),
+  (0x4A, Lsr, Accumulator), (0x46, Lsr, ZeroPage), (0x56, Lsr, ZeroPageX), (0x4E, Lsr, Absolute),
+  (0x5E, Lsr, AbsoluteX),
+  (0xEA, Nop, Implied),
+  (0x09, Ora, Immediate), (0x05, Ora, ZeroPage), (0x15, Ora, ZeroPageX), (0x0D, Ora, Absolute),
+  (0x1D, Ora, AbsoluteX), (0x19, Ora, AbsoluteY), (0x01, Ora, IndirectX), (0x11, Ora, IndirectY),
+  (0x48, Pha, Implied), (0x08, Php, Implied), (0x68, Pla, Implied), (0x28, Plp, Implied),
+  (0x2A, Rol, Accumulator), (0x26, Rol, ZeroPage), (0x36, Rol, ZeroPageX), (0x2E, Rol, Absolute),
+  (0x3E, Rol, AbsoluteX),
+  (0x6A, Ror, Accumulator), (0x66, Ror, ZeroPage), (0x76, Ror, ZeroPageX), (0x6E, Ror, Absolute),
+  (0x7E, Ror, AbsoluteX),
+  (0x40, Rti, Implied), (0x60, Rts, Implied),
+  (0xE9, Sbc, Immediate), (0xE5, Sbc, ZeroPage), (0xF5, Sbc, ZeroPageX), (0xED, Sbc, Absolute),
+  (0xFD, Sbc, AbsoluteX), (0xF9, Sbc, AbsoluteY), (0xE1, Sbc, IndirectX), (0xF1, Sbc, IndirectY),
+  (0x38, Sec, Implied), (0xF8, Sed, Implied), (0x78, Sei, Implied),
+  (0x85, Sta, ZeroPage), (0x95, Sta, ZeroPageX), (0x8D, Sta, Absolute), (0x9D, Sta, AbsoluteX),
+  (0x99, Sta, AbsoluteY), (0x81, Sta, IndirectX), (0x91, Sta, IndirectY),
+  (0x86, Stx, ZeroPage), (0x96, Stx, ZeroPageY), (0x8E, Stx, Absolute),
+  (0x84, Sty, ZeroPage), (0x94, Sty, ZeroPageX), (0x8C, Sty, Absolute),
+  (0xAA, Tax, Implied), (0xA8, Tay, Implied), (0xBA, Tsx, Implied), (0x8A, Txa, Implied),
+  (0x9A, Txs, Implied), (0x98, Tya, Implied),
+];
+
+static DECODE: [Option<(Instruction, Mode)>; 256] = decode_table(OPCODES);
+
+const fn decode_table(opcodes: &[(u8, Instruction, Mode)]) -> [Option<(Instruction, Mode)>; 256] {
+  let mut table = [None; 256];
+  let mut i = 0;
+  while i < opcodes.len() {
+    let (code, instruction, mode) = opcodes[i];
+    assert!(table[code as usize].is_none(), "an opcode is listed twice");
+    table[code as usize] = Some((instruction, mode));
+    i += 1;
+  }
+  table
+}
+
+/// Whether the cycle-stepped CPU implements an opcode (the official NMOS 6502 ones).
+pub fn is_implemented(opcode: u8) -> bool {
+  DECODE[opcode as usize].is_some()
+}
+
+/// A 6502 that advances one cycle at a time, making every bus access the real one makes: the
+/// dummy reads of indexed addressing, the double write of read-modify-write instructions, the
+/// stack reads of RTS/RTI/PLA... Interrupts are polled on the last cycle of each instruction
+/// (so the I flag changed by CLI, SEI and PLP takes effect one instruction late).
+#[derive(Clone)]
+pub struct CycleCpu {
+  pub accumulator: u8,
+  pub register_x: u8,
+  pub register_y: u8,
+  pub status: u8,
+  pub stack_pointer: u8,
+  pub program_counter: u16,
+  /// Whether ADC and SBC honour the decimal flag (the NES CPU has no decimal mode).
+  pub decimal_mode: bool,
+  cycles: u64,
+  /// Cycle of the current instruction, 0 when the next one fetches an opcode.
+  cycle: u8,
+  instruction: Instruction,
+  mode: Mode,
+  /// The BRK sequence is running for an NMI or IRQ rather than a BRK opcode.
+  hardware_interrupt: bool,
+  addr: u16,
+  pointer: u8,
+  data: u8,
+  page_crossed: bool,
+  nmi_pending: bool,
+  interrupt_latched: bool,
+}
+
+impl Default for CycleCpu {
+  fn default() -> Self {
+    CycleCpu {
+      accumulator: 0,
+      register_x: 0,
+      register_y: 0,
+      status: UNUSED | INTERRUPT_DISABLE,
+      stack_pointer: 0,
+      program_counter: 0,
+      decimal_mode: true,
+      cycles: 0,
+      cycle: 0,
+      instruction: Nop,
+      mode: Implied,
+      hardware_interrupt: false,
+      addr: 0,
+      pointer: 0,
+      data: 0,
+      page_crossed: false,
+      nmi_pending: false,
+      interrupt_latched: false,
+    }
+  }
+}
+
+impl CycleCpu {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Number of cycles run.
+  pub fn cycles(&self) -> u64 {
+    self.cycles
+  }
+
+  /// Whether the last cycle finished an instruction (the next one fetches an opcode).
+  pub fn at_instruction_boundary(&self) -> bool {
+    self.cycle == 0
+  }
+
+  /// The reset sequence: 7 cycles, with the three stack writes of an interrupt turned into reads,
+  /// then the program counter is loaded from the reset vector.
+  pub fn reset<B: CpuBus>(&mut self, bus: &mut B) {
+    bus.read(self.program_counter);
+    bus.read(self.program_counter);
+    for _ in 0..3 {
+      bus.read(STACK_PAGE | self.stack_pointer as u16);
+      self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+    }
+    self.status |= INTERRUPT_DISABLE;
+    let lo = bus.read(RESET_VECTOR);
+    let hi = bus.read(RESET_VECTOR + 1);
+    self.program_counter = u16::from_le_bytes([lo, hi]);
+
+    self.cycles += 7;
+    self.cycle = 0;
+    self.nmi_pending = false;
+    self.interrupt_latched = false;
+  }
+
+  /// Runs one cycle. An unknown or jam opcode is an error, and the CPU stays on it.
+  pub fn tick<B: CpuBus>(&mut self, bus: &mut B) -> Result<(), CpuError> {
+    if bus.poll_nmi() {
+      self.nmi_pending = true;
+    }
+    self.cycles += 1;
+
+    if self.cycle == 0 {
+      return self.fetch_opcode(bus);
+    }
+    self.cycle += 1;
+    self.execute_cycle(bus);
+    Ok(())
+  }
+
+  /// Runs the cycles of one instruction (or of an interrupt sequence) and returns how many.
+  pub fn step<B: CpuBus>(&mut self, bus: &mut B) -> Result<u64, CpuError> {
+    let start = self.cycles;
+    loop {
+      self.tick(bus)?;
+      if self.cycle == 0 {
+        return Ok(self.cycles - start);
+      }
+    }
+  }
+
+  fn fetch_opcode<B: CpuBus>(&mut self, bus: &mut B) -> Result<(), CpuError> {
+    let pc = self.program_counter;
+    self.page_crossed = false;
+
+    if self.interrupt_latched {
+      self.interrupt_latched = false;
+      bus.read(pc); // the opcode is fetched, then dropped
+      self.instruction = Brk;
+      self.mode = Implied;
+      self.hardware_interrupt = true;
+      self.cycle = 1;
+      return Ok(());
+    }
+
+    let opcode = bus.read(pc);
+    match DECODE[opcode as usize] {
+      Some((instruction, mode)) => {
+        self.instruction = instruction;
+        self.mode = mode;
+        self.hardware_interrupt = false;
+        self.program_counter = pc.wrapping_add(1);
+        self.cycle = 1;
+        Ok(())
+      }
+      None if JAM_OPCODES.contains(&opcode) => Err(CpuError::Jammed { opcode, pc }),
+      None => Err(CpuError::UnknownOpcode { opcode, pc }),
+    }
+  }
+
+  fn execute_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.instruction {
+      Brk => self.interrupt_cycle(bus),
+      Jsr => self.jsr_cycle(bus),
+      Rts => self.rts_cycle(bus),
+      Rti => self.rti_cycle(bus),
+      Pha | Php => self.push_cycle(bus),
+      Pla | Plp => self.pull_cycle(bus),
+      Jmp => self.jmp_cycle(bus),
+      Bcc | Bcs | Beq | Bmi | Bne | Bpl | Bvc | Bvs => self.branch_cycle(bus),
+      _ => match self.mode {
+        Implied | Accumulator => {
+          self.last_cycle(bus);
+          bus.read(self.program_counter);
+          self.implied();
+        }
+        Immediate => {
+          self.last_cycle(bus);
+          let data = self.fetch(bus);
+          self.operate_read(data);
+        }
+        _ => self.memory_cycle(bus),
+      },
+    }
+  }
+
+  fn fetch<B: CpuBus>(&mut self, bus: &mut B) -> u8 {
+    let data = bus.read(self.program_counter);
+    self.program_counter = self.program_counter.wrapping_add(1);
+    data
+  }
+
+  fn push<B: CpuBus>(&mut self, bus: &mut B, data: u8) {
+    bus.write(STACK_PAGE | self.stack_pointer as u16, data);
+    self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+  }
+
+  fn pull<B: CpuBus>(&mut self, bus: &mut B) -> u8 {
+    self.stack_pointer = self.stack_pointer.wrapping_add(1);
+    bus.read(STACK_PAGE | self.stack_pointer as u16)
+  }
+
+  fn read_stack<B: CpuBus>(&mut self, bus: &mut B) {
+    bus.read(STACK_PAGE | self.stack_pointer as u16);
+  }
+
+  /// Latches whether to take an interrupt after this instruction, from the state before its last
+  /// cycle.
+  fn poll_interrupts<B: CpuBus>(&mut self, bus: &mut B) {
+    self.interrupt_latched =
+      self.nmi_pending || (bus.irq() && self.status & INTERRUPT_DISABLE == 0);
+  }
+
+  fn last_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    self.poll_interrupts(bus);
+    self.cycle = 0;
+  }
+
+  /// BRK, and the NMI and IRQ sequences (which don't skip a padding byte nor set the B flag).
+  fn interrupt_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        bus.read(self.program_counter);
+        if !self.hardware_interrupt {
+          self.program_counter = self.program_counter.wrapping_add(1);
+        }
+      }
+      3 => self.push(bus, (self.program_counter >> 8) as u8),
+      4 => self.push(bus, self.program_counter as u8),
+      5 => {
+        let brk = if self.hardware_interrupt { 0 } else { BREAK };
+        self.push(bus, self.status | UNUSED | brk);
+        // an NMI by now takes over the sequence, even a BRK's
+        self.addr = if std::mem::take(&mut self.nmi_pending) {
+          NMI_VECTOR
+        } else {
+          IRQ_VECTOR
+        };
+      }
+      6 => {
+        self.data = bus.read(self.addr);
+        self.status |= INTERRUPT_DISABLE;
+      }
+      _ => {
+        // the first instruction of the handler always runs
+        self.cycle = 0;
+        self.interrupt_latched = false;
+        let hi = bus.read(self.addr.wrapping_add(1));
+        self.program_counter = u16::from_le_bytes([self.data, hi]);
+      }
+    }
+  }
+
+  fn jsr_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => self.data = self.fetch(bus),
+      3 => self.read_stack(bus),
+      4 => self.push(bus, (self.program_counter >> 8) as u8),
+      5 => self.push(bus, self.program_counter as u8),
+      _ => {
+        self.last_cycle(bus);
+        let hi = bus.read(self.program_counter);
+        self.program_counter = u16::from_le_bytes([self.data, hi]);
+      }
+    }
+  }
+
+  fn rts_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        bus.read(self.program_counter);
+      }
+      3 => self.read_stack(bus),
+      4 => self.data = self.pull(bus),
+      5 => {
+        let hi = self.pull(bus);
+        self.program_counter = u16::from_le_bytes([self.data, hi]);
+      }
+      _ => {
+        self.last_cycle(bus);
+        self.fetch(bus);
+      }
+    }
+  }
+
+  fn rti_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        bus.read(self.program_counter);
+      }
+      3 => self.read_stack(bus),
+      4 => {
+        let status = self.pull(bus);
+        self.set_status(status);
+      }
+      5 => self.data = self.pull(bus),
+      _ => {
+        self.last_cycle(bus);
+        let hi = self.pull(bus);
+        self.program_counter = u16::from_le_bytes([self.data, hi]);
+      }
+    }
+  }
+
+  fn push_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        bus.read(self.program_counter);
+      }
+      _ => {
+        self.last_cycle(bus);
+        let data = match self.instruction {
+          Pha => self.accumulator,
+          _ => self.status | BREAK | UNUSED,
+        };
+        self.push(bus, data);
+      }
+    }
+  }
+
+  fn pull_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        bus.read(self.program_counter);
+      }
+      3 => self.read_stack(bus),
+      _ => {
+        self.last_cycle(bus);
+        let data = self.pull(bus);
+        match self.instruction {
+          Pla => {
+            self.accumulator = data;
+            self.set_zero_and_negative(data);
+          }
+          _ => self.set_status(data),
+        }
+      }
+    }
+  }
+
+  fn jmp_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match (self.mode, self.cycle) {
+      (_, 2) => self.addr = self.fetch(bus) as u16,
+      (Absolute, _) => {
+        self.last_cycle(bus);
+        let hi = bus.read(self.program_counter);
+        self.program_counter = u16::from_le_bytes([self.addr as u8, hi]);
+      }
+      (_, 3) => self.addr |= (self.fetch(bus) as u16) << 8,
+      (_, 4) => self.data = bus.read(self.addr),
+      _ => {
+        self.last_cycle(bus);
+        // the pointer's high byte is read without carrying into the page
+        let hi_addr = (self.addr & 0xFF00) | (self.addr.wrapping_add(1) & 0x00FF);
+        let hi = bus.read(hi_addr);
+        self.program_counter = u16::from_le_bytes([self.data, hi]);
+      }
+    }
+  }
+
+  /// Branches poll interrupts before their operand fetch, and again before the page fix-up:
+  /// a taken branch that stays in the page doesn't poll on its last cycle.
+  fn branch_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match self.cycle {
+      2 => {
+        self.poll_interrupts(bus);
+        let offset = self.fetch(bus);
+        if !self.branch_taken() {
+          self.cycle = 0;
+          return;
+        }
+        self.addr = self.program_counter.wrapping_add(offset as i8 as u16);
+      }
+      3 => {
+        bus.read(self.program_counter);
+        if self.addr & 0xFF00 == self.program_counter & 0xFF00 {
+          self.program_counter = self.addr;
+          self.cycle = 0;
+        } else {
+          self.program_counter = (self.program_counter & 0xFF00) | (self.addr & 0x00FF);
+        }
+      }
+      _ => {
+        self.last_cycle(bus);
+        bus.read(self.program_counter);
+        self.program_counter = self.addr;
+      }
+    }
+  }
+
+  fn branch_taken(&self) -> bool {
+    let flag = |flag: u8| self.status & flag != 0;
+    match self.instruction {
+      Bcc => !flag(CARRY),
+      Bcs => flag(CARRY),
+      Beq => flag(ZERO),
+      Bne => !flag(ZERO),
+      Bmi => flag(NEGATIVE),
+      Bpl => !flag(NEGATIVE),
+      Bvs => flag(OVERFLOW),
+      _ => !flag(OVERFLOW),
+    }
+  }
+
+  /// Cycles of the instructions with an operand in memory: the address cycles, then the
+  /// accesses of a read, a write or a read-modify-write.
+  fn memory_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    let indexed = matches!(self.mode, AbsoluteX | AbsoluteY | IndirectY);
+    let address_cycles = match self.mode {
+      ZeroPage => 1,
+      ZeroPageX | ZeroPageY | Absolute | AbsoluteX | AbsoluteY => 2,
+      IndirectY => 3,
+      _ => 4,
+    };
+    if self.cycle <= address_cycles + 1 {
+      self.address_cycle(bus);
+      return;
+    }
+
+    let phase = self.cycle - address_cycles - 1;
+    // without the carry into the high byte, which takes a cycle to fix
+    let unfixed_addr = if self.page_crossed {
+      self.addr.wrapping_sub(0x100)
+    } else {
+      self.addr
+    };
+    match self.instruction.access() {
+      Access::Read => {
+        if phase == 1 && indexed && self.page_crossed {
+          bus.read(unfixed_addr);
+          return;
+        }
+        self.last_cycle(bus);
+        let data = bus.read(self.addr);
+        self.operate_read(data);
+      }
+      Access::Write => {
+        if phase == 1 && indexed {
+          bus.read(unfixed_addr);
+          return;
+        }
+        self.last_cycle(bus);
+        let data = match self.instruction {
+          Sta => self.accumulator,
+          Stx => self.register_x,
+          _ => self.register_y,
+        };
+        bus.write(self.addr, data);
+      }
+      Access::ReadModifyWrite => match if indexed { phase } else { phase + 1 } {
+        1 => {
+          bus.read(unfixed_addr);
+        }
+        2 => self.data = bus.read(self.addr),
+        // the unmodified value is written back while the ALU works
+        3 => bus.write(self.addr, self.data),
+        _ => {
+          self.last_cycle(bus);
+          let data = self.modify(self.data);
+          bus.write(self.addr, data);
+        }
+      },
+    }
+  }
+
+  fn address_cycle<B: CpuBus>(&mut self, bus: &mut B) {
+    match (self.mode, self.cycle) {
+      (ZeroPage, _) => self.addr = self.fetch(bus) as u16,
+      (ZeroPageX | ZeroPageY, 2) => self.pointer = self.fetch(bus),
+      (ZeroPageX | ZeroPageY, _) => {
+        bus.read(self.pointer as u16);
+        let index = if self.mode == ZeroPageX {
+          self.register_x
+        } else {
+          self.register_y
+        };
+        self.addr = self.pointer.wrapping_add(index) as u16;
+      }
+      (Absolute | AbsoluteX | AbsoluteY, 2) => self.addr = self.fetch(bus) as u16,
+      (Absolute, _) => self.addr |= (self.fetch(bus) as u16) << 8,
+      (AbsoluteX, _) => {
+        let hi = self.fetch(bus);
+        self.index_address(hi, self.register_x);
+      }
+      (AbsoluteY, _) => {
+        let hi = self.fetch(bus);
+        self.index_address(hi, self.register_y);
+      }
+      (IndirectX | IndirectY, 2) => self.pointer = self.fetch(bus),
+      (IndirectX, 3) => {
+        bus.read(self.pointer as u16);
+        self.pointer = self.pointer.wrapping_add(self.register_x);
+      }
+      (IndirectX, 4) => self.addr = bus.read(self.pointer as u16) as u16,
+      (IndirectX, _) => {
+        self.addr |= (bus.read(self.pointer.wrapping_add(1) as u16) as u16) << 8;
+      }
+      (IndirectY, 3) => self.addr = bus.read(self.pointer as u16) as u16,
+      (_, _) => {
+        let hi = bus.read(self.pointer.wrapping_add(1) as u16);
+        self.index_address(hi, self.register_y);
+      }
+    }
+  }
+
+  /// Adds the index to the address whose low byte is in `addr`.
+  fn index_address(&mut self, hi: u8, index: u8) {
+    let base = ((hi as u16) << 8) | self.addr;
+    self.addr = base.wrapping_add(index as u16);
+    self.page_crossed = base & 0xFF00 != self.addr & 0xFF00;
+  }
+
+  fn set_status(&mut self, data: u8) {
+    // the B flag only exists on the stack
+    self.status = (data & !BREAK) | UNUSED;
+  }
+
+  fn set_flag(&mut self, flag: u8, value: bool) {
+    if value {
+      self.status |= flag;
+    } else {
+      self.status &= !flag;
+    }
+  }
+
+  fn set_zero_and_negative(&mut self, value: u8) {
+    self.set_flag(ZERO, value == 0);
+    self.set_flag(NEGATIVE, value & 0x80 != 0);
+  }
+
+  fn operate_read(&mut self, data: u8) {
+    match self.instruction {
+      Adc => self.adc(data),
+      Sbc => self.sbc(data),
+      And => {
+        self.accumulator &= data;
+        self.set_zero_and_negative(self.accumulator);
+      }
+      Eor => {
+        self.accumulator ^= data;
+        self.set_zero_and_negative(self.accumulator);
+      }
+      Ora => {
+        self.accumulator |= data;
+        self.set_zero_and_negative(self.accumulator);
+      }
+      Bit => {
+        self.set_flag(ZERO, self.accumulator & data == 0);
+        self.set_flag(NEGATIVE, data & NEGATIVE != 0);
+        self.set_flag(OVERFLOW, data & OVERFLOW != 0);
+      }
+      Cmp => self.compare(self.accumulator, data),
+      Cpx => self.compare(self.register_x, data),
+      Cpy => self.compare(self.register_y, data),
+      Lda => {
+        self.accumulator = data;
+        self.set_zero_and_negative(data);
+      }
+      Ldx => {
+        self.register_x = data;
+        self.set_zero_and_negative(data);
+      }
+      Ldy => {
+        self.register_y = data;
+        self.set_zero_and_negative(data);
+      }
+      _ => {}
+    }
+  }
+
+  fn compare(&mut self, register: u8, data: u8) {
+    self.set_flag(CARRY, register >= data);
+    self.set_zero_and_negative(register.wrapping_sub(data));
+  }
+
+  fn adc(&mut self, data: u8) {
+    let a = self.accumulator;
+    let carry = self.status & CARRY;
+    if self.decimal_mode && self.status & DECIMAL != 0 {
+      // the NMOS 6502 sets Z from the binary sum, and N and V from the sum before the
+      // high nibble is adjusted
+      let mut lo = (a & 0x0F) + (data & 0x0F) + carry;
+      if lo > 9 {
+        lo += 6;
+      }
+      let mut hi = (a >> 4) + (data >> 4) + (lo > 0x0F) as u8;
+      self.set_flag(ZERO, a.wrapping_add(data).wrapping_add(carry) == 0);
+      self.set_flag(NEGATIVE, hi & 0x08 != 0);
+      self.set_flag(OVERFLOW, !(a ^ data) & (a ^ (hi << 4)) & 0x80 != 0);
+      if hi > 9 {
+        hi += 6;
+      }
+      self.set_flag(CARRY, hi > 0x0F);
+      self.accumulator = (hi << 4) | (lo & 0x0F);
+      return;
+    }
+
+    let sum = a as u16 + data as u16 + carry as u16;
+    let result = sum as u8;
+    self.set_flag(CARRY, sum > 0xFF);
+    self.set_flag(OVERFLOW, (a ^ result) & (data ^ result) & 0x80 != 0);
+    self.accumulator = result;
+    self.set_zero_and_negative(result);
+  }
+
+  fn sbc(&mut self, data: u8) {
+    if !(self.decimal_mode && self.status & DECIMAL != 0) {
+      self.adc(!data);
+      return;
+    }
+
+    // flags come from the binary difference on the NMOS 6502
+    let a = self.accumulator;
+    let borrow = 1 - (self.status & CARRY);
+    let difference = (a as u16)
+      .wrapping_sub(data as u16)
+      .wrapping_sub(borrow as u16);
+    let mut lo = (a & 0x0F).wrapping_sub(data & 0x0F).wrapping_sub(borrow);
+    let lo_borrow = (lo as i8) < 0;
+    if lo_borrow {
+      lo = lo.wrapping_sub(6);
+    }
+    let mut hi = (a >> 4)
+      .wrapping_sub(data >> 4)
+      .wrapping_sub(lo_borrow as u8);
+    if (hi as i8) < 0 {
+      hi = hi.wrapping_sub(6);
+    }
+
+    self.set_zero_and_negative(difference as u8);
+    self.set_flag(OVERFLOW, (a ^ data) & (a ^ difference as u8) & 0x80 != 0);
+    self.set_flag(CARRY, difference & 0xFF00 == 0);
+    self.accumulator = (hi << 4) | (lo & 0x0F);
+  }
+
+  fn modify(&mut self, data: u8) -> u8 {
+    let carry = self.status & CARRY;
+    let (result, carry_out) = match self.instruction {
+      Asl => (data << 1, Some(data & 0x80 != 0)),
+      Lsr => (data >> 1, Some(data & 0x01 != 0)),
+      Rol => ((data << 1) | carry, Some(data & 0x80 != 0)),
+      Ror => ((data >> 1) | (carry << 7), Some(data & 0x01 != 0)),
+      Inc => (data.wrapping_add(1), None),
+      _ => (data.wrapping_sub(1), None),
+    };
+    if let Some(carry_out) = carry_out {
+      self.set_flag(CARRY, carry_out);
+    }
+    self.set_zero_and_negative(result);
+    result
+  }
+
+  fn implied(&mut self) {
+    match self.instruction {
+      Clc => self.set_flag(CARRY, false),
+      Cld => self.set_flag(DECIMAL, false),
+      Cli => self.set_flag(INTERRUPT_DISABLE, false),
+      Clv => self.set_flag(OVERFLOW, false),
+      Sec => self.set_flag(CARRY, true),
+      Sed => self.set_flag(DECIMAL, true),
+      Sei => self.set_flag(INTERRUPT_DISABLE, true),
+      Dex => {
+        self.register_x = self.register_x.wrapping_sub(1);
+        self.set_zero_and_negative(self.register_x);
+      }
+      Dey => {
+        self.register_y = self.register_y.wrapping_sub(1);
+        self.set_zero_and_negative(self.register_y);
+      }
+      Inx => {
+        self.register_x = self.register_x.wrapping_add(1);
+        self.set_zero_and_negative(self.register_x);
+      }
+      Iny => {
+        self.register_y = self.register_y.wrapping_add(1);
+        self.set_zero_and_negative(self.register_y);
+      }
+      Tax => {
+        self.register_x = self.accumulator;
+        self.set_zero_and_negative(self.register_x);
+      }
+      Tay => {
+        self.register_y = self.accumulator;
+        self.set_zero_and_negative(self.register_y);
+      }
+      Tsx => {
+        self.register_x = self.stack_pointer;
+        self.set_zero_and_negative(self.register_x);
+      }
+      Txa => {
+        self.accumulator = self.register_x;
+        self.set_zero_and_negative(self.accumulator);
+      }
+      Tya => {
+        self.accumulator = self.register_y;
+        self.set_zero_and_negative(self.accumulator);
+      }
+      Txs => self.stack_pointer = self.register_x,
+      Nop => {}
+      // ASL A, LSR A, ROL A and ROR A
+      _ => self.accumulator = self.modify(self.accumulator),
+    }
+  }
+}
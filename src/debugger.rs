@@ -0,0 +1,231 @@
+use crate::cpu::CPU;
+use crate::disassembler;
+use crate::ram_search::{Comparison, Operand, RamSearch, ValueType};
+use crate::symbols::SymbolTable;
+
+const DEFAULT_SEARCH_RANGE: std::ops::RangeInclusive<u16> = 0x0000..=0x07FF;
+/// Up to this many candidates are listed after each search step.
+const LISTED_CANDIDATES: usize = 16;
+const DEFAULT_DISASSEMBLED_INSTRUCTIONS: i32 = 10;
+const HELP: &str = "\
+regs                                 show the registers
+mem ADDR [LEN]                       dump memory
+disasm [ADDR] [COUNT]                disassemble (from the PC by default)
+stack                                show the stack and the return addresses on it
+symbols FILE                         load labels from a .dbg, .nl or .mlb file
+search start [u8|i8|u16|i16] [A-B]   snapshot RAM ($0000-$07FF by default)
+search eq|ne|gt|lt [VALUE]           compare with VALUE, or with the last snapshot
+search changed N                     keep values that changed by N since the last snapshot
+search list                          show the candidates";
+
+/// Text commands for inspecting the CPU, for the frontends to offer as a debugger console.
+/// Numbers are decimal, or hex with a `$` or `0x` prefix. Addresses can also be labels.
+#[derive(Default)]
+pub struct Debugger {
+  pub ram_search: Option<RamSearch>,
+  pub symbols: SymbolTable,
+}
+
+impl Debugger {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Runs a command and returns its output.
+  pub fn execute(&mut self, cpu: &mut CPU, command: &str) -> Result<String, String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.as_slice() {
+      ["regs"] => Ok(format!(
+        "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} P:{:08b} CYC:{}",
+        cpu.program_counter,
+        cpu.accumulator,
+        cpu.register_x,
+        cpu.register_y,
+        cpu.stack_pointer,
+        cpu.status,
+        cpu.cycles()
+      )),
+      ["mem", addr, rest @ ..] => {
+        let start = self.parse_address(addr)?;
+        let len = rest.first().map_or(Ok(16), |len| parse_number(len))? as u16;
+        Ok(dump_memory(cpu, start, len))
+      }
+      ["disasm", rest @ ..] => {
+        let start = match rest.first() {
+          Some(addr) => self.parse_address(addr)?,
+          None => cpu.program_counter,
+        };
+        let count = rest
+          .get(1)
+          .map_or(Ok(DEFAULT_DISASSEMBLED_INSTRUCTIONS), |count| {
+            parse_number(count)
+          })?;
+        Ok(self.disassemble(cpu, start, count))
+      }
+      ["stack"] => Ok(self.dump_stack(cpu)),
+      ["symbols", path] => {
+        let symbols = SymbolTable::load(path)?;
+        self.symbols.extend(&symbols);
+        Ok(format!("{} labels", symbols.len()))
+      }
+      ["search", args @ ..] => self.search(cpu, args),
+      ["help"] | [] => Ok(HELP.to_string()),
+      _ => Err(format!("unknown command {:?}, try help", command)),
+    }
+  }
+
+  /// A label, or a number.
+  fn parse_address(&self, text: &str) -> Result<u16, String> {
+    match self.symbols.address(text) {
+      Some(address) => Ok(address),
+      None => Ok(parse_number(text)? as u16),
+    }
+  }
+
+  fn disassemble(&self, cpu: &CPU, start: u16, count: i32) -> String {
+    let mut lines = Vec::new();
+    let mut addr = start;
+    for _ in 0..count {
+      if let Some(label) = self.symbols.label(addr) {
+        lines.push(format!("{}:", label));
+      }
+      let (instruction, size) =
+        disassembler::disassemble_with_symbols(&cpu.bus, addr, &self.symbols);
+      lines.push(format!("${:04X}  {}", addr, instruction));
+      addr = addr.wrapping_add(size);
+    }
+
+    lines.join("\n")
+  }
+
+  fn dump_stack(&self, cpu: &CPU) -> String {
+    let bytes: Vec<String> = cpu
+      .stack_contents()
+      .iter()
+      .map(|byte| format!("{:02X}", byte))
+      .collect();
+    let mut lines = vec![format!(
+      "SP:{:02X} [{}]",
+      cpu.stack_pointer,
+      bytes.join(" ")
+    )];
+    for frame in cpu.call_stack() {
+      let subroutine = self
+        .symbols
+        .label(frame.subroutine)
+        .map_or_else(|| format!("${:04X}", frame.subroutine), String::from);
+      lines.push(format!(
+        "${:04X}: returns to ${:04X} (JSR {} at ${:04X})",
+        frame.stack_addr, frame.return_addr, subroutine, frame.caller
+      ));
+    }
+
+    lines.join("\n")
+  }
+
+  fn search(&mut self, cpu: &CPU, args: &[&str]) -> Result<String, String> {
+    if let ["start", options @ ..] = args {
+      let mut value_type = ValueType::default();
+      let mut range = DEFAULT_SEARCH_RANGE;
+      for option in options {
+        match *option {
+          "u8" => value_type = ValueType::U8,
+          "i8" => value_type = ValueType::I8,
+          "u16" => value_type = ValueType::U16,
+          "i16" => value_type = ValueType::I16,
+          _ => {
+            let (start, end) = option
+              .split_once('-')
+              .ok_or(format!("invalid option {:?}", option))?;
+            range = parse_number(start)? as u16..=parse_number(end)? as u16;
+          }
+        }
+      }
+
+      let mut search = RamSearch::new(range, value_type);
+      search.reset(&cpu.bus);
+      let output = format!("{} candidates", search.candidates().len());
+      self.ram_search = Some(search);
+      return Ok(output);
+    }
+
+    let search = self
+      .ram_search
+      .as_mut()
+      .ok_or("no search in progress, use search start")?;
+    let (comparison, operand) = match args {
+      ["list"] => return Ok(list_candidates(search, usize::MAX)),
+      ["changed", delta] => (
+        Comparison::ChangedBy(parse_number(delta)?),
+        Operand::Previous,
+      ),
+      [comparison, value @ ..] => {
+        let comparison = match *comparison {
+          "eq" => Comparison::Equal,
+          "ne" => Comparison::NotEqual,
+          "gt" => Comparison::Greater,
+          "lt" => Comparison::Less,
+          _ => return Err(format!("unknown comparison {:?}", comparison)),
+        };
+        let operand = match value.first() {
+          Some(value) => Operand::Constant(parse_number(value)?),
+          None => Operand::Previous,
+        };
+        (comparison, operand)
+      }
+      [] => return Err("missing search command".to_string()),
+    };
+
+    search.filter(&cpu.bus, comparison, operand);
+    Ok(list_candidates(search, LISTED_CANDIDATES))
+  }
+}
+
+fn parse_number(text: &str) -> Result<i32, String> {
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, text),
+  };
+  let value = match digits
+    .strip_prefix('$')
+    .or_else(|| digits.strip_prefix("0x"))
+  {
+    Some(hex) => i32::from_str_radix(hex, 16),
+    None => digits.parse(),
+  }
+  .map_err(|_| format!("invalid number {:?}", text))?;
+
+  Ok(if negative { -value } else { value })
+}
+
+fn dump_memory(cpu: &CPU, start: u16, len: u16) -> String {
+  let bytes: Vec<u8> = (0..len)
+    .map(|offset| cpu.bus.peek(start.wrapping_add(offset)))
+    .collect();
+  bytes
+    .chunks(16)
+    .enumerate()
+    .map(|(row, chunk)| {
+      let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+      format!(
+        "{:04X}: {}",
+        start.wrapping_add(row as u16 * 16),
+        hex.join(" ")
+      )
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn list_candidates(search: &RamSearch, max: usize) -> String {
+  let candidates = search.candidates();
+  let mut lines = vec![format!("{} candidates", candidates.len())];
+  if candidates.len() <= max {
+    lines.extend(
+      candidates
+        .iter()
+        .map(|addr| format!("${:04X} = {}", addr, search.value(*addr))),
+    );
+  }
+  lines.join("\n")
+}
@@ -0,0 +1,52 @@
+use crate::bus::Bus;
+use crate::cpu::AddressingMode;
+use crate::opcodes;
+use crate::symbols::SymbolTable;
+
+/// Disassembles the instruction at `addr`, in the usual assembler syntax (`LDA ($10),Y`,
+/// `BNE $0612`). Branch targets are resolved to absolute addresses. Bytes that are not a known
+/// opcode come out as `.byte $XX`.
+///
+/// Returns the text and the size of the instruction in bytes.
+pub fn disassemble(bus: &Bus, addr: u16) -> (String, u16) {
+  disassemble_with_symbols(bus, addr, &SymbolTable::new())
+}
+
+/// Like `disassemble`, with the addresses that have a label replaced by it (`JSR update_snake`).
+pub fn disassemble_with_symbols(bus: &Bus, addr: u16, symbols: &SymbolTable) -> (String, u16) {
+  let code = bus.peek(addr);
+  let opcode = match opcodes::decode(code) {
+    Some(opcode) => opcode,
+    None => return (format!(".byte ${:02X}", code), 1),
+  };
+
+  let lsb = bus.peek(addr.wrapping_add(1));
+  let word = u16::from_le_bytes([lsb, bus.peek(addr.wrapping_add(2))]);
+  let zero_page = symbols
+    .label(lsb as u16)
+    .map_or_else(|| format!("${:02X}", lsb), String::from);
+  let absolute = |address: u16| {
+    symbols
+      .label(address)
+      .map_or_else(|| format!("${:04X}", address), String::from)
+  };
+  let operand = match opcode.addressing_mode {
+    AddressingMode::Accumulator => " A".to_string(),
+    AddressingMode::Immediate => format!(" #${:02X}", lsb),
+    AddressingMode::ZeroPage => format!(" {}", zero_page),
+    AddressingMode::ZeroPageX => format!(" {},X", zero_page),
+    AddressingMode::ZeroPageY => format!(" {},Y", zero_page),
+    AddressingMode::Absolute => format!(" {}", absolute(word)),
+    AddressingMode::AbsoluteX => format!(" {},X", absolute(word)),
+    AddressingMode::AbsoluteY => format!(" {},Y", absolute(word)),
+    AddressingMode::IndirectX => format!(" ({},X)", zero_page),
+    AddressingMode::IndirectY => format!(" ({}),Y", zero_page),
+    AddressingMode::Relative => {
+      let target = addr.wrapping_add(2).wrapping_add(lsb as i8 as u16);
+      format!(" {}", absolute(target))
+    }
+    AddressingMode::NoneAddressing => String::new(),
+  };
+
+  (format!("{}{}", opcode.name, operand), opcode.bytes as u16)
+}
@@ -0,0 +1,151 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::cpu::{CpuError, StepOutcome, CPU};
+
+pub const SCREEN_WIDTH: usize = 32;
+pub const SCREEN_HEIGHT: usize = 32;
+/// The screen memory: one byte per pixel, row by row, of which the low nibble is the colour.
+pub const SCREEN_START: u16 = 0x0200;
+pub const SCREEN_END: u16 = 0x05FF;
+/// A new random byte is written here before every instruction.
+pub const RANDOM_ADDR: u16 = 0xFE;
+/// The ASCII code of the last key pressed.
+pub const LAST_KEY_ADDR: u16 = 0xFF;
+/// Size of an RGB24 frame of the screen.
+pub const FRAME_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT * 3;
+/// Instructions run by `run_frame` by default: about the pace of the snake game at 60 frames
+/// per second.
+pub const INSTRUCTIONS_PER_FRAME: usize = 240;
+
+/// The 16 colours of the easy6502 screen.
+pub const PALETTE: [(u8, u8, u8); 16] = [
+  (0x00, 0x00, 0x00),
+  (0xFF, 0xFF, 0xFF),
+  (0x88, 0x00, 0x00),
+  (0xAA, 0xFF, 0xEE),
+  (0xCC, 0x44, 0xCC),
+  (0x00, 0xCC, 0x55),
+  (0x00, 0x00, 0xAA),
+  (0xEE, 0xEE, 0x77),
+  (0xDD, 0x88, 0x55),
+  (0x66, 0x44, 0x00),
+  (0xFF, 0x77, 0x77),
+  (0x33, 0x33, 0x33),
+  (0x77, 0x77, 0x77),
+  (0xAA, 0xFF, 0x66),
+  (0x00, 0x88, 0xFF),
+  (0xBB, 0xBB, 0xBB),
+];
+
+/// The colour of a byte of screen memory.
+pub fn color(byte: u8) -> (u8, u8, u8) {
+  PALETTE[(byte & 0x0F) as usize]
+}
+
+/// The virtual machine of easy6502 (<https://skilldrick.github.io/easy6502/>): a CPU with the
+/// program at $0600, a 32x32 screen, a random number generator and a keyboard.
+pub struct Easy6502Machine {
+  pub cpu: CPU,
+  /// Number of instructions `run_frame` runs.
+  pub instructions_per_frame: usize,
+  frame: [u8; FRAME_SIZE],
+  rng: StdRng,
+}
+
+impl Default for Easy6502Machine {
+  fn default() -> Self {
+    Self::with_rng(StdRng::from_entropy())
+  }
+}
+
+impl Easy6502Machine {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// A machine whose random bytes are always the same, for tests and recordings.
+  pub fn with_seed(seed: u64) -> Self {
+    Self::with_rng(StdRng::seed_from_u64(seed))
+  }
+
+  fn with_rng(rng: StdRng) -> Self {
+    Easy6502Machine {
+      cpu: CPU::new(),
+      instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+      frame: [0; FRAME_SIZE],
+      rng,
+    }
+  }
+
+  /// Loads a program at $0600 and resets the CPU to run it.
+  pub fn load(&mut self, program: &[u8]) -> Result<(), CpuError> {
+    self.cpu.load(program.to_vec())?;
+    self.cpu.reset();
+    Ok(())
+  }
+
+  /// Tells the program a key was pressed.
+  pub fn press_key(&mut self, key: u8) {
+    self.cpu.mem_write(LAST_KEY_ADDR, key);
+  }
+
+  /// Runs one instruction, with a fresh random byte.
+  pub fn step(&mut self) -> Result<StepOutcome, CpuError> {
+    self.cpu.mem_write(RANDOM_ADDR, self.rng.gen());
+    self.cpu.step()
+  }
+
+  /// Runs `instructions_per_frame` instructions (fewer if the program stops) and redraws the
+  /// frame. Freeze cheats are applied first.
+  pub fn run_frame(&mut self) -> Result<StepOutcome, CpuError> {
+    self.run_frame_with_callback(|_| {})
+  }
+
+  /// Same as `run_frame`, calling `callback` before every instruction.
+  pub fn run_frame_with_callback<F>(&mut self, mut callback: F) -> Result<StepOutcome, CpuError>
+  where
+    F: FnMut(&mut CPU),
+  {
+    self.cpu.bus.apply_freeze_cheats();
+    let mut outcome = StepOutcome::Executed;
+    for _ in 0..self.instructions_per_frame {
+      callback(&mut self.cpu);
+      outcome = self.step()?;
+      if outcome != StepOutcome::Executed {
+        break;
+      }
+    }
+    self.update_frame();
+    Ok(outcome)
+  }
+
+  /// Redraws the frame from the screen memory, returning whether it changed.
+  pub fn update_frame(&mut self) -> bool {
+    let mut changed = false;
+    for (pixel, addr) in self
+      .frame
+      .chunks_exact_mut(3)
+      .zip(SCREEN_START..=SCREEN_END)
+    {
+      let (r, g, b) = color(self.cpu.bus.peek(addr));
+      changed |= pixel != [r, g, b];
+      pixel.copy_from_slice(&[r, g, b]);
+    }
+    changed
+  }
+
+  /// The screen as RGB24, row by row.
+  pub fn frame(&self) -> &[u8; FRAME_SIZE] {
+    &self.frame
+  }
+
+  /// The colour index (0 to 15) of a pixel of the screen.
+  pub fn pixel(&self, x: usize, y: usize) -> u8 {
+    self
+      .cpu
+      .bus
+      .peek(SCREEN_START + (y * SCREEN_WIDTH + x) as u16)
+      & 0x0F
+  }
+}
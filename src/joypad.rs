@@ -0,0 +1,50 @@
+pub const BUTTON_A: u8 = 1 << 0;
+pub const BUTTON_B: u8 = 1 << 1;
+pub const BUTTON_SELECT: u8 = 1 << 2;
+pub const BUTTON_START: u8 = 1 << 3;
+pub const BUTTON_UP: u8 = 1 << 4;
+pub const BUTTON_DOWN: u8 = 1 << 5;
+pub const BUTTON_LEFT: u8 = 1 << 6;
+pub const BUTTON_RIGHT: u8 = 1 << 7;
+
+/// Names of the buttons, in the order the controller reports them.
+pub const BUTTON_NAMES: [&str; 8] = ["a", "b", "select", "start", "up", "down", "left", "right"];
+
+/// The standard controller, in the first port ($4016).
+///
+/// Writing 1 then 0 to bit 0 of $4016 (the strobe) latches the buttons, which are then read one
+/// per read of $4016, in the order of `BUTTON_NAMES`. After the 8th read it reads 1. While the
+/// strobe is 1, every read gives the state of A.
+///
+/// See <https://www.nesdev.org/wiki/Standard_controller>.
+#[derive(Debug, Clone, Default)]
+pub struct Joypad {
+  /// The pressed buttons (`BUTTON_A` | `BUTTON_RIGHT`...).
+  pub buttons: u8,
+  strobe: bool,
+  index: u8,
+}
+
+impl Joypad {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn write(&mut self, data: u8) {
+    self.strobe = data & 1 == 1;
+    if self.strobe {
+      self.index = 0;
+    }
+  }
+
+  pub fn read(&mut self) -> u8 {
+    if self.index > 7 {
+      return 1;
+    }
+    let pressed = (self.buttons >> self.index) & 1;
+    if !self.strobe {
+      self.index += 1;
+    }
+    pressed
+  }
+}
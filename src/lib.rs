@@ -1,3 +1,25 @@
+pub mod bus;
+pub mod capture;
+pub mod cartridge;
+pub mod cheats;
 pub mod cpu;
+pub mod cycle_cpu;
+pub mod debugger;
+pub mod disassembler;
+pub mod easy6502;
+pub mod joypad;
+pub mod loader;
 pub mod opcodes;
+pub mod overlay;
 pub mod games;
+pub mod palette;
+pub mod ppu;
+pub mod ram_search;
+pub mod save_state;
+pub mod region;
+pub mod screenshot;
+pub mod script;
+pub mod symbols;
+pub mod terminal;
+pub mod test_roms;
+pub mod tracer;
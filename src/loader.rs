@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::Path;
+
+/// Bytes to put in memory at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+  pub address: u16,
+  pub data: Vec<u8>,
+}
+
+impl Segment {
+  pub fn new(address: u16, data: Vec<u8>) -> Self {
+    Segment { address, data }
+  }
+
+  /// The address after the last byte (which can be 0x10000 for a segment ending at $FFFF).
+  pub fn end(&self) -> usize {
+    self.address as usize + self.data.len()
+  }
+}
+
+/// File formats `Program::from_file` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  /// Just the bytes, loaded at an address given separately.
+  Raw,
+  /// Commodore PRG: a little-endian load address, then the bytes.
+  Prg,
+  /// Intel HEX records.
+  IntelHex,
+  /// A binary linked by ld65, with its `--dbgfile` telling where each segment goes.
+  Ld65,
+}
+
+impl Format {
+  /// Guesses the format of a file from its extension (and, for ld65 output, whether there is a
+  /// debug file next to it).
+  pub fn detect(path: &Path) -> Format {
+    let extension = path
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+      Some("prg") => Format::Prg,
+      Some("hex") | Some("ihx") => Format::IntelHex,
+      _ if path.with_extension("dbg").is_file() => Format::Ld65,
+      _ => Format::Raw,
+    }
+  }
+}
+
+/// A program to load into memory: its segments and, optionally, the vectors to point at it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+  pub segments: Vec<Segment>,
+  pub reset_vector: Option<u16>,
+  pub nmi_vector: Option<u16>,
+  pub irq_vector: Option<u16>,
+}
+
+impl Program {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a segment, checking it fits in the 64 KiB address space.
+  pub fn add_segment(&mut self, address: u16, data: Vec<u8>) -> Result<(), String> {
+    let segment = Segment::new(address, data);
+    if segment.end() > 0x10000 {
+      return Err(format!(
+        "{} bytes at ${:04X} go past the end of memory",
+        segment.data.len(),
+        address
+      ));
+    }
+
+    self.segments.push(segment);
+    Ok(())
+  }
+
+  /// A single segment at `address`, with the reset vector pointing at it.
+  pub fn from_raw(data: &[u8], address: u16) -> Result<Self, String> {
+    let mut program = Self::new();
+    program.add_segment(address, data.to_vec())?;
+    program.reset_vector = Some(address);
+    Ok(program)
+  }
+
+  /// A Commodore-style PRG file: the load address in the first two bytes, then the program, which
+  /// starts at the load address.
+  pub fn from_prg(data: &[u8]) -> Result<Self, String> {
+    if data.len() < 2 {
+      return Err("the PRG file has no load address".to_string());
+    }
+
+    Self::from_raw(&data[2..], u16::from_le_bytes([data[0], data[1]]))
+  }
+
+  /// Intel HEX records. Contiguous data records are merged into one segment; a start linear
+  /// address record (type 05) sets the reset vector.
+  pub fn from_intel_hex(text: &str) -> Result<Self, String> {
+    let mut program = Self::new();
+    let mut base: u32 = 0;
+
+    for (index, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+      let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+      let hex = line
+        .strip_prefix(':')
+        .ok_or_else(|| error("records start with ':'"))?;
+      let bytes = decode_hex(hex).ok_or_else(|| error("invalid hex digits"))?;
+      if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+        return Err(error("wrong record length"));
+      }
+      if bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return Err(error("bad checksum"));
+      }
+
+      let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+      let data = &bytes[4..bytes.len() - 1];
+      match bytes[3] {
+        0x00 => {
+          let address = base + offset;
+          if address + data.len() as u32 > 0x10000 {
+            return Err(error("data past $FFFF"));
+          }
+          program.append(address as u16, data);
+        }
+        0x01 => break,
+        0x02 if data.len() == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+        0x04 if data.len() == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+        0x03 if data.len() == 4 => {}
+        0x05 if data.len() == 4 => {
+          let address = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+          let address = u16::try_from(address).map_err(|_| error("start address past $FFFF"))?;
+          program.reset_vector = Some(address);
+        }
+        kind => return Err(error(&format!("unsupported record type {:02X}", kind))),
+      }
+    }
+
+    Ok(program)
+  }
+
+  /// A binary linked by ld65, with the text of the debug file it wrote (`--dbgfile`). Every
+  /// segment the debug file places in the binary (`ooffs`) is loaded at its run address.
+  pub fn from_ld65(binary: &[u8], debug_info: &str) -> Result<Self, String> {
+    let mut program = Self::new();
+
+    for line in debug_info.lines() {
+      let Some(("seg", fields)) = line.split_once(char::is_whitespace) else {
+        continue;
+      };
+      let fields = parse_debug_fields(fields);
+      let field = |name: &str| {
+        fields
+          .iter()
+          .find(|(key, _)| *key == name)
+          .map(|(_, value)| value.as_str())
+      };
+      let number = |name: &str| -> Result<Option<usize>, String> {
+        field(name)
+          .map(|value| parse_debug_number(value).ok_or(format!("invalid {} {:?}", name, value)))
+          .transpose()
+      };
+
+      let (Some(start), Some(size), Some(offset)) =
+        (number("start")?, number("size")?, number("ooffs")?)
+      else {
+        continue; // not in the output file (bss, zeropage...)
+      };
+      let name = field("name").unwrap_or("?");
+      let data = offset
+        .checked_add(size)
+        .and_then(|end| binary.get(offset..end))
+        .ok_or(format!("segment {} is past the end of the binary", name))?;
+      let start = u16::try_from(start).map_err(|_| format!("segment {} is past $FFFF", name))?;
+      program.add_segment(start, data.to_vec())?;
+    }
+
+    if program.segments.is_empty() {
+      return Err("the debug file places no segment in the binary".to_string());
+    }
+    Ok(program)
+  }
+
+  /// Loads a file, in the format its name suggests (see `Format::detect`). Raw binaries need a
+  /// load address.
+  pub fn from_file(path: impl AsRef<Path>, load_address: Option<u16>) -> Result<Self, String> {
+    let path = path.as_ref();
+    let read = |path: &Path| fs::read(path).map_err(|err| format!("{}: {}", path.display(), err));
+    let bytes = read(path)?;
+
+    match Format::detect(path) {
+      Format::Raw => {
+        let address = load_address.ok_or("raw binaries need a load address")?;
+        Self::from_raw(&bytes, address)
+      }
+      Format::Prg => Self::from_prg(&bytes),
+      Format::IntelHex => Self::from_intel_hex(&String::from_utf8_lossy(&bytes)),
+      Format::Ld65 => {
+        let debug_info = read(&path.with_extension("dbg"))?;
+        Self::from_ld65(&bytes, &String::from_utf8_lossy(&debug_info))
+      }
+    }
+    .map_err(|err| format!("{}: {}", path.display(), err))
+  }
+
+  /// Adds bytes, extending the last segment when they follow it.
+  fn append(&mut self, address: u16, data: &[u8]) {
+    match self.segments.last_mut() {
+      Some(last) if last.end() == address as usize => last.data.extend_from_slice(data),
+      _ => self.segments.push(Segment::new(address, data.to_vec())),
+    }
+  }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+  if !hex.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+/// Splits the `key=value,key="value"` fields of a line of an ld65 debug file.
+pub(crate) fn parse_debug_fields(fields: &str) -> Vec<(&str, String)> {
+  let mut parsed = Vec::new();
+  let mut rest = fields.trim();
+
+  while let Some((key, after_key)) = rest.split_once('=') {
+    let (value, after_value) = match after_key.strip_prefix('"') {
+      Some(quoted) => {
+        let end = quoted.find('"').unwrap_or(quoted.len());
+        let after = quoted.get(end + 1..).unwrap_or("");
+        (quoted[..end].to_string(), after)
+      }
+      None => {
+        let end = after_key.find(',').unwrap_or(after_key.len());
+        (after_key[..end].to_string(), &after_key[end..])
+      }
+    };
+    parsed.push((key.trim(), value));
+    rest = after_value.trim_start_matches(',');
+  }
+
+  parsed
+}
+
+/// Numbers in ld65 debug files are decimal or `0x` hex.
+pub(crate) fn parse_debug_number(value: &str) -> Option<usize> {
+  match value.strip_prefix("0x") {
+    Some(hex) => usize::from_str_radix(hex, 16).ok(),
+    None => value.parse().ok(),
+  }
+}
@@ -1,154 +1,194 @@
-use std::collections::HashMap;
-
-use lazy_static::lazy_static;
-
-use crate::cpu::AddressingMode;
+use crate::cpu::{AddressingMode, CpuError, CPU};
+
+/// Runs an instruction, once its opcode has been fetched.
+#[derive(Debug, Clone, Copy)]
+pub enum Handler {
+  /// An instruction without an operand.
+  Implied(fn(&mut CPU)),
+  Operand(fn(&mut CPU, &AddressingMode) -> Result<(), CpuError>),
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Opcode {
   pub code: u8,
   pub name: &'static str,
   pub bytes: u8,
-  _cycles: u8,
+  pub cycles: u8,
   pub addressing_mode: AddressingMode,
+  pub handler: Handler,
 }
 
 impl Opcode {
-  pub fn new(
+  pub const fn new(
     code: u8,
     name: &'static str,
     bytes: u8,
-    _cycles: u8,
+    cycles: u8,
     addressing_mode: AddressingMode,
+    handler: Handler,
   ) -> Self {
     Self {
       code,
       name,
       bytes,
-      _cycles,
+      cycles,
       addressing_mode,
+      handler,
     }
   }
-}
 
-lazy_static! {
-  pub static ref CPU_OP_CODES: Vec<Opcode> = vec![
-    Opcode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x7D, "ADC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-    Opcode::new(0x79, "ADC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-    Opcode::new(0x61, "ADC", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0x71, "ADC", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY),
-
-    Opcode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x3D, "AND", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-    Opcode::new(0x39, "AND", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-    Opcode::new(0x21, "AND", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0x31, "AND", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY),
-
-    Opcode::new(0x0A, "ASL", 1, 2, AddressingMode::Accumulator),
-    Opcode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
-    Opcode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX),
-    Opcode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute),
-    Opcode::new(0x1E, "ASL", 3, 7 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-
-    Opcode::new(0x90, "BCC", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative),
-    
-    Opcode::new(0xB0, "BCS", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative),
-    
-    Opcode::new(0xF0, "BEQ", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative),
-    
-    Opcode::new(0xD0, "BNE", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative),
-
-    Opcode::new(0x10, "BPL", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative),
-
-    Opcode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing),
-    
-    Opcode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing),
-
-    Opcode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xDD, "CMP", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-    Opcode::new(0xD9, "CMP", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-    Opcode::new(0xC1, "CMP", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0xD1, "CMP", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY),
-
-    Opcode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute),
-
-    Opcode::new(0xCA, "DEX", 1, 2, AddressingMode::NoneAddressing),
-
-    Opcode::new(0xE8, "INX", 1, 2, AddressingMode::NoneAddressing),
-
-    Opcode::new(0x20, "JSR", 3, 6, AddressingMode::Absolute),
-
-    Opcode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBD, "LDA", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-    Opcode::new(0xB9, "LDA", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-    Opcode::new(0xA1, "LDA", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0xB1, "LDA", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY),
-
-    Opcode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBE, "LDX", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-
-    Opcode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xBC, "LDY", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-
-    Opcode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing),
-
-    Opcode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate),
-    Opcode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0xFD, "SBC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX),
-    Opcode::new(0xF9, "SBC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY),
-    Opcode::new(0xE1, "SBC", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0xF1, "SBC", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY),
-
-    Opcode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute),
-    Opcode::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX),
-    Opcode::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY),
-    Opcode::new(0x81, "STA", 2, 6, AddressingMode::IndirectX),
-    Opcode::new(0x91, "STA", 2, 6, AddressingMode::IndirectY),
-    
-    Opcode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing),
-
-    Opcode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY),
-    Opcode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute),
-
-    Opcode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
-    Opcode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX),
-    Opcode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute),
-
-    Opcode::new(0xAA, "TAX", 1, 2, AddressingMode::NoneAddressing),
-    Opcode::new(0x8A, "TXA", 1, 2, AddressingMode::NoneAddressing),
-  ];
-
-  pub static ref OPCODES_MAP: HashMap<u8, &'static Opcode> = {
-    let mut map = HashMap::new();
-    for opcode in &*CPU_OP_CODES {
-      map.insert(opcode.code, opcode);
+  pub fn execute(&self, cpu: &mut CPU) -> Result<(), CpuError> {
+    match self.handler {
+      Handler::Implied(handler) => {
+        handler(cpu);
+        Ok(())
+      }
+      Handler::Operand(handler) => handler(cpu, &self.addressing_mode),
     }
-    map
-  };
+  }
+}
+
+/// Every opcode the CPU implements.
+#[rustfmt::skip]
+pub const CPU_OP_CODES: &[Opcode] = &[
+  Opcode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::adc)),
+  Opcode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::adc)),
+  Opcode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::adc)),
+  Opcode::new(0x6D, "ADC", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::adc)),
+  Opcode::new(0x7D, "ADC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::adc)),
+  Opcode::new(0x79, "ADC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::adc)),
+  Opcode::new(0x61, "ADC", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::adc)),
+  Opcode::new(0x71, "ADC", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY, Handler::Operand(CPU::adc)),
+
+  Opcode::new(0x29, "AND", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::and)),
+  Opcode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::and)),
+  Opcode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::and)),
+  Opcode::new(0x2D, "AND", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::and)),
+  Opcode::new(0x3D, "AND", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::and)),
+  Opcode::new(0x39, "AND", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::and)),
+  Opcode::new(0x21, "AND", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::and)),
+  Opcode::new(0x31, "AND", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY, Handler::Operand(CPU::and)),
+
+  Opcode::new(0x0A, "ASL", 1, 2, AddressingMode::Accumulator, Handler::Operand(CPU::asl)),
+  Opcode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage, Handler::Operand(CPU::asl)),
+  Opcode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPageX, Handler::Operand(CPU::asl)),
+  Opcode::new(0x0E, "ASL", 3, 6, AddressingMode::Absolute, Handler::Operand(CPU::asl)),
+  Opcode::new(0x1E, "ASL", 3, 7, AddressingMode::AbsoluteX, Handler::Operand(CPU::asl)),
+
+  Opcode::new(0x90, "BCC", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative, Handler::Operand(CPU::bcc)),
+
+  Opcode::new(0xB0, "BCS", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative, Handler::Operand(CPU::bcs)),
+
+  Opcode::new(0xF0, "BEQ", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative, Handler::Operand(CPU::beq)),
+
+  Opcode::new(0xD0, "BNE", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative, Handler::Operand(CPU::bne)),
+
+  Opcode::new(0x10, "BPL", 2, 2 /* 2 (+1 if branch succeeds, +2 if to a new page) */, AddressingMode::Relative, Handler::Operand(CPU::bpl)),
+
+  Opcode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing, Handler::Implied(CPU::brk)),
+
+  Opcode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::clc)),
+
+  Opcode::new(0xC9, "CMP", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xC5, "CMP", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xD5, "CMP", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xCD, "CMP", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xDD, "CMP", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xD9, "CMP", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xC1, "CMP", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::cmp)),
+  Opcode::new(0xD1, "CMP", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY, Handler::Operand(CPU::cmp)),
+
+  Opcode::new(0xE0, "CPX", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::cpx)),
+  Opcode::new(0xE4, "CPX", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::cpx)),
+  Opcode::new(0xEC, "CPX", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::cpx)),
+
+  Opcode::new(0xCA, "DEX", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::dex)),
+
+  Opcode::new(0xE8, "INX", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::inx)),
+
+  Opcode::new(0x20, "JSR", 3, 6, AddressingMode::Absolute, Handler::Operand(CPU::jsr)),
+
+  Opcode::new(0xA9, "LDA", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::lda)),
+  Opcode::new(0xA5, "LDA", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::lda)),
+  Opcode::new(0xB5, "LDA", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::lda)),
+  Opcode::new(0xAD, "LDA", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::lda)),
+  Opcode::new(0xBD, "LDA", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::lda)),
+  Opcode::new(0xB9, "LDA", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::lda)),
+  Opcode::new(0xA1, "LDA", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::lda)),
+  Opcode::new(0xB1, "LDA", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY, Handler::Operand(CPU::lda)),
+
+  Opcode::new(0xA2, "LDX", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::ldx)),
+  Opcode::new(0xA6, "LDX", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::ldx)),
+  Opcode::new(0xB6, "LDX", 2, 4, AddressingMode::ZeroPageY, Handler::Operand(CPU::ldx)),
+  Opcode::new(0xAE, "LDX", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::ldx)),
+  Opcode::new(0xBE, "LDX", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::ldx)),
+
+  Opcode::new(0xA0, "LDY", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::ldy)),
+  Opcode::new(0xA4, "LDY", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::ldy)),
+  Opcode::new(0xB4, "LDY", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::ldy)),
+  Opcode::new(0xAC, "LDY", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::ldy)),
+  Opcode::new(0xBC, "LDY", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::ldy)),
+
+  Opcode::new(0x48, "PHA", 1, 3, AddressingMode::NoneAddressing, Handler::Implied(CPU::pha)),
+  Opcode::new(0x08, "PHP", 1, 3, AddressingMode::NoneAddressing, Handler::Implied(CPU::php)),
+  Opcode::new(0x68, "PLA", 1, 4, AddressingMode::NoneAddressing, Handler::Implied(CPU::pla)),
+  Opcode::new(0x28, "PLP", 1, 4, AddressingMode::NoneAddressing, Handler::Implied(CPU::plp)),
+
+  Opcode::new(0x40, "RTI", 1, 6, AddressingMode::NoneAddressing, Handler::Implied(CPU::rti)),
+
+  Opcode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing, Handler::Implied(CPU::rts)),
+
+  Opcode::new(0xE9, "SBC", 2, 2, AddressingMode::Immediate, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xE5, "SBC", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xF5, "SBC", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xED, "SBC", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xFD, "SBC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteX, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xF9, "SBC", 3, 4 /* +1 if page crossed */, AddressingMode::AbsoluteY, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xE1, "SBC", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::sbc)),
+  Opcode::new(0xF1, "SBC", 2, 5 /* +1 if page crossed */, AddressingMode::IndirectY, Handler::Operand(CPU::sbc)),
+
+  Opcode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::sta)),
+  Opcode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::sta)),
+  Opcode::new(0x8D, "STA", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::sta)),
+  Opcode::new(0x9D, "STA", 3, 5, AddressingMode::AbsoluteX, Handler::Operand(CPU::sta)),
+  Opcode::new(0x99, "STA", 3, 5, AddressingMode::AbsoluteY, Handler::Operand(CPU::sta)),
+  Opcode::new(0x81, "STA", 2, 6, AddressingMode::IndirectX, Handler::Operand(CPU::sta)),
+  Opcode::new(0x91, "STA", 2, 6, AddressingMode::IndirectY, Handler::Operand(CPU::sta)),
+
+  Opcode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::sec)),
+
+  Opcode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::stx)),
+  Opcode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPageY, Handler::Operand(CPU::stx)),
+  Opcode::new(0x8E, "STX", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::stx)),
+
+  Opcode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage, Handler::Operand(CPU::sty)),
+  Opcode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPageX, Handler::Operand(CPU::sty)),
+  Opcode::new(0x8C, "STY", 3, 4, AddressingMode::Absolute, Handler::Operand(CPU::sty)),
+
+  Opcode::new(0xAA, "TAX", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::tax)),
+  Opcode::new(0x8A, "TXA", 1, 2, AddressingMode::NoneAddressing, Handler::Implied(CPU::txa)),
+];
+
+/// The opcode of each byte (`None` for the ones the CPU doesn't implement), built at compile
+/// time from `CPU_OP_CODES`.
+pub static OPCODES: [Option<Opcode>; 256] = decode_table(CPU_OP_CODES);
+
+/// The opcode of a byte, if the CPU implements it.
+pub fn decode(code: u8) -> Option<&'static Opcode> {
+  OPCODES[code as usize].as_ref()
+}
+
+const fn decode_table(opcodes: &[Opcode]) -> [Option<Opcode>; 256] {
+  let mut table = [None; 256];
+  let mut i = 0;
+  while i < opcodes.len() {
+    let opcode = opcodes[i];
+    assert!(
+      table[opcode.code as usize].is_none(),
+      "an opcode is listed twice"
+    );
+    table[opcode.code as usize] = Some(opcode);
+    i += 1;
+  }
+  table
 }
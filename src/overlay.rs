@@ -0,0 +1,207 @@
+/// Size of a character of the overlay font, in pixels.
+pub const GLYPH_WIDTH: i32 = 3;
+pub const GLYPH_HEIGHT: i32 = 5;
+/// Distance between the start of two characters, and between two lines.
+const CHARACTER_ADVANCE: i32 = GLYPH_WIDTH + 1;
+const LINE_ADVANCE: i32 = GLYPH_HEIGHT + 1;
+
+/// A 3x5 font, row by row (bit 2 is the left column). Lowercase letters are drawn as uppercase
+/// ones, and missing characters as '?'.
+const FONT: [(char, [u8; 5]); 60] = [
+  (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+  ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+  ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+  ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+  ('3', [0b111, 0b001, 0b011, 0b001, 0b111]),
+  ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+  ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+  ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+  ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+  ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+  ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+  ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+  ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+  ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+  ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+  ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+  ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+  ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+  ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+  ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+  ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+  ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+  ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+  ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+  ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+  ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+  ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+  ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+  ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+  ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+  ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+  ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+  ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+  ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+  ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+  ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+  ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+  ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+  (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+  (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+  (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+  ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+  ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+  ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+  ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+  ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+  ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+  ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+  ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+  ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+  ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+  ('$', [0b011, 0b110, 0b010, 0b011, 0b110]),
+  ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+  ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+  (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+  ('[', [0b011, 0b010, 0b010, 0b010, 0b011]),
+  (']', [0b110, 0b010, 0b010, 0b010, 0b110]),
+  ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+  ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+  ('|', [0b010, 0b010, 0b010, 0b010, 0b010]),
+];
+
+/// Something to draw on top of a frame. Colours are 0xRRGGBB and coordinates are in pixels of
+/// the frame, from its top left corner; what falls outside of it is clipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shape {
+  /// Text in the overlay font, starting at its top left corner. `\n` starts a new line.
+  Text {
+    x: i32,
+    y: i32,
+    text: String,
+    color: u32,
+  },
+  /// A rectangle, corners included, filled or not.
+  Box {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+    fill: Option<u32>,
+    outline: u32,
+  },
+}
+
+/// Text and boxes to draw on top of the frames, like the HUDs of the FCEUX and Mesen scripts.
+#[derive(Debug, Clone, Default)]
+pub struct Overlay {
+  pub shapes: Vec<Shape>,
+}
+
+impl Overlay {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn text(&mut self, x: i32, y: i32, text: &str, color: u32) {
+    self.shapes.push(Shape::Text {
+      x,
+      y,
+      text: text.to_string(),
+      color,
+    });
+  }
+
+  pub fn rect(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, fill: Option<u32>, outline: u32) {
+    self.shapes.push(Shape::Box {
+      x1,
+      y1,
+      x2,
+      y2,
+      fill,
+      outline,
+    });
+  }
+
+  pub fn clear(&mut self) {
+    self.shapes.clear();
+  }
+
+  /// Draws the shapes, in the order they were added, on an RGB24 frame.
+  pub fn draw(&self, rgb: &mut [u8], width: usize, height: usize) {
+    let mut canvas = Canvas { rgb, width, height };
+    for shape in &self.shapes {
+      match shape {
+        Shape::Text { x, y, text, color } => canvas.text(*x, *y, text, *color),
+        Shape::Box {
+          x1,
+          y1,
+          x2,
+          y2,
+          fill,
+          outline,
+        } => canvas.rect(*x1, *y1, *x2, *y2, *fill, *outline),
+      }
+    }
+  }
+}
+
+struct Canvas<'a> {
+  rgb: &'a mut [u8],
+  width: usize,
+  height: usize,
+}
+
+impl Canvas<'_> {
+  fn pixel(&mut self, x: i32, y: i32, color: u32) {
+    if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+      return;
+    }
+    let offset = (y as usize * self.width + x as usize) * 3;
+    self.rgb[offset..offset + 3].copy_from_slice(&color.to_be_bytes()[1..]);
+  }
+
+  fn text(&mut self, x: i32, y: i32, text: &str, color: u32) {
+    for (line_index, line) in text.lines().enumerate() {
+      let top = y + line_index as i32 * LINE_ADVANCE;
+      for (index, character) in line.chars().enumerate() {
+        let left = x + index as i32 * CHARACTER_ADVANCE;
+        for (row, bits) in glyph(character).iter().enumerate() {
+          for column in 0..GLYPH_WIDTH {
+            if bits & (0b100 >> column) != 0 {
+              self.pixel(left + column, top + row as i32, color);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn rect(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, fill: Option<u32>, outline: u32) {
+    let (left, right) = (x1.min(x2), x1.max(x2));
+    let (top, bottom) = (y1.min(y2), y1.max(y2));
+    // only the visible part is walked through, however big the box is
+    for y in top.max(0)..=bottom.min(self.height as i32 - 1) {
+      for x in left.max(0)..=right.min(self.width as i32 - 1) {
+        let on_border = x == left || x == right || y == top || y == bottom;
+        match (on_border, fill) {
+          (true, _) => self.pixel(x, y, outline),
+          (false, Some(fill)) => self.pixel(x, y, fill),
+          (false, None) => {}
+        }
+      }
+    }
+  }
+}
+
+fn glyph(character: char) -> [u8; 5] {
+  let find = |character| {
+    FONT
+      .iter()
+      .find(|(glyph_character, _)| *glyph_character == character)
+      .map(|(_, rows)| *rows)
+  };
+  find(character.to_ascii_uppercase())
+    .or_else(|| find('?'))
+    .unwrap()
+}
@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// 64 colours, times the 8 combinations of the PPUMASK colour emphasis bits.
+pub const PALETTE_SIZE: usize = 512;
+const BASE_COLORS: usize = 64;
+
+/// Attenuation applied to a colour channel for each emphasis bit (of the other channels) that is
+/// set, for palettes that do not define their own emphasis colours.
+const EMPHASIS_ATTENUATION: f64 = 0.816328;
+
+/// Colour palettes bundled with the emulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuiltinPalette {
+  /// Generated from the measured composite video levels of the 2C02, emphasis included.
+  /// See <https://www.nesdev.org/wiki/NTSC_video> for more info.
+  #[default]
+  Ppu2C02,
+  /// The default palette of FCEUX.
+  Fceux,
+  /// Close to the YUV palette of Nestopia.
+  Nestopia,
+}
+
+const FCEUX_COLORS: [u32; BASE_COLORS] = [
+  0x747474, 0x24188C, 0x0000A8, 0x44009C, 0x8C0074, 0xA80010, 0xA40000, 0x7C0800,
+  0x402C00, 0x004400, 0x005000, 0x003C14, 0x183C5C, 0x000000, 0x000000, 0x000000,
+  0xBCBCBC, 0x0070EC, 0x2038EC, 0x8000F0, 0xBC00BC, 0xE40058, 0xD82800, 0xC84C0C,
+  0x887000, 0x009400, 0x00A800, 0x009038, 0x008088, 0x000000, 0x000000, 0x000000,
+  0xFCFCFC, 0x3CBCFC, 0x5C94FC, 0xCC88FC, 0xF478FC, 0xFC74B4, 0xFC7460, 0xFC9838,
+  0xF0BC3C, 0x80D010, 0x4CDC48, 0x58F898, 0x00E8D8, 0x787878, 0x000000, 0x000000,
+  0xFCFCFC, 0xA8E4FC, 0xC4D4FC, 0xD4C8FC, 0xFCC4FC, 0xFCC4D8, 0xFCBCB0, 0xFCD8A8,
+  0xFCE4A0, 0xE0FCA0, 0xA8F0BC, 0xB0FCCC, 0x9CFCF0, 0xC4C4C4, 0x000000, 0x000000,
+];
+
+const NESTOPIA_COLORS: [u32; BASE_COLORS] = [
+  0x666666, 0x002A88, 0x1412A7, 0x3B00A4, 0x5C007E, 0x6E0040, 0x6C0600, 0x561D00,
+  0x333500, 0x0B4800, 0x005200, 0x004F08, 0x00404D, 0x000000, 0x000000, 0x000000,
+  0xADADAD, 0x155FD9, 0x4240FF, 0x7527FE, 0xA01ACC, 0xB71E7B, 0xB53120, 0x994E00,
+  0x6B6D00, 0x388700, 0x0C9300, 0x008F32, 0x007C8D, 0x000000, 0x000000, 0x000000,
+  0xFFFEFF, 0x64B0FF, 0x9290FF, 0xC676FF, 0xF36AFF, 0xFE6ECC, 0xFE8170, 0xEA9E22,
+  0xBCBE00, 0x88D800, 0x5CE430, 0x45E082, 0x48CDDE, 0x4F4F4F, 0x000000, 0x000000,
+  0xFFFEFF, 0xC0DFFF, 0xD3D2FF, 0xE8C8FF, 0xFBC2FF, 0xFEC4EA, 0xFECCC5, 0xF7D8A5,
+  0xE4E594, 0xCFEF96, 0xBDF4AB, 0xB3F3CC, 0xB5EBF2, 0xB8B8B8, 0x000000, 0x000000,
+];
+
+/// Maps the 9-bit colours produced by the PPU (emphasis bits << 6 | colour index) to RGB.
+#[derive(Clone)]
+pub struct Palette {
+  colors: [(u8, u8, u8); PALETTE_SIZE],
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    Self::builtin(BuiltinPalette::default())
+  }
+}
+
+impl Palette {
+  pub fn builtin(palette: BuiltinPalette) -> Self {
+    match palette {
+      BuiltinPalette::Ppu2C02 => Self::generate_2c02(),
+      BuiltinPalette::Fceux => Self::from_base_colors(&FCEUX_COLORS.map(Self::unpack)),
+      BuiltinPalette::Nestopia => Self::from_base_colors(&NESTOPIA_COLORS.map(Self::unpack)),
+    }
+  }
+
+  /// Parses the content of a `.pal` file: 64 RGB triplets (192 bytes), or 512 of them
+  /// (1536 bytes) for files that also define the emphasized colours.
+  pub fn from_pal_bytes(bytes: &[u8]) -> io::Result<Self> {
+    let triplets: Vec<(u8, u8, u8)> = bytes.chunks_exact(3).map(|rgb| (rgb[0], rgb[1], rgb[2])).collect();
+
+    match bytes.len() {
+      len if len == BASE_COLORS * 3 => Ok(Self::from_base_colors(&triplets)),
+      len if len == PALETTE_SIZE * 3 => {
+        let mut colors = [(0, 0, 0); PALETTE_SIZE];
+        colors.copy_from_slice(&triplets);
+        Ok(Self { colors })
+      }
+      len => Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("a .pal file has 192 or 1536 bytes, got {}", len),
+      )),
+    }
+  }
+
+  /// Loads a `.pal` file (see `from_pal_bytes`).
+  pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+    Self::from_pal_bytes(&fs::read(path)?)
+  }
+
+  /// RGB value of a 9-bit PPU colour.
+  pub fn rgb(&self, color: u16) -> (u8, u8, u8) {
+    self.colors[color as usize % PALETTE_SIZE]
+  }
+
+  /// Converts a frame of PPU colours into packed RGB24 pixels.
+  pub fn frame_to_rgb(&self, frame: &[u16], rgb: &mut [u8]) {
+    for (pixel, color) in rgb.chunks_exact_mut(3).zip(frame) {
+      let (r, g, b) = self.rgb(*color);
+      pixel.copy_from_slice(&[r, g, b]);
+    }
+  }
+
+  fn unpack(rgb: u32) -> (u8, u8, u8) {
+    ((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+  }
+
+  /// Builds the emphasized colours by dimming each channel once for every other channel that is
+  /// emphasized (so emphasizing all of them darkens the whole picture, like the real PPU does).
+  /// Columns $xE and $xF are black on the 2C02, which emphasis doesn't change.
+  fn from_base_colors(base: &[(u8, u8, u8)]) -> Self {
+    let mut colors = [(0, 0, 0); PALETTE_SIZE];
+    for (index, color) in colors.iter_mut().enumerate() {
+      let (r, g, b) = base[index % BASE_COLORS];
+      let emphasis = if index & 0x0F < 0x0E { (index / BASE_COLORS) as u8 } else { 0 };
+
+      let dim = |channel: u8, channel_bit: u8| {
+        let other_emphasized_channels = (emphasis & !channel_bit).count_ones() as i32;
+        (channel as f64 * EMPHASIS_ATTENUATION.powi(other_emphasized_channels)).round() as u8
+      };
+      *color = (dim(r, 0b001), dim(g, 0b010), dim(b, 0b100));
+    }
+
+    Self { colors }
+  }
+
+  /// Simulates the square wave the 2C02 outputs for each colour and decodes it like an ideal
+  /// NTSC television would. Emphasizing red, green or blue attenuates the signal during the
+  /// phases of their complements (colours $C, $4 and $8), except in columns $xE/$xF.
+  fn generate_2c02() -> Self {
+    const BLACK: f64 = 0.518;
+    const WHITE: f64 = 1.962;
+    const ATTENUATION: f64 = 0.746;
+    const LOW_LEVELS: [f64; 4] = [0.350, 0.518, 0.962, 1.550];
+    const HIGH_LEVELS: [f64; 4] = [1.094, 1.506, 1.962, 1.962];
+    const GAMMA: f64 = 2.2 / 1.8;
+    // phase of the colour burst, the reference the television decodes hues against
+    const COLOR_BURST_PHASE: f64 = 3.9;
+
+    let in_color_phase = |color: usize, phase: usize| (color + phase) % 12 < 6;
+    let to_channel = |value: f64| {
+      let corrected = if value <= 0.0 { 0.0 } else { value.powf(GAMMA) };
+      (corrected * 255.95).clamp(0.0, 255.0) as u8
+    };
+
+    let mut colors = [(0, 0, 0); PALETTE_SIZE];
+    for (index, rgb) in colors.iter_mut().enumerate() {
+      let color = index & 0x0F;
+      let level = if color < 0x0E { (index >> 4) & 0b11 } else { 1 };
+      let emphasis = index >> 6;
+
+      let low = if color == 0x00 { HIGH_LEVELS[level] } else { LOW_LEVELS[level] };
+      let high = if color < 0x0D { HIGH_LEVELS[level] } else { LOW_LEVELS[level] };
+
+      let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+      for phase in 0..12 {
+        let mut signal = if in_color_phase(color, phase) { high } else { low };
+        let attenuated = color < 0x0E
+          && ((emphasis & 1 != 0 && in_color_phase(0x0C, phase))
+            || (emphasis & 2 != 0 && in_color_phase(0x04, phase))
+            || (emphasis & 4 != 0 && in_color_phase(0x08, phase)));
+        if attenuated {
+          signal *= ATTENUATION;
+        }
+
+        let value = (signal - BLACK) / (WHITE - BLACK) / 12.0;
+        let angle = std::f64::consts::PI / 6.0 * (phase as f64 + COLOR_BURST_PHASE);
+        y += value;
+        i += value * angle.cos();
+        q += value * angle.sin();
+      }
+
+      *rgb = (
+        to_channel(y + 0.946882 * i + 0.623557 * q),
+        to_channel(y - 0.274788 * i - 0.635691 * q),
+        to_channel(y - 1.108545 * i + 1.709007 * q),
+      );
+    }
+
+    Self { colors }
+  }
+}
@@ -0,0 +1,684 @@
+use crate::region::Region;
+
+const OAM_SIZE: usize = 256;
+const SPRITES_PER_SCANLINE: usize = 8;
+const CHR_RAM_SIZE: usize = 0x2000;
+const VRAM_SIZE: usize = 0x1000;
+const PALETTE_TABLE_SIZE: usize = 32;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+const DOTS_PER_SCANLINE: u16 = 341;
+const VISIBLE_SCANLINES: u16 = 240;
+
+// PPUCTRL ($2000)
+const CTRL_NAMETABLE: u8 = 0b0000_0011;
+const CTRL_VRAM_INCREMENT: u8 = 0b0000_0100;
+const CTRL_SPRITE_PATTERN_TABLE: u8 = 0b0000_1000;
+const CTRL_BACKGROUND_PATTERN_TABLE: u8 = 0b0001_0000;
+const CTRL_SPRITE_SIZE: u8 = 0b0010_0000;
+const CTRL_GENERATE_NMI: u8 = 0b1000_0000;
+
+// PPUMASK ($2001)
+const MASK_GREYSCALE: u8 = 0b0000_0001;
+const MASK_SHOW_BACKGROUND_LEFTMOST: u8 = 0b0000_0010;
+const MASK_SHOW_SPRITES_LEFTMOST: u8 = 0b0000_0100;
+const MASK_SHOW_BACKGROUND: u8 = 0b0000_1000;
+const MASK_SHOW_SPRITES: u8 = 0b0001_0000;
+
+// PPUSTATUS ($2002)
+const STATUS_SPRITE_OVERFLOW: u8 = 0b0010_0000;
+const STATUS_SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+const STATUS_VBLANK: u8 = 0b1000_0000;
+
+// Sprite attributes (byte 2 of each OAM entry)
+const SPRITE_PALETTE: u8 = 0b0000_0011;
+const SPRITE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const SPRITE_FLIP_HORIZONTALLY: u8 = 0b0100_0000;
+const SPRITE_FLIP_VERTICALLY: u8 = 0b1000_0000;
+
+// Loopy registers (v and t) layout: 0yyy NNYY YYYX XXXX
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const FINE_Y: u16 = 0x7000;
+const HORIZONTAL_BITS: u16 = NAMETABLE_X | COARSE_X;
+const VERTICAL_BITS: u16 = FINE_Y | NAMETABLE_Y | COARSE_Y;
+
+/// How the two physical nametables (2KiB of VRAM) are laid out in the four logical ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+  /// $2000 = $0800 and $2400 = $0C00 (horizontal scrolling games).
+  Vertical,
+  /// $2000 = $2400 and $2800 = $2C00 (vertical scrolling games).
+  Horizontal,
+  /// The cartridge provides 2KiB of extra VRAM, so there is no mirroring at all.
+  FourScreen,
+}
+
+/// Picture Processing Unit.
+///
+/// It renders one dot per `tick`, following the real fetch pattern of the 2C02, so games that
+/// change PPUSCROLL/PPUADDR in the middle of the frame (split-screen status bars) are drawn
+/// correctly. The scroll position lives in the internal "loopy" registers:
+/// - `v`: current VRAM address, also used as the scroll position while rendering;
+/// - `t`: temporary VRAM address (the scroll position for the top left of the next frame);
+/// - `fine_x`: fine X scroll (3 bits);
+/// - `w`: first/second write toggle shared by PPUSCROLL and PPUADDR.
+///
+/// See <https://www.nesdev.org/wiki/PPU_scrolling> and
+/// <https://www.nesdev.org/wiki/PPU_rendering> for more info.
+#[derive(Clone)]
+pub struct PPU {
+  pub ctrl: u8,
+  pub mask: u8,
+  pub status: u8,
+  pub oam_addr: u8,
+  pub oam_data: [u8; OAM_SIZE],
+  pub v: u16,
+  pub t: u16,
+  pub fine_x: u8,
+  pub w: bool,
+  pub region: Region,
+  /// Scanline and dot the next `tick` will render.
+  pub scanline: u16,
+  pub dot: u16,
+  pub frame_count: u64,
+  /// Colours of the last rendered frame, row by row: the palette index (0x00-0x3F) with the
+  /// PPUMASK emphasis bits on top (bits 6-8: red, green, blue). See `palette::Palette`.
+  pub frame: [u16; SCREEN_WIDTH * SCREEN_HEIGHT],
+  chr: Vec<u8>,
+  vram: [u8; VRAM_SIZE],
+  palette_table: [u8; PALETTE_TABLE_SIZE],
+  mirroring: Mirroring,
+  data_buffer: u8,
+  background: BackgroundPipeline,
+  sprites: Vec<SpriteSlot>,
+  nmi_interrupt: bool,
+  suppress_vblank: bool,
+}
+
+/// A sprite selected by the evaluation of the previous scanline, ready to be drawn.
+#[derive(Clone)]
+struct SpriteSlot {
+  x: u8,
+  attribute: u8,
+  pattern_lsb: u8,
+  pattern_msb: u8,
+  is_sprite_zero: bool,
+}
+
+/// Latches and shift registers the PPU uses to fetch background tiles ahead of drawing them.
+#[derive(Clone, Default)]
+struct BackgroundPipeline {
+  next_tile_id: u8,
+  next_tile_attribute: u8,
+  next_tile_lsb: u8,
+  next_tile_msb: u8,
+  pattern_lsb: u16,
+  pattern_msb: u16,
+  attribute_lsb: u16,
+  attribute_msb: u16,
+}
+
+impl Default for PPU {
+  fn default() -> Self {
+    Self::new_with_chr(vec![0; CHR_RAM_SIZE], Mirroring::Horizontal)
+  }
+}
+
+impl PPU {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Creates a PPU connected to the cartridge pattern tables (CHR ROM or RAM).
+  pub fn new_with_chr(chr: Vec<u8>, mirroring: Mirroring) -> Self {
+    let region = Region::default();
+    Self {
+      ctrl: 0,
+      mask: 0,
+      status: 0,
+      oam_addr: 0,
+      oam_data: [0; OAM_SIZE],
+      v: 0,
+      t: 0,
+      fine_x: 0,
+      w: false,
+      region,
+      scanline: region.pre_render_scanline(),
+      dot: 0,
+      frame_count: 0,
+      frame: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+      chr,
+      vram: [0; VRAM_SIZE],
+      palette_table: [0; PALETTE_TABLE_SIZE],
+      mirroring,
+      data_buffer: 0,
+      background: BackgroundPipeline::default(),
+      sprites: Vec::with_capacity(SPRITES_PER_SCANLINE),
+      nmi_interrupt: false,
+      suppress_vblank: false,
+    }
+  }
+
+  fn rendering_enabled(&self) -> bool {
+    self.mask & (MASK_SHOW_BACKGROUND | MASK_SHOW_SPRITES) != 0
+  }
+
+  /// Whether the CPU should be interrupted (NMI) at the start of VBlank.
+  pub fn nmi_enabled(&self) -> bool {
+    self.ctrl & CTRL_GENERATE_NMI != 0
+  }
+
+  /// Returns (and acknowledges) a pending NMI.
+  pub fn poll_nmi(&mut self) -> bool {
+    std::mem::take(&mut self.nmi_interrupt)
+  }
+
+  /// $2000 (PPUCTRL)
+  pub fn write_to_ctrl(&mut self, value: u8) {
+    // enabling NMIs during VBlank triggers one right away
+    let nmi_turned_on = !self.nmi_enabled() && value & CTRL_GENERATE_NMI != 0;
+    if nmi_turned_on && self.status & STATUS_VBLANK != 0 {
+      self.nmi_interrupt = true;
+    }
+
+    self.ctrl = value;
+    self.t = (self.t & !(NAMETABLE_X | NAMETABLE_Y)) | (((value & CTRL_NAMETABLE) as u16) << 10);
+  }
+
+  /// $2001 (PPUMASK)
+  pub fn write_to_mask(&mut self, value: u8) {
+    self.mask = value;
+  }
+
+  /// $2002 (PPUSTATUS) read. Clears the VBlank flag and the write toggle.
+  ///
+  /// Reads racing with the start of VBlank behave differently: reading one dot before the flag
+  /// is set returns it clear and prevents both the flag and the NMI for this frame, while reading
+  /// on the dot it is set (or the next one) returns it set but still cancels the NMI.
+  /// See <https://www.nesdev.org/wiki/PPU_frame_timing#VBL_Flag_Timing> for more info.
+  pub fn read_status(&mut self) -> u8 {
+    if self.scanline == self.region.vblank_start_scanline() {
+      match self.dot {
+        1 => self.suppress_vblank = true,
+        2 | 3 => self.nmi_interrupt = false,
+        _ => {}
+      }
+    }
+
+    let status = self.status;
+    self.status &= !STATUS_VBLANK;
+    self.w = false;
+    status
+  }
+
+  /// $2003 (OAMADDR)
+  pub fn write_to_oam_addr(&mut self, value: u8) {
+    self.oam_addr = value;
+  }
+
+  /// $2004 (OAMDATA) write. Increments OAMADDR, wrapping around the 256 bytes of OAM.
+  pub fn write_to_oam_data(&mut self, value: u8) {
+    self.oam_data[self.oam_addr as usize] = value;
+    self.oam_addr = self.oam_addr.wrapping_add(1);
+  }
+
+  /// $2004 (OAMDATA) read. Unlike writes, reads do not increment OAMADDR.
+  pub fn read_oam_data(&self) -> u8 {
+    self.oam_data[self.oam_addr as usize]
+  }
+
+  /// $2005 (PPUSCROLL). The first write sets the X scroll, the second one the Y scroll.
+  pub fn write_to_scroll(&mut self, value: u8) {
+    if !self.w {
+      self.t = (self.t & !COARSE_X) | (value >> 3) as u16;
+      self.fine_x = value & 0b111;
+    } else {
+      self.t = (self.t & !(FINE_Y | COARSE_Y))
+        | (((value & 0b111) as u16) << 12)
+        | (((value >> 3) as u16) << 5);
+    }
+    self.w = !self.w;
+  }
+
+  /// $2006 (PPUADDR). The first write sets the high byte, the second one the low byte and
+  /// copies `t` into `v`.
+  pub fn write_to_ppu_addr(&mut self, value: u8) {
+    if !self.w {
+      self.t = (self.t & 0x00FF) | (((value & 0b0011_1111) as u16) << 8);
+    } else {
+      self.t = (self.t & 0xFF00) | value as u16;
+      self.v = self.t;
+    }
+    self.w = !self.w;
+  }
+
+  /// $2007 (PPUDATA) write.
+  pub fn write_to_data(&mut self, value: u8) {
+    self.vram_write(self.v, value);
+    self.increment_vram_addr();
+  }
+
+  /// $2007 (PPUDATA) read. Reads below the palettes return the content of an internal buffer
+  /// (the value read by the previous access), so the first read after setting PPUADDR is stale.
+  pub fn read_data(&mut self) -> u8 {
+    let addr = self.v & 0x3FFF;
+    let value = self.vram_read(addr);
+    let result = if addr >= 0x3F00 {
+      // palettes are returned right away, but the buffer gets the nametable "underneath" them
+      self.data_buffer = self.vram_read(addr - 0x1000);
+      value
+    } else {
+      std::mem::replace(&mut self.data_buffer, value)
+    };
+
+    self.increment_vram_addr();
+    result
+  }
+
+  fn increment_vram_addr(&mut self) {
+    let rendering = self.rendering_enabled()
+      && (self.scanline < VISIBLE_SCANLINES || self.scanline == self.region.pre_render_scanline());
+
+    if rendering {
+      // during rendering the PPU increments v as if it was fetching tiles
+      self.increment_scroll_x();
+      self.increment_scroll_y();
+    } else if self.ctrl & CTRL_VRAM_INCREMENT != 0 {
+      self.v = self.v.wrapping_add(32) & 0x7FFF;
+    } else {
+      self.v = self.v.wrapping_add(1) & 0x7FFF;
+    }
+  }
+
+  fn mirror_vram_addr(&self, addr: u16) -> usize {
+    let vram_index = (addr & 0x0FFF) as usize; // $2000-$2FFF, mirrored up to $3EFF
+    let nametable = vram_index / 0x400;
+    let offset = vram_index % 0x400;
+
+    match self.mirroring {
+      Mirroring::Vertical => (nametable % 2) * 0x400 + offset,
+      Mirroring::Horizontal => (nametable / 2) * 0x400 + offset,
+      Mirroring::FourScreen => vram_index,
+    }
+  }
+
+  fn palette_index(addr: u16) -> usize {
+    let index = (addr & 0x1F) as usize;
+    // $3F10/$3F14/$3F18/$3F1C mirror the background entries
+    match index {
+      0x10 | 0x14 | 0x18 | 0x1C => index - 0x10,
+      _ => index,
+    }
+  }
+
+  fn vram_read(&self, addr: u16) -> u8 {
+    let addr = addr & 0x3FFF;
+    match addr {
+      0x0000..=0x1FFF => self.chr[addr as usize],
+      0x2000..=0x3EFF => self.vram[self.mirror_vram_addr(addr)],
+      _ => self.palette_table[Self::palette_index(addr)],
+    }
+  }
+
+  fn vram_write(&mut self, addr: u16, value: u8) {
+    let addr = addr & 0x3FFF;
+    match addr {
+      0x0000..=0x1FFF => self.chr[addr as usize] = value,
+      0x2000..=0x3EFF => self.vram[self.mirror_vram_addr(addr)] = value,
+      _ => self.palette_table[Self::palette_index(addr)] = value & 0b0011_1111,
+    }
+  }
+
+  /// Moves `v` to the next tile horizontally, switching nametables at the right edge.
+  fn increment_scroll_x(&mut self) {
+    if self.v & COARSE_X == 31 {
+      self.v &= !COARSE_X;
+      self.v ^= NAMETABLE_X;
+    } else {
+      self.v += 1;
+    }
+  }
+
+  /// Moves `v` to the next row of pixels, switching nametables after the 30th row of tiles.
+  fn increment_scroll_y(&mut self) {
+    if self.v & FINE_Y != FINE_Y {
+      self.v += 0x1000;
+      return;
+    }
+
+    self.v &= !FINE_Y;
+    let mut coarse_y = (self.v & COARSE_Y) >> 5;
+    if coarse_y == 29 {
+      coarse_y = 0;
+      self.v ^= NAMETABLE_Y;
+    } else if coarse_y == 31 {
+      // out of bounds coarse Y (attribute data used as tiles) wraps without switching
+      coarse_y = 0;
+    } else {
+      coarse_y += 1;
+    }
+    self.v = (self.v & !COARSE_Y) | (coarse_y << 5);
+  }
+
+  fn copy_horizontal_bits(&mut self) {
+    self.v = (self.v & !HORIZONTAL_BITS) | (self.t & HORIZONTAL_BITS);
+  }
+
+  fn copy_vertical_bits(&mut self) {
+    self.v = (self.v & !VERTICAL_BITS) | (self.t & VERTICAL_BITS);
+  }
+
+  fn load_background_shifters(&mut self) {
+    let bg = &mut self.background;
+    bg.pattern_lsb = (bg.pattern_lsb & 0xFF00) | bg.next_tile_lsb as u16;
+    bg.pattern_msb = (bg.pattern_msb & 0xFF00) | bg.next_tile_msb as u16;
+
+    let attribute_lsb = if bg.next_tile_attribute & 0b01 != 0 { 0xFF } else { 0x00 };
+    let attribute_msb = if bg.next_tile_attribute & 0b10 != 0 { 0xFF } else { 0x00 };
+    bg.attribute_lsb = (bg.attribute_lsb & 0xFF00) | attribute_lsb;
+    bg.attribute_msb = (bg.attribute_msb & 0xFF00) | attribute_msb;
+  }
+
+  fn shift_background_shifters(&mut self) {
+    let bg = &mut self.background;
+    bg.pattern_lsb <<= 1;
+    bg.pattern_msb <<= 1;
+    bg.attribute_lsb <<= 1;
+    bg.attribute_msb <<= 1;
+  }
+
+  /// One step of the 8-dot tile fetch cycle: nametable byte, attribute byte, pattern low and
+  /// pattern high bytes, then move on to the next tile.
+  fn fetch_background_tile(&mut self) {
+    let background_table: u16 = if self.ctrl & CTRL_BACKGROUND_PATTERN_TABLE != 0 { 0x1000 } else { 0 };
+
+    match (self.dot - 1) % 8 {
+      0 => {
+        self.load_background_shifters();
+        self.background.next_tile_id = self.vram_read(0x2000 | (self.v & 0x0FFF));
+      }
+      2 => {
+        let attribute_addr = 0x23C0
+          | (self.v & (NAMETABLE_X | NAMETABLE_Y))
+          | ((self.v >> 4) & 0b11_1000)
+          | ((self.v >> 2) & 0b111);
+        let mut attribute = self.vram_read(attribute_addr);
+        // each attribute byte covers 4x4 tiles, 2 bits per 2x2 quadrant
+        if self.v & 0x0040 != 0 {
+          attribute >>= 4;
+        }
+        if self.v & 0b10 != 0 {
+          attribute >>= 2;
+        }
+        self.background.next_tile_attribute = attribute & 0b11;
+      }
+      4 => {
+        let fine_y = (self.v & FINE_Y) >> 12;
+        let addr = background_table + (self.background.next_tile_id as u16) * 16 + fine_y;
+        self.background.next_tile_lsb = self.vram_read(addr);
+      }
+      6 => {
+        let fine_y = (self.v & FINE_Y) >> 12;
+        let addr = background_table + (self.background.next_tile_id as u16) * 16 + fine_y + 8;
+        self.background.next_tile_msb = self.vram_read(addr);
+      }
+      7 => self.increment_scroll_x(),
+      _ => {}
+    }
+  }
+
+  fn sprite_height(&self) -> u16 {
+    if self.ctrl & CTRL_SPRITE_SIZE != 0 {
+      16
+    } else {
+      8
+    }
+  }
+
+  fn sprite_in_range(&self, y: u8) -> bool {
+    let row = self.scanline.wrapping_sub(y as u16);
+    row < self.sprite_height()
+  }
+
+  /// Looks for the (up to 8) sprites to draw on the next scanline.
+  ///
+  /// Real hardware does this over dots 65-256 and fetches their patterns over dots 257-320; here
+  /// it all happens at once, on dot 257.
+  /// Once 8 sprites are found, the PPU keeps scanning OAM to set the sprite overflow flag, but a
+  /// hardware bug makes it increment the byte index together with the sprite index, so it
+  /// compares tile numbers, attributes and X positions as if they were Y coordinates. This gives
+  /// both false positives and false negatives, which some games rely on.
+  /// See <https://www.nesdev.org/wiki/PPU_sprite_evaluation> for more info.
+  fn evaluate_sprites(&mut self) {
+    self.sprites.clear();
+
+    let mut n = 0;
+    while n < 64 && self.sprites.len() < SPRITES_PER_SCANLINE {
+      let y = self.oam_data[n * 4];
+      if self.sprite_in_range(y) {
+        let sprite = self.fetch_sprite(n);
+        self.sprites.push(sprite);
+      }
+      n += 1;
+    }
+
+    let mut m = 0;
+    while n < 64 {
+      if self.sprite_in_range(self.oam_data[n * 4 + m]) {
+        self.status |= STATUS_SPRITE_OVERFLOW;
+        break;
+      }
+      n += 1;
+      m = (m + 1) % 4;
+    }
+  }
+
+  fn fetch_sprite(&self, index: usize) -> SpriteSlot {
+    let y = self.oam_data[index * 4];
+    let tile = self.oam_data[index * 4 + 1];
+    let attribute = self.oam_data[index * 4 + 2];
+    let x = self.oam_data[index * 4 + 3];
+
+    let height = self.sprite_height();
+    let mut row = self.scanline.wrapping_sub(y as u16);
+    if attribute & SPRITE_FLIP_VERTICALLY != 0 {
+      row = height - 1 - row;
+    }
+
+    let tile_addr = if height == 16 {
+      // 8x16 sprites take the pattern table from bit 0 of the tile number
+      let table = (tile as u16 & 1) * 0x1000;
+      let top_tile = (tile & 0xFE) as u16;
+      table + (top_tile + row / 8) * 16 + row % 8
+    } else {
+      let table: u16 = if self.ctrl & CTRL_SPRITE_PATTERN_TABLE != 0 { 0x1000 } else { 0 };
+      table + (tile as u16) * 16 + row
+    };
+
+    let mut pattern_lsb = self.vram_read(tile_addr);
+    let mut pattern_msb = self.vram_read(tile_addr + 8);
+    if attribute & SPRITE_FLIP_HORIZONTALLY != 0 {
+      pattern_lsb = pattern_lsb.reverse_bits();
+      pattern_msb = pattern_msb.reverse_bits();
+    }
+
+    SpriteSlot {
+      x,
+      attribute,
+      pattern_lsb,
+      pattern_msb,
+      is_sprite_zero: index == 0,
+    }
+  }
+
+  /// Background pixel (palette 0-3, colour 0-3) under the current dot.
+  fn background_pixel(&self) -> (u8, u8) {
+    let x = self.dot - 1;
+    let show_background = self.mask & MASK_SHOW_BACKGROUND != 0;
+    let show_leftmost = self.mask & MASK_SHOW_BACKGROUND_LEFTMOST != 0;
+    if !show_background || (x < 8 && !show_leftmost) {
+      return (0, 0);
+    }
+
+    let bit = 0x8000 >> self.fine_x;
+    let bg = &self.background;
+    let pixel = (((bg.pattern_msb & bit) != 0) as u8) << 1 | ((bg.pattern_lsb & bit) != 0) as u8;
+    let palette = (((bg.attribute_msb & bit) != 0) as u8) << 1 | ((bg.attribute_lsb & bit) != 0) as u8;
+
+    (palette, pixel)
+  }
+
+  /// First opaque sprite pixel under the current dot, in OAM order.
+  fn sprite_pixel(&self) -> Option<(&SpriteSlot, u8)> {
+    let x = self.dot - 1;
+    let show_sprites = self.mask & MASK_SHOW_SPRITES != 0;
+    let show_leftmost = self.mask & MASK_SHOW_SPRITES_LEFTMOST != 0;
+    if !show_sprites || (x < 8 && !show_leftmost) {
+      return None;
+    }
+
+    self.sprites.iter().find_map(|sprite| {
+      let column = x.wrapping_sub(sprite.x as u16);
+      if column >= 8 {
+        return None;
+      }
+
+      let bit = 0x80 >> column;
+      let pixel = (((sprite.pattern_msb & bit) != 0) as u8) << 1 | ((sprite.pattern_lsb & bit) != 0) as u8;
+      if pixel == 0 {
+        None
+      } else {
+        Some((sprite, pixel))
+      }
+    })
+  }
+
+  /// Applies the greyscale and colour emphasis bits of PPUMASK to a palette index.
+  fn output_color(&self, color: u8) -> u16 {
+    let color = if self.mask & MASK_GREYSCALE != 0 { color & 0x30 } else { color };
+
+    let mut emphasis = self.mask >> 5;
+    if self.region != Region::Ntsc {
+      // the PAL (and Dendy) PPU swaps the red and green emphasis bits
+      emphasis = (emphasis & 0b100) | ((emphasis & 0b001) << 1) | ((emphasis & 0b010) >> 1);
+    }
+
+    (emphasis as u16) << 6 | color as u16
+  }
+
+  fn draw_pixel(&mut self) {
+    let x = self.dot - 1;
+    let (background_palette, background_pixel) = self.background_pixel();
+    let sprite = self.sprite_pixel();
+
+    let mut sprite_zero_hit = false;
+    let palette_addr = match sprite {
+      Some((sprite, sprite_pixel)) => {
+        // sprite 0 hit never happens on the last pixel of the line
+        sprite_zero_hit = sprite.is_sprite_zero && background_pixel != 0 && x != 255;
+
+        if background_pixel != 0 && sprite.attribute & SPRITE_BEHIND_BACKGROUND != 0 {
+          0x3F00 + (background_palette as u16) * 4 + background_pixel as u16
+        } else {
+          0x3F10 + ((sprite.attribute & SPRITE_PALETTE) as u16) * 4 + sprite_pixel as u16
+        }
+      }
+      None if background_pixel != 0 => 0x3F00 + (background_palette as u16) * 4 + background_pixel as u16,
+      None => 0x3F00,
+    };
+
+    if sprite_zero_hit {
+      self.status |= STATUS_SPRITE_ZERO_HIT;
+    }
+
+    let y = self.scanline as usize;
+    self.frame[y * SCREEN_WIDTH + x as usize] = self.output_color(self.vram_read(palette_addr));
+  }
+
+  fn render_dot(&mut self) {
+    let pre_render = self.scanline == self.region.pre_render_scanline();
+    if self.scanline >= VISIBLE_SCANLINES && !pre_render {
+      return;
+    }
+
+    if self.rendering_enabled() {
+      match self.dot {
+        2..=257 | 321..=337 => {
+          self.shift_background_shifters();
+          self.fetch_background_tile();
+        }
+        _ => {}
+      }
+
+      match self.dot {
+        256 => self.increment_scroll_y(),
+        257 => {
+          self.copy_horizontal_bits();
+          if pre_render {
+            // no sprites are evaluated on the pre-render line, so the first line has none
+            self.sprites.clear();
+          } else {
+            self.evaluate_sprites();
+          }
+        }
+        280..=304 if pre_render => self.copy_vertical_bits(),
+        _ => {}
+      }
+
+      // OAMADDR is used (and reset) while sprite patterns are fetched
+      if (257..=320).contains(&self.dot) {
+        self.oam_addr = 0;
+      }
+    }
+
+    if self.scanline < VISIBLE_SCANLINES && (1..=256).contains(&self.dot) {
+      self.draw_pixel();
+    }
+  }
+
+  /// Runs one dot (PPU cycle).
+  fn step(&mut self) {
+    self.render_dot();
+
+    let pre_render = self.scanline == self.region.pre_render_scanline();
+    if self.scanline == self.region.vblank_start_scanline() && self.dot == 1 {
+      if !std::mem::take(&mut self.suppress_vblank) {
+        self.status |= STATUS_VBLANK;
+        self.nmi_interrupt = self.nmi_enabled();
+      }
+    } else if pre_render && self.dot == 1 {
+      self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO_HIT | STATUS_SPRITE_OVERFLOW);
+    }
+
+    self.dot += 1;
+
+    // with rendering enabled, odd (NTSC) frames are one dot shorter: the last dot of the
+    // pre-render line is skipped
+    let skip_last_dot = pre_render
+      && self.dot == DOTS_PER_SCANLINE - 1
+      && self.frame_count % 2 == 1
+      && self.rendering_enabled()
+      && self.region.has_odd_frame_skip();
+
+    if self.dot == DOTS_PER_SCANLINE || skip_last_dot {
+      self.dot = 0;
+      self.scanline += 1;
+      if self.scanline == self.region.scanlines_per_frame() {
+        self.scanline = 0;
+        self.frame_count += 1;
+      }
+    }
+  }
+
+  /// Runs the PPU for `dots` cycles.
+  pub fn tick(&mut self, dots: u16) {
+    for _ in 0..dots {
+      self.step();
+    }
+  }
+}
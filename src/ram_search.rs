@@ -0,0 +1,125 @@
+use std::ops::RangeInclusive;
+
+use crate::bus::Bus;
+
+/// How the bytes at a candidate address are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValueType {
+  #[default]
+  U8,
+  I8,
+  /// Little-endian, the low byte at the candidate address.
+  U16,
+  I16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+  Equal,
+  NotEqual,
+  Greater,
+  Less,
+  /// The value is exactly N more than the operand (N can be negative).
+  ChangedBy(i32),
+}
+
+/// What the current values are compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+  /// The values of the previous snapshot.
+  Previous,
+  Constant(i32),
+}
+
+/// Narrows down the addresses holding a game variable (lives, health, position...) by
+/// repeatedly comparing RAM against its previous snapshot or a constant, like the RAM search of
+/// FCEUX.
+///
+/// ```text
+/// let mut search = RamSearch::new(0x0000..=0x07FF, ValueType::U8);
+/// search.reset(&cpu.bus);
+/// // ...lose a life...
+/// search.filter(&cpu.bus, Comparison::ChangedBy(-1), Operand::Previous);
+/// ```
+pub struct RamSearch {
+  pub value_type: ValueType,
+  range: RangeInclusive<u16>,
+  snapshot: Vec<u8>,
+  candidates: Vec<u16>,
+}
+
+impl RamSearch {
+  pub fn new(range: RangeInclusive<u16>, value_type: ValueType) -> Self {
+    Self {
+      value_type,
+      range,
+      snapshot: Vec::new(),
+      candidates: Vec::new(),
+    }
+  }
+
+  /// Takes a snapshot of the searched range and makes every address a candidate again.
+  pub fn reset(&mut self, bus: &Bus) {
+    self.snapshot = Self::read_range(&self.range, bus);
+    let last = match self.value_type {
+      ValueType::U8 | ValueType::I8 => Some(*self.range.end()),
+      // the high byte of a 16-bit value must be in the range too
+      ValueType::U16 | ValueType::I16 => self.range.end().checked_sub(1),
+    };
+    self.candidates = match last {
+      Some(last) => (*self.range.start()..=last).collect(),
+      // the range is shorter than one value
+      None => Vec::new(),
+    };
+  }
+
+  /// Keeps only the candidates whose current value compares as asked against `operand`, then
+  /// takes a new snapshot. Returns how many candidates are left.
+  pub fn filter(&mut self, bus: &Bus, comparison: Comparison, operand: Operand) -> usize {
+    let current = Self::read_range(&self.range, bus);
+
+    let mut candidates = std::mem::take(&mut self.candidates);
+    candidates.retain(|&addr| {
+      let value = self.value_at(&current, addr);
+      let reference = match operand {
+        Operand::Previous => self.value_at(&self.snapshot, addr),
+        Operand::Constant(constant) => constant,
+      };
+
+      match comparison {
+        Comparison::Equal => value == reference,
+        Comparison::NotEqual => value != reference,
+        Comparison::Greater => value > reference,
+        Comparison::Less => value < reference,
+        Comparison::ChangedBy(delta) => value - reference == delta,
+      }
+    });
+
+    self.candidates = candidates;
+    self.snapshot = current;
+    self.candidates.len()
+  }
+
+  pub fn candidates(&self) -> &[u16] {
+    &self.candidates
+  }
+
+  /// Value at `addr` in the last snapshot.
+  pub fn value(&self, addr: u16) -> i32 {
+    self.value_at(&self.snapshot, addr)
+  }
+
+  fn value_at(&self, snapshot: &[u8], addr: u16) -> i32 {
+    let offset = (addr - self.range.start()) as usize;
+    match self.value_type {
+      ValueType::U8 => snapshot[offset] as i32,
+      ValueType::I8 => snapshot[offset] as i8 as i32,
+      ValueType::U16 => u16::from_le_bytes([snapshot[offset], snapshot[offset + 1]]) as i32,
+      ValueType::I16 => i16::from_le_bytes([snapshot[offset], snapshot[offset + 1]]) as i32,
+    }
+  }
+
+  fn read_range(range: &RangeInclusive<u16>, bus: &Bus) -> Vec<u8> {
+    range.clone().map(|addr| bus.peek(addr)).collect()
+  }
+}
@@ -0,0 +1,226 @@
+/// Console timing region.
+///
+/// Every timing constant that differs between NTSC, PAL and Dendy consoles lives here, so the
+/// components (CPU, PPU, APU) only have to ask their `Region` instead of hardcoding NTSC values.
+/// See <https://www.nesdev.org/wiki/Cycle_reference_chart> for more info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+  #[default]
+  Ntsc,
+  Pal,
+  Dendy,
+}
+
+/// Whether the region is chosen by the user or taken from the ROM header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegionSetting {
+  #[default]
+  Auto,
+  Manual(Region),
+}
+
+const NTSC_NOISE_PERIODS: [u16; 16] = [
+  4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const PAL_NOISE_PERIODS: [u16; 16] = [
+  4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+const NTSC_DMC_RATES: [u16; 16] = [
+  428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const PAL_DMC_RATES: [u16; 16] = [
+  398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+// Frame counter steps, in CPU cycles since the frame counter was reset.
+const NTSC_FOUR_STEP_SEQUENCE: [u32; 4] = [7457, 14913, 22371, 29829];
+const NTSC_FIVE_STEP_SEQUENCE: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_FOUR_STEP_SEQUENCE: [u32; 4] = [8313, 16627, 24939, 33253];
+const PAL_FIVE_STEP_SEQUENCE: [u32; 5] = [8313, 16627, 24939, 33253, 41565];
+
+impl Region {
+  /// Decodes the CPU/PPU timing field of a NES 2.0 header (byte 12, bits 0-1).
+  /// Multi-region ROMs (value 2) run as NTSC.
+  pub fn from_nes2_timing(timing: u8) -> Self {
+    match timing & 0b0000_0011 {
+      1 => Region::Pal,
+      3 => Region::Dendy,
+      _ => Region::Ntsc,
+    }
+  }
+
+  /// Detects the region from a 16-byte iNES header.
+  ///
+  /// NES 2.0 headers carry an explicit timing field. Plain iNES headers only have the (rarely
+  /// set) TV system bit of byte 9, which cannot express Dendy.
+  /// Returns `None` if `header` is not an iNES header at all.
+  pub fn from_ines_header(header: &[u8]) -> Option<Self> {
+    if header.len() < 16 || header[0..4] != [0x4E, 0x45, 0x53, 0x1A] {
+      return None;
+    }
+
+    let is_nes2 = header[7] & 0b0000_1100 == 0b0000_1000;
+    if is_nes2 {
+      Some(Region::from_nes2_timing(header[12]))
+    } else if header[9] & 0b0000_0001 == 1 {
+      Some(Region::Pal)
+    } else {
+      Some(Region::Ntsc)
+    }
+  }
+
+  /// Frequency of the master oscillator, in Hz.
+  pub fn master_clock_hz(&self) -> f64 {
+    match self {
+      Region::Ntsc => 236.25e6 / 11.0,
+      Region::Pal | Region::Dendy => 26.601712e6,
+    }
+  }
+
+  /// How many master clocks make one CPU cycle.
+  pub fn cpu_clock_divider(&self) -> u32 {
+    match self {
+      Region::Ntsc => 12,
+      Region::Pal => 16,
+      Region::Dendy => 15,
+    }
+  }
+
+  /// How many master clocks make one PPU dot.
+  pub fn ppu_clock_divider(&self) -> u32 {
+    match self {
+      Region::Ntsc => 4,
+      Region::Pal | Region::Dendy => 5,
+    }
+  }
+
+  pub fn cpu_clock_hz(&self) -> f64 {
+    self.master_clock_hz() / self.cpu_clock_divider() as f64
+  }
+
+  /// PPU dots per CPU cycle: 3 on NTSC and Dendy, 3.2 on PAL.
+  pub fn ppu_dots_per_cpu_cycle(&self) -> f64 {
+    self.cpu_clock_divider() as f64 / self.ppu_clock_divider() as f64
+  }
+
+  pub fn scanlines_per_frame(&self) -> u16 {
+    match self {
+      Region::Ntsc => 262,
+      Region::Pal | Region::Dendy => 312,
+    }
+  }
+
+  /// Scanline on which the VBlank flag is set (and NMI fires).
+  /// Dendy has 50 extra post-render scanlines before it, instead of a longer VBlank.
+  pub fn vblank_start_scanline(&self) -> u16 {
+    match self {
+      Region::Ntsc | Region::Pal => 241,
+      Region::Dendy => 291,
+    }
+  }
+
+  /// Number of scanlines the VBlank flag stays set for.
+  pub fn vblank_scanlines(&self) -> u16 {
+    match self {
+      Region::Ntsc | Region::Dendy => 20,
+      Region::Pal => 70,
+    }
+  }
+
+  /// The last scanline of a frame, during which the PPU prefetches the first visible line.
+  pub fn pre_render_scanline(&self) -> u16 {
+    self.scanlines_per_frame() - 1
+  }
+
+  /// Only the NTSC PPU skips a dot on odd frames when rendering is enabled.
+  pub fn has_odd_frame_skip(&self) -> bool {
+    *self == Region::Ntsc
+  }
+
+  pub fn frames_per_second(&self) -> f64 {
+    let dots_per_frame = 341.0 * self.scanlines_per_frame() as f64
+      - if self.has_odd_frame_skip() { 0.5 } else { 0.0 };
+    let ppu_clock_hz = self.master_clock_hz() / self.ppu_clock_divider() as f64;
+
+    ppu_clock_hz / dots_per_frame
+  }
+
+  /// `frames_per_second` as an exact fraction (numerator, denominator), for video files.
+  pub fn frame_rate(&self) -> (u64, u64) {
+    let (master_clock, master_clock_divisor) = match self {
+      Region::Ntsc => (236_250_000, 11),
+      Region::Pal | Region::Dendy => (26_601_712, 1),
+    };
+    let half_dots_per_frame = 2 * 341 * self.scanlines_per_frame() as u64
+      - if self.has_odd_frame_skip() { 1 } else { 0 };
+
+    let numerator = 2 * master_clock;
+    let denominator = master_clock_divisor * self.ppu_clock_divider() as u64 * half_dots_per_frame;
+    let divisor = gcd(numerator, denominator);
+    (numerator / divisor, denominator / divisor)
+  }
+
+  /// APU frame counter steps in 4-step mode. The IRQ (if enabled) fires on the last one.
+  /// Dendy uses the NTSC sequencer.
+  pub fn frame_counter_four_step(&self) -> &'static [u32; 4] {
+    match self {
+      Region::Ntsc | Region::Dendy => &NTSC_FOUR_STEP_SEQUENCE,
+      Region::Pal => &PAL_FOUR_STEP_SEQUENCE,
+    }
+  }
+
+  /// APU frame counter steps in 5-step mode.
+  pub fn frame_counter_five_step(&self) -> &'static [u32; 5] {
+    match self {
+      Region::Ntsc | Region::Dendy => &NTSC_FIVE_STEP_SEQUENCE,
+      Region::Pal => &PAL_FIVE_STEP_SEQUENCE,
+    }
+  }
+
+  /// Noise channel timer periods, in CPU cycles, indexed by the low 4 bits of $400E.
+  pub fn noise_periods(&self) -> &'static [u16; 16] {
+    match self {
+      Region::Ntsc | Region::Dendy => &NTSC_NOISE_PERIODS,
+      Region::Pal => &PAL_NOISE_PERIODS,
+    }
+  }
+
+  /// DMC timer periods, in CPU cycles, indexed by the low 4 bits of $4010.
+  pub fn dmc_rates(&self) -> &'static [u16; 16] {
+    match self {
+      Region::Ntsc | Region::Dendy => &NTSC_DMC_RATES,
+      Region::Pal => &PAL_DMC_RATES,
+    }
+  }
+}
+
+impl RegionSetting {
+  /// Parses a `--region` value: `auto`, `ntsc`, `pal` or `dendy`.
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name.to_ascii_lowercase().as_str() {
+      "auto" => Ok(RegionSetting::Auto),
+      "ntsc" => Ok(RegionSetting::Manual(Region::Ntsc)),
+      "pal" => Ok(RegionSetting::Manual(Region::Pal)),
+      "dendy" => Ok(RegionSetting::Manual(Region::Dendy)),
+      _ => Err(format!("unknown region {:?} (expected auto, ntsc, pal or dendy)", name)),
+    }
+  }
+
+  /// Picks the region to run with: the manual choice if any, otherwise whatever the ROM header
+  /// says, falling back to NTSC.
+  pub fn resolve(&self, header: Option<&[u8]>) -> Region {
+    match self {
+      RegionSetting::Manual(region) => *region,
+      RegionSetting::Auto => header.and_then(Region::from_ines_header).unwrap_or_default(),
+    }
+  }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
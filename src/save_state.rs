@@ -0,0 +1,24 @@
+use crate::cpu::CPU;
+
+/// A snapshot of the whole machine (registers, memory, PPU, cartridge RAM, joypad), kept in
+/// memory to go back to it later, like the save states of the other emulators.
+#[derive(Clone)]
+pub struct SaveState {
+  cpu: CPU,
+}
+
+impl SaveState {
+  pub fn capture(cpu: &CPU) -> Self {
+    SaveState { cpu: cpu.clone() }
+  }
+
+  /// Puts the machine back in the saved state. What isn't part of the emulated machine stays as
+  /// it is: the cheats, the watched addresses, the break and jam policies and the save file.
+  pub fn restore(&self, cpu: &mut CPU) {
+    let mut restored = self.cpu.clone();
+    restored.bus.take_tools_from(&mut cpu.bus);
+    restored.break_policy = cpu.break_policy;
+    restored.jam_policy = cpu.jam_policy;
+    *cpu = restored;
+  }
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+
+/// Scales an RGB24 frame up `scale` times, each pixel becoming a `scale` x `scale` square.
+pub fn scale_frame(rgb: &[u8], width: usize, scale: usize) -> Vec<u8> {
+  let mut scaled = Vec::with_capacity(rgb.len() * scale * scale);
+  for row in rgb.chunks_exact(width * 3) {
+    let mut scaled_row = Vec::with_capacity(row.len() * scale);
+    for pixel in row.chunks_exact(3) {
+      for _ in 0..scale {
+        scaled_row.extend_from_slice(pixel);
+      }
+    }
+    for _ in 0..scale {
+      scaled.extend_from_slice(&scaled_row);
+    }
+  }
+  scaled
+}
+
+/// Encodes an RGB24 frame (the easy6502 screen, the PPU output...) as a PNG image, `scale`
+/// times its size.
+pub fn encode_png(
+  rgb: &[u8],
+  width: usize,
+  height: usize,
+  scale: usize,
+) -> Result<Vec<u8>, String> {
+  if scale == 0 {
+    return Err("the scale must be at least 1".to_string());
+  }
+  if rgb.len() != width * height * 3 {
+    return Err(format!(
+      "a {}x{} frame is {} bytes, not {}",
+      width,
+      height,
+      width * height * 3,
+      rgb.len()
+    ));
+  }
+
+  let mut png = Vec::new();
+  let mut encoder = png::Encoder::new(&mut png, (width * scale) as u32, (height * scale) as u32);
+  encoder.set_color(png::ColorType::Rgb);
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+  writer
+    .write_image_data(&scale_frame(rgb, width, scale))
+    .map_err(|err| err.to_string())?;
+  writer.finish().map_err(|err| err.to_string())?;
+  Ok(png)
+}
+
+/// Writes an RGB24 frame to a PNG file, `scale` times its size.
+pub fn save_png(
+  path: impl AsRef<Path>,
+  rgb: &[u8],
+  width: usize,
+  height: usize,
+  scale: usize,
+) -> Result<(), String> {
+  let path = path.as_ref();
+  let png = encode_png(rgb, width, height, scale)?;
+  fs::write(path, png).map_err(|err| format!("{}: {}", path.display(), err))
+}
@@ -0,0 +1,382 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, Module, AST, INT};
+
+use crate::bus::AccessKind;
+use crate::cpu::CPU;
+use crate::joypad::BUTTON_NAMES;
+use crate::overlay::Overlay;
+use crate::save_state::SaveState;
+
+/// Colour of the text when the script doesn't give one.
+const DEFAULT_TEXT_COLOR: u32 = 0xFFFFFF;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Runs a Rhai (<https://rhai.rs>) script alongside the emulator, in the spirit of the Lua scripts
+/// of FCEUX and Mesen. The script runs once when it's loaded, and registers callbacks:
+///
+/// ```text
+/// memory::read(addr)  memory::read_u16(addr)  memory::write(addr, value)
+/// memory::register(name)  memory::set_register(name, value)   pc, a, x, y, s or p
+/// memory::on_read(addr, |addr, value| ...)  memory::on_write(addr, |addr, value| ...)
+/// memory::on_exec(addr, |addr| ...)         before the instruction at addr runs
+/// emu::on_frame(|| ...)  emu::frame_count()  emu::cycles()
+/// joypad::set(#{ a: true, right: true })  joypad::get()
+/// gui::text(x, y, text[, color])  gui::box(x1, y1, x2, y2, [fill, ]outline)   colors are 0xRRGGBB
+/// savestate::save(slot)  savestate::load(slot)
+/// ```
+///
+/// The frontends call `before_instruction` from their `run_with_callback` hook and `end_frame`
+/// when a frame is complete, and draw the overlay on top of the frames they show. Memory access
+/// callbacks run before the instruction following the access. What the script draws is shown on
+/// the next completed frame.
+pub struct ScriptHost {
+  engine: Engine,
+  ast: AST,
+  state: Rc<RefCell<ScriptState>>,
+}
+
+/// What the script functions work on.
+#[derive(Default)]
+struct ScriptState {
+  /// The emulator's CPU while the script runs (swapped in and out by `ScriptHost::with_cpu`), a
+  /// placeholder otherwise.
+  cpu: CPU,
+  frame_count: u64,
+  frame_callbacks: Vec<FnPtr>,
+  exec_callbacks: Vec<(u16, FnPtr)>,
+  access_callbacks: Vec<(u16, AccessKind, FnPtr)>,
+  /// What the script draws for the next frame.
+  drawing: Overlay,
+  /// What it drew for the last completed frame.
+  overlay: Overlay,
+  save_states: HashMap<INT, SaveState>,
+}
+
+impl ScriptHost {
+  /// Compiles a script and runs it, with access to `cpu`.
+  pub fn new(source: &str, cpu: &mut CPU) -> Result<Self, String> {
+    let state = Rc::new(RefCell::new(ScriptState::default()));
+    let mut engine = Engine::new();
+    engine.register_static_module("memory", memory_module(&state).into());
+    engine.register_static_module("emu", emu_module(&state).into());
+    engine.register_static_module("joypad", joypad_module(&state).into());
+    engine.register_static_module("gui", gui_module(&state).into());
+    engine.register_static_module("savestate", savestate_module(&state).into());
+    let ast = engine.compile(source).map_err(|err| err.to_string())?;
+
+    let host = ScriptHost { engine, ast, state };
+    host.with_cpu(cpu, |host| host.engine.run_ast(&host.ast))?;
+    Ok(host)
+  }
+
+  /// Loads a script file.
+  pub fn load(path: impl AsRef<Path>, cpu: &mut CPU) -> Result<Self, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Self::new(&source, cpu).map_err(|err| format!("{}: {}", path.display(), err))
+  }
+
+  /// Runs the callbacks of the watched memory accesses since the last call, then the ones of the
+  /// instruction at the program counter. To be called before every instruction.
+  pub fn before_instruction(&mut self, cpu: &mut CPU) -> Result<(), String> {
+    let mut calls = self.access_calls(cpu);
+    let state = self.state.borrow();
+    for (_, callback) in state
+      .exec_callbacks
+      .iter()
+      .filter(|(addr, _)| *addr == cpu.program_counter)
+    {
+      calls.push((callback.clone(), vec![(cpu.program_counter as INT).into()]));
+    }
+    drop(state);
+
+    self.call(cpu, calls)
+  }
+
+  /// Runs the frame callbacks, and shows what the script drew since the last frame.
+  pub fn end_frame(&mut self, cpu: &mut CPU) -> Result<(), String> {
+    let mut calls = self.access_calls(cpu);
+    let mut state = self.state.borrow_mut();
+    state.frame_count += 1;
+    for callback in &state.frame_callbacks {
+      calls.push((callback.clone(), Vec::new()));
+    }
+    drop(state);
+
+    let result = self.call(cpu, calls);
+    let mut state = self.state.borrow_mut();
+    state.overlay = std::mem::take(&mut state.drawing);
+    result
+  }
+
+  /// Draws what the script drew for the last frame on an RGB24 frame.
+  pub fn draw_overlay(&self, rgb: &mut [u8], width: usize, height: usize) {
+    self.state.borrow().overlay.draw(rgb, width, height);
+  }
+
+  /// Number of frames completed since the script was loaded.
+  pub fn frame_count(&self) -> u64 {
+    self.state.borrow().frame_count
+  }
+
+  /// The callbacks of the memory accesses recorded by the bus.
+  fn access_calls(&self, cpu: &mut CPU) -> Vec<(FnPtr, Vec<Dynamic>)> {
+    let state = self.state.borrow();
+    let mut calls = Vec::new();
+    for access in cpu.bus.take_accesses() {
+      for (_, _, callback) in state
+        .access_callbacks
+        .iter()
+        .filter(|(addr, kind, _)| *addr == access.addr && *kind == access.kind)
+      {
+        let args = vec![(access.addr as INT).into(), (access.value as INT).into()];
+        calls.push((callback.clone(), args));
+      }
+    }
+    calls
+  }
+
+  fn call(&self, cpu: &mut CPU, calls: Vec<(FnPtr, Vec<Dynamic>)>) -> Result<(), String> {
+    if calls.is_empty() {
+      return Ok(());
+    }
+    self.with_cpu(cpu, |host| {
+      for (callback, args) in calls {
+        // what the callbacks return is ignored
+        let _ = callback.call::<Dynamic>(&host.engine, &host.ast, args)?;
+      }
+      Ok(())
+    })
+  }
+
+  /// Runs `f` with `cpu` in the script state. The memory accesses made by the script itself
+  /// don't trigger callbacks.
+  fn with_cpu(
+    &self,
+    cpu: &mut CPU,
+    f: impl FnOnce(&Self) -> ScriptResult<()>,
+  ) -> Result<(), String> {
+    std::mem::swap(cpu, &mut self.state.borrow_mut().cpu);
+    let result = f(self);
+    std::mem::swap(cpu, &mut self.state.borrow_mut().cpu);
+    cpu.bus.take_accesses();
+    result.map_err(|err| err.to_string())
+  }
+}
+
+fn to_address(addr: INT) -> ScriptResult<u16> {
+  u16::try_from(addr).map_err(|_| format!("invalid address {}", addr).into())
+}
+
+fn to_byte(value: INT) -> ScriptResult<u8> {
+  u8::try_from(value).map_err(|_| format!("invalid byte {}", value).into())
+}
+
+fn to_color(color: INT) -> ScriptResult<u32> {
+  match u32::try_from(color) {
+    Ok(color) if color <= 0xFFFFFF => Ok(color),
+    _ => Err(format!("invalid colour {:#X}, expected 0xRRGGBB", color).into()),
+  }
+}
+
+fn memory_module(state: &Rc<RefCell<ScriptState>>) -> Module {
+  let mut module = Module::new();
+
+  let shared = state.clone();
+  module.set_native_fn("read", move |addr: INT| {
+    Ok(shared.borrow().cpu.bus.peek(to_address(addr)?) as INT)
+  });
+  let shared = state.clone();
+  module.set_native_fn("read_u16", move |addr: INT| {
+    let addr = to_address(addr)?;
+    let bus = &shared.borrow().cpu.bus;
+    Ok(u16::from_le_bytes([bus.peek(addr), bus.peek(addr.wrapping_add(1))]) as INT)
+  });
+  let shared = state.clone();
+  module.set_native_fn("write", move |addr: INT, value: INT| {
+    let (addr, value) = (to_address(addr)?, to_byte(value)?);
+    shared.borrow_mut().cpu.mem_write(addr, value);
+    Ok(())
+  });
+
+  let shared = state.clone();
+  module.set_native_fn("register", move |name: &str| {
+    let cpu = &shared.borrow().cpu;
+    let value = match name {
+      "pc" => cpu.program_counter,
+      "a" => cpu.accumulator as u16,
+      "x" => cpu.register_x as u16,
+      "y" => cpu.register_y as u16,
+      "s" => cpu.stack_pointer as u16,
+      "p" => cpu.status as u16,
+      _ => return Err(format!("unknown register {:?}", name).into()),
+    };
+    Ok(value as INT)
+  });
+  let shared = state.clone();
+  module.set_native_fn("set_register", move |name: &str, value: INT| {
+    let cpu = &mut shared.borrow_mut().cpu;
+    match name {
+      "pc" => cpu.program_counter = to_address(value)?,
+      "a" => cpu.accumulator = to_byte(value)?,
+      "x" => cpu.register_x = to_byte(value)?,
+      "y" => cpu.register_y = to_byte(value)?,
+      "s" => cpu.stack_pointer = to_byte(value)?,
+      "p" => cpu.status = to_byte(value)?,
+      _ => return Err(format!("unknown register {:?}", name).into()),
+    }
+    Ok(())
+  });
+
+  for (name, kind) in [
+    ("on_read", AccessKind::Read),
+    ("on_write", AccessKind::Write),
+  ] {
+    let shared = state.clone();
+    module.set_native_fn(name, move |addr: INT, callback: FnPtr| {
+      let addr = to_address(addr)?;
+      let mut state = shared.borrow_mut();
+      state.cpu.bus.watch(addr);
+      state.access_callbacks.push((addr, kind, callback));
+      Ok(())
+    });
+  }
+  let shared = state.clone();
+  module.set_native_fn("on_exec", move |addr: INT, callback: FnPtr| {
+    let addr = to_address(addr)?;
+    shared.borrow_mut().exec_callbacks.push((addr, callback));
+    Ok(())
+  });
+
+  module
+}
+
+fn emu_module(state: &Rc<RefCell<ScriptState>>) -> Module {
+  let mut module = Module::new();
+
+  let shared = state.clone();
+  module.set_native_fn("on_frame", move |callback: FnPtr| {
+    shared.borrow_mut().frame_callbacks.push(callback);
+    Ok(())
+  });
+  let shared = state.clone();
+  module.set_native_fn("frame_count", move || {
+    Ok(shared.borrow().frame_count as INT)
+  });
+  let shared = state.clone();
+  module.set_native_fn("cycles", move || Ok(shared.borrow().cpu.cycles() as INT));
+
+  module
+}
+
+fn joypad_module(state: &Rc<RefCell<ScriptState>>) -> Module {
+  let mut module = Module::new();
+
+  let shared = state.clone();
+  module.set_native_fn("set", move |buttons: Map| {
+    let mut pressed = 0;
+    for (name, value) in buttons {
+      let bit = BUTTON_NAMES
+        .iter()
+        .position(|button| *button == name.as_str())
+        .ok_or_else(|| format!("unknown button {:?}", name.as_str()))?;
+      let value = value
+        .as_bool()
+        .map_err(|_| format!("button {:?} should be true or false", name.as_str()))?;
+      if value {
+        pressed |= 1 << bit;
+      }
+    }
+    shared.borrow_mut().cpu.bus.joypad.buttons = pressed;
+    Ok(())
+  });
+  let shared = state.clone();
+  module.set_native_fn("get", move || {
+    let buttons = shared.borrow().cpu.bus.joypad.buttons;
+    let map: Map = BUTTON_NAMES
+      .iter()
+      .enumerate()
+      .map(|(bit, name)| ((*name).into(), (buttons & (1 << bit) != 0).into()))
+      .collect();
+    Ok(map)
+  });
+
+  module
+}
+
+fn gui_module(state: &Rc<RefCell<ScriptState>>) -> Module {
+  let mut module = Module::new();
+
+  let shared = state.clone();
+  module.set_native_fn("text", move |x: INT, y: INT, text: &str| {
+    let drawing = &mut shared.borrow_mut().drawing;
+    drawing.text(x as i32, y as i32, text, DEFAULT_TEXT_COLOR);
+    Ok(())
+  });
+  let shared = state.clone();
+  module.set_native_fn("text", move |x: INT, y: INT, text: &str, color: INT| {
+    let color = to_color(color)?;
+    let drawing = &mut shared.borrow_mut().drawing;
+    drawing.text(x as i32, y as i32, text, color);
+    Ok(())
+  });
+  let shared = state.clone();
+  module.set_native_fn(
+    "box",
+    move |x1: INT, y1: INT, x2: INT, y2: INT, outline: INT| {
+      let outline = to_color(outline)?;
+      let drawing = &mut shared.borrow_mut().drawing;
+      drawing.rect(x1 as i32, y1 as i32, x2 as i32, y2 as i32, None, outline);
+      Ok(())
+    },
+  );
+  let shared = state.clone();
+  module.set_native_fn(
+    "box",
+    move |x1: INT, y1: INT, x2: INT, y2: INT, fill: INT, outline: INT| {
+      let (fill, outline) = (to_color(fill)?, to_color(outline)?);
+      let drawing = &mut shared.borrow_mut().drawing;
+      drawing.rect(
+        x1 as i32,
+        y1 as i32,
+        x2 as i32,
+        y2 as i32,
+        Some(fill),
+        outline,
+      );
+      Ok(())
+    },
+  );
+
+  module
+}
+
+fn savestate_module(state: &Rc<RefCell<ScriptState>>) -> Module {
+  let mut module = Module::new();
+
+  let shared = state.clone();
+  module.set_native_fn("save", move |slot: INT| {
+    let mut state = shared.borrow_mut();
+    let save_state = SaveState::capture(&state.cpu);
+    state.save_states.insert(slot, save_state);
+    Ok(())
+  });
+  let shared = state.clone();
+  module.set_native_fn("load", move |slot: INT| {
+    let state = &mut *shared.borrow_mut();
+    let save_state = state
+      .save_states
+      .get(&slot)
+      .ok_or_else(|| format!("nothing saved in slot {}", slot))?;
+    save_state.restore(&mut state.cpu);
+    Ok(())
+  });
+
+  module
+}
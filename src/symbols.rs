@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::cartridge::{PRG_RAM_START, PRG_ROM_START};
+use crate::loader::{parse_debug_fields, parse_debug_number};
+
+/// Names for addresses, from the label files of assemblers and other emulators, so the debugging
+/// tools can show `JSR update_snake` instead of `JSR $068D`.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+  labels: HashMap<u16, String>,
+  addresses: HashMap<String, u16>,
+}
+
+impl SymbolTable {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Names an address. A later name for the same address replaces the earlier one.
+  pub fn add(&mut self, address: u16, name: &str) {
+    if let Some(old) = self.labels.insert(address, name.to_string()) {
+      self.addresses.remove(&old);
+    }
+    self.addresses.insert(name.to_string(), address);
+  }
+
+  pub fn label(&self, address: u16) -> Option<&str> {
+    self.labels.get(&address).map(String::as_str)
+  }
+
+  pub fn address(&self, name: &str) -> Option<u16> {
+    self.addresses.get(name).copied()
+  }
+
+  pub fn len(&self) -> usize {
+    self.labels.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.labels.is_empty()
+  }
+
+  /// Adds the labels of another table.
+  pub fn extend(&mut self, other: &SymbolTable) {
+    for (address, name) in &other.labels {
+      self.add(*address, name);
+    }
+  }
+
+  /// The labels (`type=lab`) of a ca65/ld65 debug file.
+  pub fn from_ld65_dbg(text: &str) -> Result<Self, String> {
+    let mut symbols = Self::new();
+    for (index, line) in text.lines().enumerate() {
+      let Some(("sym", fields)) = line.split_once(char::is_whitespace) else {
+        continue;
+      };
+      let fields = parse_debug_fields(fields);
+      let field = |name: &str| {
+        fields
+          .iter()
+          .find(|(key, _)| *key == name)
+          .map(|(_, value)| value.as_str())
+      };
+      if field("type") != Some("lab") {
+        continue;
+      }
+
+      let (Some(name), Some(value)) = (field("name"), field("val")) else {
+        return Err(format!("line {}: label without a name or value", index + 1));
+      };
+      let address = parse_debug_number(value)
+        .and_then(|value| u16::try_from(value).ok())
+        .ok_or(format!("line {}: invalid value {:?}", index + 1, value))?;
+      symbols.add(address, name);
+    }
+    Ok(symbols)
+  }
+
+  /// An FCEUX name list (`$068D#update_snake#comment` lines).
+  pub fn from_fceux_nl(text: &str) -> Result<Self, String> {
+    let mut symbols = Self::new();
+    for (index, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+
+      let mut parts = line.split('#');
+      let address = parts.next().unwrap_or("");
+      let name = parts.next().unwrap_or("");
+      let address = address
+        .strip_prefix('$')
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+        .ok_or(format!("line {}: invalid address {:?}", index + 1, address))?;
+      if !name.is_empty() {
+        symbols.add(address, name);
+      }
+    }
+    Ok(symbols)
+  }
+
+  /// A Mesen label file (`R:0010:lives:comment` lines). Addresses are offsets in a memory type,
+  /// mapped to where that memory is for the CPU: internal RAM and registers are where they
+  /// are, save/work RAM is at $6000 and PRG ROM at $8000 (as mapped by NROM).
+  pub fn from_mesen_mlb(text: &str) -> Result<Self, String> {
+    let mut symbols = Self::new();
+    for (index, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+      let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+      let mut parts = line.splitn(4, ':');
+      let (Some(memory), Some(range), Some(name)) = (parts.next(), parts.next(), parts.next())
+      else {
+        return Err(error("expected TYPE:ADDRESS:LABEL"));
+      };
+      let base = match memory {
+        "R" | "NesInternalRam" | "G" | "NesMemory" => 0,
+        "S" | "W" | "NesSaveRam" | "NesWorkRam" => PRG_RAM_START,
+        "P" | "NesPrgRom" => PRG_ROM_START,
+        _ => continue, // CHR and other memory the CPU doesn't see
+      };
+      // ranges (`0010-0011`) are named by their first address
+      let start = range.split('-').next().unwrap_or(range);
+      let offset = u16::from_str_radix(start, 16).map_err(|_| error("invalid address"))?;
+      if !name.is_empty() {
+        symbols.add(base.wrapping_add(offset), name);
+      }
+    }
+    Ok(symbols)
+  }
+
+  /// Loads a label file, in the format its extension says (`.dbg`, `.nl` or `.mlb`).
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("dbg") => Self::from_ld65_dbg(&text),
+      Some("nl") => Self::from_fceux_nl(&text),
+      Some("mlb") => Self::from_mesen_mlb(&text),
+      _ => Err("unknown label file format, expected .dbg, .nl or .mlb".to_string()),
+    }
+    .map_err(|err| format!("{}: {}", path.display(), err))
+  }
+}
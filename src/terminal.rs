@@ -0,0 +1,57 @@
+use std::fmt::Write;
+
+use crate::cpu::CPU;
+
+/// The upper half block: its foreground is the top pixel and its background the bottom one.
+const UPPER_HALF_BLOCK: char = '▀';
+const RESET: &str = "\x1b[0m";
+
+/// Draws an RGB24 frame with truecolour half blocks, two rows of pixels per line of text. An
+/// odd last row gets a black bottom half.
+pub fn half_block_lines(frame: &[u8], width: usize, height: usize) -> Vec<String> {
+  let pixel = |x: usize, y: usize| {
+    if y >= height {
+      return (0, 0, 0);
+    }
+    let i = (y * width + x) * 3;
+    (frame[i], frame[i + 1], frame[i + 2])
+  };
+
+  (0..height)
+    .step_by(2)
+    .map(|y| {
+      let mut line = String::new();
+      let mut colors = None;
+      for x in 0..width {
+        let (top, bottom) = (pixel(x, y), pixel(x, y + 1));
+        // the colours only need to be set again when they change
+        if colors != Some((top, bottom)) {
+          write!(
+            line,
+            "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+            top.0, top.1, top.2, bottom.0, bottom.1, bottom.2
+          )
+          .unwrap();
+          colors = Some((top, bottom));
+        }
+        line.push(UPPER_HALF_BLOCK);
+      }
+      line.push_str(RESET);
+      line
+    })
+    .collect()
+}
+
+/// The registers and flags of the CPU, one per line, for a side panel.
+pub fn register_lines(cpu: &CPU) -> Vec<String> {
+  vec![
+    format!("PC  ${:04X}", cpu.program_counter),
+    format!("A   ${:02X}", cpu.accumulator),
+    format!("X   ${:02X}", cpu.register_x),
+    format!("Y   ${:02X}", cpu.register_y),
+    format!("SP  ${:02X}", cpu.stack_pointer),
+    "    NV-BDIZC".to_string(),
+    format!("P   {:08b}", cpu.status),
+    format!("cycles {}", cpu.cycles()),
+  ]
+}
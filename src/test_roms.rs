@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use crate::bus::Bus;
+use crate::cartridge::{Cartridge, PRG_RAM_START};
+use crate::cycle_cpu::{CpuBus, CycleCpu};
+
+/// Where Klaus Dormann's functional and interrupt tests start.
+pub const KLAUS_START: u16 = 0x0400;
+/// The interrupt feedback register of Klaus Dormann's interrupt test: bit 0 drives the IRQ line
+/// and bit 1 the NMI line.
+pub const INTERRUPT_PORT: u16 = 0xBFFC;
+const IRQ_BIT: u8 = 0b01;
+const NMI_BIT: u8 = 0b10;
+/// Number of instructions kept in the trace of a trap.
+const TRACE_LENGTH: usize = 20;
+
+/// Blargg's test ROMs report through PRG RAM: a status byte at $6000, the signature at
+/// $6001-$6003 once the status is valid, and a zero-terminated message from $6004.
+const BLARGG_STATUS: u16 = PRG_RAM_START;
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const BLARGG_MESSAGE: u16 = PRG_RAM_START + 4;
+const BLARGG_RUNNING: u8 = 0x80;
+const BLARGG_RESET_REQUIRED: u8 = 0x81;
+/// How long to wait before pressing reset when a ROM asks for it (at least 100ms).
+const BLARGG_RESET_DELAY_CYCLES: u64 = 200_000;
+
+/// 64 KiB of RAM holding a whole test image, with the interrupt feedback register at
+/// `INTERRUPT_PORT`.
+pub struct KlausBus {
+  pub memory: Vec<u8>,
+  nmi: bool,
+}
+
+impl KlausBus {
+  /// A bus with `image` loaded at $0000 (the images are the whole 64 KiB address space).
+  pub fn new(image: &[u8]) -> Result<Self, String> {
+    if image.len() > 0x10000 {
+      return Err(format!(
+        "the image is {} bytes, more than the 64 KiB address space",
+        image.len()
+      ));
+    }
+    let mut memory = vec![0; 0x10000];
+    memory[..image.len()].copy_from_slice(image);
+    Ok(KlausBus { memory, nmi: false })
+  }
+}
+
+impl CpuBus for KlausBus {
+  fn read(&mut self, addr: u16) -> u8 {
+    self.memory[addr as usize]
+  }
+
+  fn write(&mut self, addr: u16, data: u8) {
+    let old = self.memory[addr as usize];
+    if addr == INTERRUPT_PORT && data & NMI_BIT != 0 && old & NMI_BIT == 0 {
+      self.nmi = true;
+    }
+    self.memory[addr as usize] = data;
+  }
+
+  fn poll_nmi(&mut self) -> bool {
+    std::mem::take(&mut self.nmi)
+  }
+
+  fn irq(&mut self) -> bool {
+    self.memory[INTERRUPT_PORT as usize] & IRQ_BIT != 0
+  }
+}
+
+/// Where a test program got stuck, with the instructions that led there.
+#[derive(Debug)]
+pub struct Trap {
+  pub pc: u16,
+  /// Number of instructions run before the trap.
+  pub instructions: u64,
+  /// The registers before each of the last instructions, oldest first.
+  pub trace: Vec<String>,
+}
+
+impl Trap {
+  /// Fails unless the program trapped at `success`, describing where it stopped otherwise.
+  pub fn expect_at(&self, success: u16) -> Result<(), String> {
+    if self.pc == success {
+      return Ok(());
+    }
+    Err(format!(
+      "trapped at ${:04X} after {} instructions (success is ${:04X})\n{}",
+      self.pc,
+      self.instructions,
+      success,
+      self.trace.join("\n")
+    ))
+  }
+}
+
+/// Runs until an instruction jumps (or branches) to itself, the way the test programs stop both
+/// on success and on failure, giving up after `max_instructions`.
+pub fn run_until_trap<B: CpuBus>(
+  cpu: &mut CycleCpu,
+  bus: &mut B,
+  max_instructions: u64,
+) -> Result<Trap, String> {
+  let mut trace = VecDeque::with_capacity(TRACE_LENGTH);
+  for instructions in 0..max_instructions {
+    let pc = cpu.program_counter;
+    if trace.len() == TRACE_LENGTH {
+      trace.pop_front();
+    }
+    trace.push_back(format!(
+      "{:04X}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+      pc, cpu.accumulator, cpu.register_x, cpu.register_y, cpu.status, cpu.stack_pointer
+    ));
+
+    cpu
+      .step(bus)
+      .map_err(|err| format!("{}\n{}", err, Vec::from(trace.clone()).join("\n")))?;
+
+    if cpu.program_counter == pc {
+      return Ok(Trap {
+        pc,
+        instructions: instructions + 1,
+        trace: trace.into(),
+      });
+    }
+  }
+  Err(format!(
+    "no trap after {} instructions (PC ${:04X})\n{}",
+    max_instructions,
+    cpu.program_counter,
+    Vec::from(trace).join("\n")
+  ))
+}
+
+/// The final status of a blargg test ROM: 0 when it passed, otherwise the number of the failed
+/// test (or another error code), with the text the ROM printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlarggResult {
+  pub status: u8,
+  pub message: String,
+}
+
+impl BlarggResult {
+  pub fn passed(&self) -> bool {
+    self.status == 0
+  }
+}
+
+/// The status byte at $6000, once the ROM has written the signature that makes it valid.
+pub fn blargg_status(bus: &Bus) -> Option<u8> {
+  let signature = [1, 2, 3].map(|offset| bus.peek(BLARGG_STATUS + offset));
+  (signature == BLARGG_SIGNATURE).then(|| bus.peek(BLARGG_STATUS))
+}
+
+/// The zero-terminated text at $6004.
+pub fn blargg_message(bus: &Bus) -> String {
+  let text: Vec<u8> = (BLARGG_MESSAGE..=0x7FFF)
+    .map(|addr| bus.peek(addr))
+    .take_while(|&byte| byte != 0)
+    .collect();
+  String::from_utf8_lossy(&text).trim_end().to_string()
+}
+
+/// Runs a blargg test ROM from reset until it reports a result, pressing reset when it asks for
+/// it, giving up after `max_cycles`.
+pub fn run_blargg(
+  cpu: &mut CycleCpu,
+  bus: &mut Bus,
+  max_cycles: u64,
+) -> Result<BlarggResult, String> {
+  cpu.reset(bus);
+  let mut reset_at = None;
+  while cpu.cycles() < max_cycles {
+    cpu.step(bus).map_err(|err| err.to_string())?;
+
+    match blargg_status(bus) {
+      Some(BLARGG_RUNNING) | None => reset_at = None,
+      Some(BLARGG_RESET_REQUIRED) => {
+        let at = *reset_at.get_or_insert(cpu.cycles() + BLARGG_RESET_DELAY_CYCLES);
+        if cpu.cycles() >= at {
+          cpu.reset(bus);
+          reset_at = None;
+        }
+      }
+      Some(status) => {
+        return Ok(BlarggResult {
+          status,
+          message: blargg_message(bus),
+        })
+      }
+    }
+  }
+  Err(format!(
+    "no result after {} cycles: {}",
+    max_cycles,
+    blargg_message(bus)
+  ))
+}
+
+/// Loads a blargg test ROM and runs it on a console with the cycle-stepped CPU.
+pub fn run_blargg_rom(path: impl AsRef<Path>, max_cycles: u64) -> Result<BlarggResult, String> {
+  let path = path.as_ref();
+  let bytes = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+  // not Cartridge::load: a battery-backed test ROM would leave a save file behind
+  let cartridge = Cartridge::from_ines(&bytes)?;
+  let mut bus = Bus::new();
+  bus.insert_cartridge(cartridge);
+  run_blargg(&mut CycleCpu::new(), &mut bus, max_cycles)
+}
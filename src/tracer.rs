@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::cpu::{CpuError, CPU};
+use crate::disassembler;
+use crate::symbols::SymbolTable;
+
+const JSR: u8 = 0x20;
+
+/// A condition on the state of the CPU.
+pub type Condition = Box<dyn Fn(&CPU) -> bool>;
+
+/// Logs every instruction the CPU executes, with the registers and the cycle count before it
+/// runs, in the format of the nestest log:
+///
+/// ```text
+/// 0600  A9 01     LDA #$01                        A:00 X:00 Y:00 P:00 SP:FF CYC:0
+/// ```
+///
+/// Lines are written to the output as they come or, in ring buffer mode, only the last N are
+/// kept in memory and dumped (to the output, or stderr) when `run` stops with an error or panics.
+/// Callers running the CPU themselves call `dump_ring_buffer` on errors.
+#[derive(Default)]
+pub struct Tracer {
+  /// Only trace instructions in this address range.
+  pub pc_range: Option<RangeInclusive<u16>>,
+  /// Only trace when this holds.
+  pub condition: Option<Condition>,
+  /// Only trace the first instruction of each subroutine (the one a JSR jumps to).
+  pub subroutine_entries_only: bool,
+  /// Labels to show instead of addresses.
+  pub symbols: SymbolTable,
+  output: Option<Box<dyn Write>>,
+  ring_buffer: Option<VecDeque<String>>,
+  ring_buffer_capacity: usize,
+  after_jsr: bool,
+}
+
+impl Tracer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// A tracer writing to a (new or truncated) file.
+  pub fn to_file(path: impl AsRef<Path>) -> io::Result<Self> {
+    let mut tracer = Self::new();
+    tracer.set_output(Box::new(BufWriter::new(File::create(path)?)));
+    Ok(tracer)
+  }
+
+  pub fn set_output(&mut self, output: Box<dyn Write>) {
+    self.output = Some(output);
+  }
+
+  /// Keeps only the last `capacity` lines in memory instead of writing them out.
+  pub fn set_ring_buffer(&mut self, capacity: usize) {
+    self.ring_buffer = Some(VecDeque::with_capacity(capacity));
+    self.ring_buffer_capacity = capacity;
+  }
+
+  /// Lines in the ring buffer, oldest first.
+  pub fn ring_buffer(&self) -> impl Iterator<Item = &str> {
+    self.ring_buffer.iter().flatten().map(String::as_str)
+  }
+
+  /// Formats the state of the CPU before it runs the instruction at the program counter.
+  pub fn format_line(cpu: &CPU) -> String {
+    Self::format_line_with_symbols(cpu, &SymbolTable::new())
+  }
+
+  /// Like `format_line`, with labels in the instruction.
+  pub fn format_line_with_symbols(cpu: &CPU, symbols: &SymbolTable) -> String {
+    let pc = cpu.program_counter;
+    let (instruction, size) = disassembler::disassemble_with_symbols(&cpu.bus, pc, symbols);
+    let bytes: Vec<String> = (0..size)
+      .map(|offset| format!("{:02X}", cpu.bus.peek(pc.wrapping_add(offset))))
+      .collect();
+
+    format!(
+      "{:04X}  {:<8}  {:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+      pc,
+      bytes.join(" "),
+      instruction,
+      cpu.accumulator,
+      cpu.register_x,
+      cpu.register_y,
+      cpu.status,
+      cpu.stack_pointer,
+      cpu.cycles()
+    )
+  }
+
+  /// Traces the instruction the CPU is about to run (call it from `run_with_callback`).
+  pub fn trace(&mut self, cpu: &CPU) {
+    let code = cpu.bus.peek(cpu.program_counter);
+    let entering_subroutine = std::mem::replace(&mut self.after_jsr, code == JSR);
+
+    let pc = cpu.program_counter;
+    let in_range = self
+      .pc_range
+      .as_ref()
+      .is_none_or(|range| range.contains(&pc));
+    let traced = (!self.subroutine_entries_only || entering_subroutine)
+      && in_range
+      && self
+        .condition
+        .as_ref()
+        .is_none_or(|condition| condition(cpu));
+    if traced {
+      let line = Self::format_line_with_symbols(cpu, &self.symbols);
+      match &mut self.ring_buffer {
+        Some(ring_buffer) => {
+          ring_buffer.push_back(line);
+          // a capacity of 0 keeps nothing
+          if ring_buffer.len() > self.ring_buffer_capacity {
+            ring_buffer.pop_front();
+          }
+        }
+        None => self.write_line(&line),
+      }
+    }
+  }
+
+  /// Runs the CPU, tracing every instruction. If it fails or panics, the ring buffer is dumped
+  /// first.
+  pub fn run(&mut self, cpu: &mut CPU) -> Result<(), CpuError> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      cpu.run_with_callback(|cpu| self.trace(cpu))
+    }))
+    .unwrap_or_else(|payload| {
+      self.dump_ring_buffer("the CPU panicked");
+      panic::resume_unwind(payload)
+    });
+
+    if let Err(err) = &result {
+      self.dump_ring_buffer(&err.to_string());
+    }
+    self.flush();
+    result
+  }
+
+  /// Writes out (and empties) the ring buffer, after a line explaining why.
+  pub fn dump_ring_buffer(&mut self, reason: &str) {
+    let lines: Vec<String> = match &mut self.ring_buffer {
+      Some(ring_buffer) if !ring_buffer.is_empty() => ring_buffer.drain(..).collect(),
+      _ => return,
+    };
+
+    self.write_line(&format!("{}, last {} instructions:", reason, lines.len()));
+    for line in &lines {
+      self.write_line(line);
+    }
+    self.flush();
+  }
+
+  pub fn flush(&mut self) {
+    if let Some(output) = &mut self.output {
+      if let Err(err) = output.flush() {
+        eprintln!("could not write the trace: {}", err);
+      }
+    }
+  }
+
+  fn write_line(&mut self, line: &str) {
+    match &mut self.output {
+      Some(output) => {
+        if let Err(err) = writeln!(output, "{}", line) {
+          eprintln!("could not write the trace: {}", err);
+        }
+      }
+      None => eprintln!("{}", line),
+    }
+  }
+}
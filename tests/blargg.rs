@@ -0,0 +1,152 @@
+//! Blargg's test ROMs, which report through the $6000 status protocol. The ROMs aren't vendored:
+//! put the `.nes` files in `tests/fixtures/blargg` (or point `BLARGG_TESTS_DIR` at a directory of
+//! them) and run the ignored suite with `--ignored`. It fails when there are no ROMs.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use nes_emulator_rust::bus::Bus;
+use nes_emulator_rust::cartridge::Cartridge;
+use nes_emulator_rust::cycle_cpu::CycleCpu;
+use nes_emulator_rust::test_roms::{blargg_status, run_blargg, run_blargg_rom};
+
+/// A minute of NTSC CPU time.
+const MAX_CYCLES: u64 = 60 * 1_789_773;
+
+fn bus_with_program(program: &[u8]) -> Bus {
+  let mut bus = Bus::new();
+  bus.insert_cartridge(Cartridge::from_ines(&common::nrom_image(0, program)).unwrap());
+  bus
+}
+
+/// LDA #value; STA addr
+fn store(code: &mut Vec<u8>, addr: u16, value: u8) {
+  let [lo, hi] = addr.to_le_bytes();
+  code.extend([0xA9, value, 0x8D, lo, hi]);
+}
+
+/// Marks the test as running and writes the signature, like the ROMs do when they start.
+fn start(code: &mut Vec<u8>) {
+  store(code, 0x6000, 0x80);
+  for (offset, byte) in [0xDE, 0xB0, 0x61].into_iter().enumerate() {
+    store(code, 0x6001 + offset as u16, byte);
+  }
+}
+
+/// Starts, writes the message and then the status, and stops.
+fn report(code: &mut Vec<u8>, status: u8, message: &str) {
+  start(code);
+  for (offset, byte) in message.bytes().chain([0]).enumerate() {
+    store(code, 0x6004 + offset as u16, byte);
+  }
+  store(code, 0x6000, status);
+  let [lo, hi] = (0x8000 + code.len() as u16).to_le_bytes();
+  code.extend([0x4C, lo, hi]); // JMP *
+}
+
+#[test]
+fn test_passing_rom() {
+  // arrange
+  let mut program = Vec::new();
+  report(&mut program, 0x00, "all_instrs\n\nPassed\n");
+  let mut bus = bus_with_program(&program);
+
+  // act
+  let result = run_blargg(&mut CycleCpu::new(), &mut bus, MAX_CYCLES).unwrap();
+
+  // assert
+  assert!(result.passed());
+  assert_eq!(result.message, "all_instrs\n\nPassed");
+}
+
+#[test]
+fn test_failing_rom() {
+  // arrange
+  let mut program = Vec::new();
+  report(&mut program, 0x03, "Failed #3");
+  let mut bus = bus_with_program(&program);
+
+  // act
+  let result = run_blargg(&mut CycleCpu::new(), &mut bus, MAX_CYCLES).unwrap();
+
+  // assert
+  assert!(!result.passed());
+  assert_eq!(result.status, 3);
+  assert_eq!(result.message, "Failed #3");
+}
+
+#[test]
+fn test_status_is_ignored_until_the_signature_is_written() {
+  // arrange
+  let mut program = Vec::new();
+  store(&mut program, 0x6000, 0x00);
+  program.extend([0x4C, 0x05, 0x80]); // JMP *
+  let mut bus = bus_with_program(&program);
+
+  // act
+  let result = run_blargg(&mut CycleCpu::new(), &mut bus, 10_000);
+
+  // assert
+  assert_eq!(blargg_status(&bus), None);
+  assert!(result
+    .unwrap_err()
+    .starts_with("no result after 10000 cycles"));
+}
+
+#[test]
+fn test_reset_is_pressed_when_the_rom_asks_for_it() {
+  // arrange
+  let mut program = vec![0xA5, 0x10]; // LDA $10
+  let first_run_len = 2 + 5 * 5 + 3; // INC, start, status, JMP
+  program.extend([0xD0, first_run_len as u8]); // BNE second_run
+  program.extend([0xE6, 0x10]); // INC $10
+  start(&mut program);
+  store(&mut program, 0x6000, 0x81);
+  let [lo, hi] = (0x8000 + program.len() as u16).to_le_bytes();
+  program.extend([0x4C, lo, hi]); // JMP *
+  report(&mut program, 0x00, "Passed"); // second_run
+  let mut bus = bus_with_program(&program);
+
+  // act
+  let result = run_blargg(&mut CycleCpu::new(), &mut bus, MAX_CYCLES).unwrap();
+
+  // assert
+  assert!(result.passed());
+  assert_eq!(result.message, "Passed");
+  assert_eq!(bus.peek(0x0010), 1);
+}
+
+#[test]
+#[ignore = "needs blargg's test ROMs"]
+fn test_blargg_suite() {
+  let dir = std::env::var_os("BLARGG_TESTS_DIR")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| PathBuf::from("tests/fixtures/blargg"));
+  let entries = fs::read_dir(&dir).unwrap_or_else(|err| panic!("{}: {}", dir.display(), err));
+  let mut roms: Vec<PathBuf> = entries
+    .filter_map(|entry| Some(entry.ok()?.path()))
+    .filter(|path| path.extension().is_some_and(|ext| ext == "nes"))
+    .collect();
+  roms.sort();
+  assert!(!roms.is_empty(), "no blargg test ROMs in {}", dir.display());
+
+  let mut failures = Vec::new();
+  for rom in &roms {
+    let name = rom.file_name().unwrap().to_string_lossy();
+    match run_blargg_rom(rom, MAX_CYCLES) {
+      Ok(result) if result.passed() => println!("{}: passed", name),
+      Ok(result) => {
+        println!("{}: failed with status {}", name, result.status);
+        failures.push(format!("{}: {}", name, result.message));
+      }
+      Err(err) => {
+        println!("{}: {}", name, err);
+        failures.push(format!("{}: {}", name, err));
+      }
+    }
+  }
+  println!("{} of {} passed", roms.len() - failures.len(), roms.len());
+  assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
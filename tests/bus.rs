@@ -0,0 +1,128 @@
+use nes_emulator_rust::bus::{AccessKind, MemoryAccess};
+use nes_emulator_rust::{cpu::CPU, ppu::PPU};
+
+#[test]
+fn test_oam_dma_copies_page_into_oam_starting_at_oam_addr() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  for offset in 0..=0xFFu16 {
+    cpu.mem_write(0x0300 + offset, offset as u8);
+  }
+  let program = vec![
+    0xA9, 0x10, 0x8D, 0x03, 0x20, // LDA #$10; STA $2003
+    0xA9, 0x03, 0x8D, 0x14, 0x40, // LDA #$03; STA $4014
+    0x00, // BRK
+  ];
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  let ppu = cpu.bus.ppu.as_ref().unwrap();
+  assert_eq!(ppu.oam_data[0x10], 0x00);
+  assert_eq!(ppu.oam_data[0xFF], 0xEF);
+  assert_eq!(ppu.oam_data[0x00], 0xF0); // wrapped around
+  assert_eq!(ppu.oam_data[0x0F], 0xFF);
+  assert_eq!(ppu.oam_addr, 0x10);
+}
+
+#[test]
+fn test_oam_dma_stalls_513_cycles_when_started_on_even_cycle() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  let program = vec![0xA9, 0x02, 0x8D, 0x14, 0x40, 0x00]; // LDA #$02; STA $4014; BRK
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.bus.cycles(), 2 + 4 + 513);
+}
+
+#[test]
+fn test_oam_dma_stalls_514_cycles_when_started_on_odd_cycle() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  cpu.mem_write(0x10, 0x02);
+  let program = vec![0xA5, 0x10, 0x8D, 0x14, 0x40, 0x00]; // LDA $10; STA $4014; BRK
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.bus.cycles(), 3 + 4 + 514);
+}
+
+#[test]
+fn test_4014_is_plain_memory_without_ppu() {
+  // arrange
+  let mut cpu = CPU::new();
+  let program = vec![0xA9, 0x02, 0x8D, 0x14, 0x40, 0x00]; // LDA #$02; STA $4014; BRK
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x4014), 0x02);
+  assert_eq!(cpu.bus.cycles(), 2 + 4);
+}
+
+#[test]
+fn test_cycles_include_page_crossing_and_taken_branch_penalties() {
+  // arrange
+  let mut cpu = CPU::new();
+  let program = vec![
+    0xA2, 0x01, // LDX #$01
+    0xBD, 0xFF, 0x06, // LDA $06FF,X (page crossed)
+    0xF0, 0x00, // BEQ +0 (taken)
+    0x00, // BRK
+  ];
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.bus.cycles(), 2 + 5 + 3);
+}
+
+#[test]
+fn test_watched_addresses_record_their_accesses() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.watch(0x10);
+  let program = vec![0xA9, 0x05, 0x85, 0x10, 0xA5, 0x10, 0x85, 0x11, 0x00]; // LDA #$05; STA $10; LDA $10; STA $11; BRK
+
+  // act
+  cpu.load_and_run(program);
+  let accesses = cpu.bus.take_accesses();
+
+  // assert
+  let access = |kind| MemoryAccess {
+    addr: 0x10,
+    value: 0x05,
+    kind,
+  };
+  assert_eq!(
+    accesses,
+    [access(AccessKind::Write), access(AccessKind::Read)]
+  );
+  assert!(cpu.bus.take_accesses().is_empty());
+}
+
+#[test]
+fn test_unwatch_all_stops_recording() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.watch(0x10);
+  cpu.mem_write(0x10, 1);
+
+  // act
+  cpu.bus.unwatch_all();
+  cpu.mem_write(0x10, 2);
+
+  // assert
+  assert!(cpu.bus.take_accesses().is_empty());
+}
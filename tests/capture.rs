@@ -0,0 +1,89 @@
+use std::fs;
+use std::io::Cursor;
+
+use nes_emulator_rust::capture::{Recorder, WavWriter, Y4mWriter, AUDIO_SAMPLE_RATE};
+use nes_emulator_rust::region::Region;
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn test_y4m_header_and_planes() {
+  // arrange
+  let mut video = Y4mWriter::new(Vec::new(), 2, 1, (60, 1)).unwrap();
+
+  // act
+  video.write_frame(&[255, 255, 255, 0, 0, 0]).unwrap();
+  let bytes = video.finish().unwrap();
+
+  // assert
+  let header = b"YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444\n";
+  assert_eq!(bytes[..header.len()], header[..]);
+  assert_eq!(
+    bytes[header.len()..],
+    [b"FRAME\n".as_slice(), &[235, 16], &[128, 128], &[128, 128]].concat()
+  );
+}
+
+#[test]
+fn test_y4m_rejects_frames_of_the_wrong_size() {
+  // arrange
+  let mut video = Y4mWriter::new(Vec::new(), 2, 2, (60, 1)).unwrap();
+
+  // act
+  let result = video.write_frame(&[0; 6]);
+
+  // assert
+  assert_eq!(
+    result.unwrap_err().to_string(),
+    "the frame is 6 bytes, not 12"
+  );
+}
+
+#[test]
+fn test_wav_sizes_are_filled_in_by_finish() {
+  // arrange
+  let mut audio = WavWriter::new(Cursor::new(Vec::new()), 44_100).unwrap();
+
+  // act
+  audio.write_samples(&[1, -1, 256]).unwrap();
+  let bytes = audio.finish().unwrap().into_inner();
+
+  // assert
+  assert_eq!(bytes.len(), 44 + 6);
+  assert_eq!(&bytes[0..4], b"RIFF");
+  assert_eq!(u32_at(&bytes, 4), 42);
+  assert_eq!(u32_at(&bytes, 24), 44_100);
+  assert_eq!(&bytes[36..40], b"data");
+  assert_eq!(u32_at(&bytes, 40), 6);
+  assert_eq!(bytes[44..], [0x01, 0x00, 0xFF, 0xFF, 0x00, 0x01]);
+}
+
+#[test]
+fn test_recorder_keeps_the_audio_as_long_as_the_video() {
+  // arrange
+  let path = std::env::temp_dir().join(format!("capture-{}", std::process::id()));
+  let frame_rate = Region::Ntsc.frame_rate();
+  let mut recorder = Recorder::create(&path, 1, 1, frame_rate).unwrap();
+
+  // act
+  recorder.record_frame(&[0, 0, 0], &[100; 10]).unwrap();
+  for _ in 1..600 {
+    recorder.record_frame(&[0, 0, 0], &[]).unwrap();
+  }
+  let frames = recorder.frames();
+  recorder.finish().unwrap();
+  let video = fs::read(path.with_extension("y4m")).unwrap();
+  let audio = fs::read(path.with_extension("wav")).unwrap();
+  fs::remove_file(path.with_extension("y4m")).unwrap();
+  fs::remove_file(path.with_extension("wav")).unwrap();
+
+  // assert
+  let expected_samples = 600 * AUDIO_SAMPLE_RATE as u64 * frame_rate.1 / frame_rate.0;
+  assert_eq!(frames, 600);
+  assert_eq!(video.windows(6).filter(|w| w == b"FRAME\n").count(), 600);
+  assert_eq!(u32_at(&audio, 40) as u64, expected_samples * 2);
+  assert_eq!(audio[44..46], [100, 0]);
+  assert_eq!(audio[64..66], [0, 0]);
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use std::fs;
+
+use nes_emulator_rust::bus::Bus;
+use nes_emulator_rust::cartridge::Cartridge;
+use nes_emulator_rust::cpu::{StopReason, CPU};
+use nes_emulator_rust::ppu::Mirroring;
+use nes_emulator_rust::region::{Region, RegionSetting};
+
+use common::{frame_counter_image, nrom_image, temp_dir};
+
+#[test]
+fn test_from_ines_parses_the_header() {
+  // arrange
+  let image = nrom_image(0b0000_0011, &[]);
+
+  // act
+  let cartridge = Cartridge::from_ines(&image).unwrap();
+
+  // assert
+  assert_eq!(cartridge.mapper, 0);
+  assert_eq!(cartridge.mirroring, Mirroring::Vertical);
+  assert!(cartridge.has_battery);
+  assert_eq!(cartridge.prg_rom.len(), 0x4000);
+  assert_eq!(cartridge.chr.len(), 0x2000);
+}
+
+#[test]
+fn test_from_ines_rejects_unsupported_mappers() {
+  // arrange
+  let image = nrom_image(0b0001_0000, &[]); // MMC1
+
+  // act
+  let result = Cartridge::from_ines(&image);
+
+  // assert
+  assert_eq!(result.err(), Some("mapper 1 is not supported".to_string()));
+}
+
+#[test]
+fn test_manual_region_overrides_the_header() {
+  // arrange
+  let image = nrom_image(0, &[]);
+  let mut bus = Bus::new();
+
+  // act
+  let detected = Cartridge::from_ines(&image).unwrap();
+  let forced =
+    Cartridge::from_ines_with_region(&image, RegionSetting::Manual(Region::Dendy)).unwrap();
+  bus.insert_cartridge(forced);
+
+  // assert
+  assert_eq!(detected.region, Region::Ntsc);
+  assert_eq!(bus.ppu.as_ref().unwrap().region, Region::Dendy);
+}
+
+#[test]
+fn test_prg_rom_is_mirrored_and_read_only() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.insert_cartridge(Cartridge::from_ines(&nrom_image(0, &[0xEA])).unwrap());
+
+  // act
+  bus.mem_write(0x8000, 0x42);
+
+  // assert
+  assert_eq!(bus.mem_read(0x8000), 0xEA);
+  assert_eq!(bus.mem_read(0xC000), 0xEA);
+  assert_eq!(bus.mem_read(0xFFFD), 0x80);
+}
+
+#[test]
+fn test_cycle_core_runs_a_cartridge_for_several_frames() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.use_cycle_core();
+  cpu.bus.insert_cartridge(Cartridge::from_ines(&frame_counter_image()).unwrap());
+  cpu.reset();
+
+  // act
+  let reasons: Vec<_> = (0..5).map(|_| cpu.run_until_frame().unwrap()).collect();
+
+  // assert
+  assert!(reasons.iter().all(|reason| *reason == StopReason::FrameCompleted));
+  assert_eq!(cpu.bus.ppu.as_ref().unwrap().frame_count, 5);
+  // the PPU powers up on the pre-render line, so the first frame ends before any VBlank
+  assert_eq!(cpu.mem_read(0x11), 4);
+  assert_ne!(cpu.mem_read(0x10), 0);
+  assert_eq!(cpu.stack_pointer, 0xFF);
+}
+
+#[test]
+fn test_battery_ram_is_loaded_from_the_save_file() {
+  // arrange
+  let dir = temp_dir("load-save");
+  let rom_path = dir.join("game.nes");
+  fs::write(&rom_path, nrom_image(0b0000_0010, &[])).unwrap();
+  let mut save = vec![0; 0x2000];
+  save[0x10] = 0x99;
+  fs::write(dir.join("game.sav"), save).unwrap();
+
+  // act
+  let cartridge = Cartridge::load(&rom_path).unwrap();
+
+  // assert
+  assert_eq!(cartridge.save_path(), Some(dir.join("game.sav").as_path()));
+  assert_eq!(cartridge.prg_ram()[0x10], 0x99);
+  assert_eq!(cartridge.read_prg(0x6010), 0x99);
+}
+
+#[test]
+fn test_battery_ram_written_by_the_game_is_saved_on_exit() {
+  // arrange
+  let dir = temp_dir("save-on-exit");
+  let rom_path = dir.join("game.nes");
+  let program = [0xA9, 0x5A, 0x8D, 0x00, 0x60, 0x00]; // LDA #$5A; STA $6000; BRK
+  fs::write(&rom_path, nrom_image(0b0000_0010, &program)).unwrap();
+  let mut cpu = CPU::new();
+  cpu
+    .bus
+    .insert_cartridge(Cartridge::load(&rom_path).unwrap());
+
+  // act
+  cpu.reset();
+  cpu.run_with_callback(|_| {}).unwrap();
+  drop(cpu);
+
+  // assert
+  let save = fs::read(dir.join("game.sav")).unwrap();
+  assert_eq!(save.len(), 0x2000);
+  assert_eq!(save[0], 0x5A);
+}
+
+#[test]
+fn test_battery_ram_is_flushed_periodically() {
+  // arrange
+  let dir = temp_dir("periodic-flush");
+  let rom_path = dir.join("game.nes");
+  fs::write(&rom_path, nrom_image(0b0000_0010, &[])).unwrap();
+  let mut bus = Bus::new();
+  bus.insert_cartridge(Cartridge::load(&rom_path).unwrap());
+  bus.mem_write(0x7FFF, 0x01);
+
+  // act
+  while bus.ppu.as_ref().unwrap().frame_count < 60 {
+    bus.tick(10_000);
+  }
+
+  // assert
+  let save = fs::read(dir.join("game.sav")).unwrap();
+  assert_eq!(save[0x1FFF], 0x01);
+  assert!(!bus.cartridge.as_mut().unwrap().flush_save().unwrap()); // nothing left to write
+}
+
+#[test]
+fn test_save_editing_api_marks_the_ram_for_flushing() {
+  // arrange
+  let dir = temp_dir("edit-save");
+  let mut cartridge = Cartridge::from_ines(&nrom_image(0b0000_0010, &[])).unwrap();
+  cartridge.set_save_path(Some(dir.join("edited.sav")));
+
+  // act
+  cartridge.prg_ram_mut()[0x100] = 0x63;
+  let written = cartridge.flush_save().unwrap();
+
+  // assert
+  assert!(written);
+  assert_eq!(fs::read(dir.join("edited.sav")).unwrap()[0x100], 0x63);
+}
+
+#[test]
+fn test_mark_save_dirty_flushes_unchanged_ram() {
+  // arrange
+  let dir = temp_dir("mark-dirty");
+  let mut cartridge = Cartridge::from_ines(&nrom_image(0b0000_0010, &[])).unwrap();
+  cartridge.set_save_path(Some(dir.join("marked.sav")));
+
+  // act
+  let before = cartridge.flush_save().unwrap();
+  cartridge.mark_save_dirty();
+  let after = cartridge.flush_save().unwrap();
+
+  // assert
+  assert!(!before);
+  assert!(after);
+  assert!(dir.join("marked.sav").exists());
+}
+
+#[test]
+fn test_cartridges_without_battery_are_never_saved() {
+  // arrange
+  let dir = temp_dir("no-battery");
+  let rom_path = dir.join("game.nes");
+  fs::write(&rom_path, nrom_image(0, &[])).unwrap();
+  let mut cartridge = Cartridge::load(&rom_path).unwrap();
+
+  // act
+  cartridge.write_prg(0x6000, 0x01);
+  drop(cartridge);
+
+  // assert
+  assert!(!dir.join("game.sav").exists());
+}
@@ -0,0 +1,114 @@
+use nes_emulator_rust::bus::Bus;
+use nes_emulator_rust::cheats::{decode_game_genie, Cheat, CheatKind, Cheats};
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::ppu::PPU;
+
+#[test]
+fn test_decode_six_letter_game_genie_code() {
+  // arrange
+  let code = "SXIOPO"; // Super Mario Bros.: infinite lives
+
+  // act
+  let decoded = decode_game_genie(code);
+
+  // assert
+  assert_eq!(decoded, Ok((0x91D9, 0xAD, None)));
+}
+
+#[test]
+fn test_decode_eight_letter_game_genie_code() {
+  // arrange
+  let code = "zexpygla"; // case doesn't matter
+
+  // act
+  let decoded = decode_game_genie(code);
+
+  // assert
+  assert_eq!(decoded, Ok((0x94A7, 0x02, Some(0x03))));
+}
+
+#[test]
+fn test_decode_rejects_invalid_game_genie_codes() {
+  assert!(decode_game_genie("SXIOP").is_err()); // 5 letters
+  assert!(decode_game_genie("SXIOPB").is_err()); // B is not in the alphabet
+}
+
+#[test]
+fn test_parse_raw_codes() {
+  // arrange
+  let freeze = Cheat::parse("0075:09").unwrap();
+  let rom_patch = Cheat::parse("c123:ea").unwrap();
+  let compare_patch = Cheat::parse("8000?A9:EA").unwrap();
+
+  // assert
+  assert_eq!(
+    (freeze.kind, freeze.address, freeze.value),
+    (CheatKind::Freeze, 0x0075, 0x09)
+  );
+  assert_eq!(rom_patch.kind, CheatKind::ReadPatch);
+  assert_eq!(rom_patch.code, "C123:EA");
+  assert_eq!(compare_patch.kind, CheatKind::ReadPatch);
+  assert_eq!(compare_patch.compare, Some(0xA9));
+  assert!(Cheat::parse("12345:00").is_err());
+  assert!(Cheat::parse("0075:").is_err());
+}
+
+#[test]
+fn test_read_patch_applies_only_when_compare_matches() {
+  // arrange
+  let mut cheats = Cheats::new();
+  cheats.add("0300?10:20").unwrap();
+
+  // act / assert
+  assert_eq!(cheats.patch_read(0x0300, 0x10), 0x20);
+  assert_eq!(cheats.patch_read(0x0300, 0x11), 0x11);
+  assert_eq!(cheats.patch_read(0x0301, 0x10), 0x10);
+}
+
+#[test]
+fn test_cpu_reads_go_through_the_cheats() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.mem_write(0x10, 0x05);
+  cpu.bus.cheats.add("0010?05:42").unwrap();
+  let program = vec![0xA5, 0x10, 0x00]; // LDA $10; BRK
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.accumulator, 0x42);
+}
+
+#[test]
+fn test_disabled_cheats_are_ignored() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.mem_write(0x10, 0x05);
+  let index = bus.cheats.add("0010?05:42").unwrap();
+
+  // act
+  let enabled = bus.cheats.toggle(index);
+
+  // assert
+  assert_eq!(enabled, Some(false));
+  assert_eq!(bus.mem_read(0x10), 0x05);
+  assert_eq!(bus.cheats.toggle(1), None);
+}
+
+#[test]
+fn test_freeze_cheats_rewrite_ram_every_frame() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.ppu = Some(PPU::new());
+  bus.cheats.cheats.push(Cheat::freeze(0x0075, 0x09));
+  bus.mem_write(0x0075, 0x01);
+
+  // act
+  while bus.ppu.as_ref().unwrap().frame_count < 1 {
+    bus.tick(1000);
+  }
+
+  // assert
+  assert_eq!(bus.mem_read(0x0075), 0x09);
+}
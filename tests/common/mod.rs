@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests (each test crate uses some of them).
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A fresh directory for the files of one test.
+pub fn temp_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("nes-emulator-rust-{}-{}", name, std::process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+/// An NROM-128 image (one PRG and one CHR bank) running `program` from $8000: the NMI, reset and
+/// IRQ vectors all point there.
+pub fn nrom_image(flags_6: u8, program: &[u8]) -> Vec<u8> {
+  let mut image = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, flags_6, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+  let mut prg_rom = vec![0; 0x4000];
+  prg_rom[..program.len()].copy_from_slice(program);
+  prg_rom[0x3FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+  image.extend(prg_rom);
+  image.extend(vec![0; 0x2000]);
+  image
+}
+
+/// A game counting the frames at $11 in its NMI handler, while its main loop increments $10.
+/// It needs opcodes the easy6502 table lacks: SEI, CLD, TXS, INC, JMP, BIT and RTI.
+pub fn frame_counter_image() -> Vec<u8> {
+  let program = [
+    0x78, // $8000: SEI
+    0xD8, // CLD
+    0xA2, 0xFF, // LDX #$FF
+    0x9A, // TXS
+    0xA9, 0x80, // LDA #$80
+    0x8D, 0x00, 0x20, // STA $2000 (NMI on)
+    0xE6, 0x10, // $800A: INC $10
+    0x4C, 0x0A, 0x80, // JMP $800A
+    0xE6, 0x11, // $800F: INC $11
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x40, // RTI
+  ];
+  let mut image = nrom_image(0, &program);
+  image[16 + 0x3FFA] = 0x0F; // NMI vector: $800F
+  image
+}
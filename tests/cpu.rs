@@ -0,0 +1,252 @@
+use nes_emulator_rust::cpu::{CpuError, JamPolicy, StepOutcome, StopReason, CPU};
+use nes_emulator_rust::ppu::PPU;
+
+fn cpu_with_program(program: &[u8]) -> CPU {
+  let mut cpu = CPU::new();
+  cpu.load(program.to_vec()).unwrap();
+  cpu.reset();
+  cpu
+}
+
+#[test]
+fn test_step_runs_one_instruction() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0x00]); // INX; INX; BRK
+
+  // act
+  let outcomes = [cpu.step(), cpu.step(), cpu.step()];
+
+  // assert
+  assert_eq!(
+    outcomes,
+    [
+      Ok(StepOutcome::Executed),
+      Ok(StepOutcome::Executed),
+      Ok(StepOutcome::Break)
+    ]
+  );
+  assert_eq!(cpu.register_x, 2);
+}
+
+#[test]
+fn test_unknown_opcode_is_an_error_and_leaves_the_pc_on_it() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xFF]); // INX; (unknown)
+
+  // act
+  let result = cpu.run_with_callback(|_| {});
+
+  // assert
+  assert_eq!(
+    result,
+    Err(CpuError::UnknownOpcode {
+      opcode: 0xFF,
+      pc: 0x0601
+    })
+  );
+  assert_eq!(cpu.program_counter, 0x0601);
+  assert_eq!(
+    result.unwrap_err().to_string(),
+    "OP code ff not found at $0601"
+  );
+}
+
+#[test]
+fn test_load_rejects_programs_that_overlap_the_vectors() {
+  // arrange
+  let mut cpu = CPU::new();
+  let program = vec![0xEA; 0xFA00];
+
+  // act
+  let result = cpu.load(program);
+
+  // assert
+  assert_eq!(
+    result,
+    Err(CpuError::ProgramTooLarge {
+      size: 0xFA00,
+      max: 0xF9FC
+    })
+  );
+}
+
+#[test]
+fn test_jam_opcode_is_an_error_by_default() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0x02]);
+
+  // act
+  let result = cpu.step();
+
+  // assert
+  assert_eq!(
+    result,
+    Err(CpuError::Jammed {
+      opcode: 0x02,
+      pc: 0x0600
+    })
+  );
+}
+
+#[test]
+fn test_jam_opcode_halts_the_cpu_with_halt_policy() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0x12]);
+  cpu.jam_policy = JamPolicy::Halt;
+
+  // act
+  let outcomes = [cpu.step(), cpu.step()];
+
+  // assert
+  assert_eq!(outcomes, [Ok(StepOutcome::Jammed), Ok(StepOutcome::Jammed)]);
+  assert_eq!(cpu.program_counter, 0x0600);
+  assert_eq!(cpu.run_with_callback(|_| {}), Ok(()));
+}
+
+#[test]
+fn test_jam_opcode_is_skipped_with_ignore_policy() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xF2, 0xE8, 0x00]); // (jam); INX; BRK
+  cpu.jam_policy = JamPolicy::Ignore;
+
+  // act
+  let result = cpu.run_with_callback(|_| {});
+
+  // assert
+  assert_eq!(result, Ok(()));
+  assert_eq!(cpu.register_x, 1);
+}
+
+#[test]
+fn test_run_for_cycles_stops_after_the_cycles_elapsed() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0xE8, 0x00]); // INX x4; BRK
+
+  // act
+  let reason = cpu.run_for_cycles(5);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::CyclesElapsed));
+  assert_eq!(cpu.cycles(), 6); // INX takes 2 cycles, so it ran 3 of them
+  assert_eq!(cpu.register_x, 3);
+}
+
+#[test]
+fn test_run_for_cycles_without_limit_runs_until_break() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0x00]); // INX; INX; BRK
+  cpu.step().unwrap();
+
+  // act
+  let reason = cpu.run_for_cycles(u64::MAX);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::Break));
+  assert_eq!(cpu.register_x, 2);
+}
+
+#[test]
+fn test_run_until_pc_stops_before_the_instruction() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+
+  // act
+  let reason = cpu.run_until_pc(0x0602);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::PcReached));
+  assert_eq!(cpu.program_counter, 0x0602);
+  assert_eq!(cpu.register_x, 2);
+}
+
+#[test]
+fn test_run_until_pc_runs_nothing_when_already_there() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0x00]); // INX; INX; BRK
+
+  // act
+  let reason = cpu.run_until_pc(0x0600);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::PcReached));
+  assert_eq!(cpu.program_counter, 0x0600);
+  assert_eq!(cpu.register_x, 0);
+}
+
+#[test]
+fn test_run_until_condition() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+
+  // act
+  let reason = cpu.run_until(|cpu| cpu.register_x == 1);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::ConditionMet));
+  assert_eq!(cpu.program_counter, 0x0601);
+}
+
+#[test]
+fn test_run_until_stops_at_break() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0x00]); // INX; BRK
+
+  // act
+  let reason = cpu.run_until_pc(0x1234);
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::Break));
+}
+
+#[test]
+fn test_run_until_frame_stops_when_the_ppu_finishes_a_frame() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xF0, 0xFE]); // BEQ to itself
+  cpu.bus.ppu = Some(PPU::new());
+  cpu.status = 0b0000_0010; // zero flag
+
+  // act
+  let reason = cpu.run_until_frame();
+
+  // assert
+  assert_eq!(reason, Ok(StopReason::FrameCompleted));
+  assert_eq!(cpu.bus.ppu.as_ref().unwrap().frame_count, 1);
+}
+
+#[test]
+fn test_u16_accesses_at_ffff_wrap_to_zero() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.mem_write(0xFFFF, 0x34);
+  cpu.mem_write(0x0000, 0x12);
+
+  // act
+  let read = cpu.mem_read_u16(0xFFFF);
+  cpu.mem_write_u16(0xFFFF, 0xABCD);
+
+  // assert
+  assert_eq!(read, 0x1234);
+  assert_eq!(cpu.mem_read(0xFFFF), 0xCD);
+  assert_eq!(cpu.mem_read(0x0000), 0xAB);
+}
+
+#[test]
+fn test_branch_offsets_at_the_limits_of_the_signed_range() {
+  // arrange
+  let branch_to = |offset: u8| {
+    let mut cpu = cpu_with_program(&[0xA2, 0x01, 0xD0, offset]); // LDX #$01; BNE offset
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    (cpu.program_counter, cpu.cycles())
+  };
+
+  // act
+  let forward = branch_to(0x7F);
+  let backward = branch_to(0x80);
+  let minus_one = branch_to(0xFF);
+
+  // assert
+  assert_eq!(forward, (0x0683, 5)); // LDX 2 + BNE 3 (same page)
+  assert_eq!(backward, (0x0584, 6)); // LDX 2 + BNE 4 (another page)
+  assert_eq!(minus_one, (0x0603, 5)); // into its own operand
+}
@@ -0,0 +1,314 @@
+use nes_emulator_rust::bus::Bus;
+use nes_emulator_rust::cycle_cpu::{BusCycle, CycleCpu, RamBus};
+
+/// A CPU about to run `program` at $0200 (the bus log is cleared after the reset).
+fn cpu_with_program(program: &[u8]) -> (CycleCpu, RamBus) {
+  let mut bus = RamBus::new();
+  bus.load(0x0200, program);
+  bus.load(0xFFFC, &[0x00, 0x02]);
+  let mut cpu = CycleCpu::new();
+  cpu.reset(&mut bus);
+  bus.cycles.clear();
+  (cpu, bus)
+}
+
+fn r(addr: u16, data: u8) -> BusCycle {
+  BusCycle::read(addr, data)
+}
+
+fn w(addr: u16, data: u8) -> BusCycle {
+  BusCycle::write(addr, data)
+}
+
+#[test]
+fn test_reset_reads_the_vector() {
+  // arrange
+  let mut bus = RamBus::new();
+  bus.load(0xFFFC, &[0x34, 0x12]);
+  let mut cpu = CycleCpu::new();
+
+  // act
+  cpu.reset(&mut bus);
+
+  // assert
+  assert_eq!(cpu.program_counter, 0x1234);
+  assert_eq!(cpu.stack_pointer, 0xFD);
+  assert_eq!(bus.cycles.len(), 7);
+  assert_eq!(cpu.cycles(), 7);
+}
+
+#[test]
+fn test_indexed_read_across_a_page_reads_the_unfixed_address_first() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0xBD, 0xF0, 0x12]); // LDA $12F0,X
+  cpu.register_x = 0x20;
+  bus.memory[0x1310] = 0x42;
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 5);
+  assert_eq!(
+    bus.cycles,
+    vec![
+      r(0x0200, 0xBD),
+      r(0x0201, 0xF0),
+      r(0x0202, 0x12),
+      r(0x1210, 0x00),
+      r(0x1310, 0x42)
+    ]
+  );
+  assert_eq!(cpu.accumulator, 0x42);
+}
+
+#[test]
+fn test_indexed_read_in_the_page_takes_no_extra_cycle() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0xB1, 0x10]); // LDA ($10),Y
+  cpu.register_y = 0x01;
+  bus.load(0x0010, &[0x00, 0x30]);
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 5);
+  assert_eq!(bus.cycles[4], r(0x3001, 0x00));
+}
+
+#[test]
+fn test_indexed_write_always_does_the_dummy_read() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0x9D, 0x00, 0x12]); // STA $1200,X
+  cpu.register_x = 0x01;
+  cpu.accumulator = 0x99;
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 5);
+  assert_eq!(bus.cycles[3..], [r(0x1201, 0x00), w(0x1201, 0x99)]);
+}
+
+#[test]
+fn test_read_modify_write_writes_twice() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0xFE, 0x00, 0x12]); // INC $1200,X
+  cpu.register_x = 0x05;
+  bus.memory[0x1205] = 0x7F;
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 7);
+  assert_eq!(
+    bus.cycles[3..],
+    [
+      r(0x1205, 0x7F),
+      r(0x1205, 0x7F),
+      w(0x1205, 0x7F),
+      w(0x1205, 0x80)
+    ]
+  );
+  assert_eq!(cpu.status & 0x80, 0x80);
+}
+
+#[test]
+fn test_jsr_and_rts_bus_activity() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[
+    0x20, 0x05, 0x02, // JSR $0205
+    0xEA, 0xEA, // NOP; NOP
+    0x60, // $0205: RTS
+  ]);
+
+  // act
+  let jsr = cpu.step(&mut bus).unwrap();
+  let jsr_cycles = bus.cycles.split_off(0);
+  let rts = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!((jsr, rts), (6, 6));
+  assert_eq!(
+    jsr_cycles,
+    vec![
+      r(0x0200, 0x20),
+      r(0x0201, 0x05),
+      r(0x01FD, 0x00),
+      w(0x01FD, 0x02),
+      w(0x01FC, 0x02),
+      r(0x0202, 0x02)
+    ]
+  );
+  assert_eq!(
+    bus.cycles,
+    vec![
+      r(0x0205, 0x60),
+      r(0x0206, 0x00),
+      r(0x01FB, 0x00),
+      r(0x01FC, 0x02),
+      r(0x01FD, 0x02),
+      r(0x0202, 0x02)
+    ]
+  );
+  assert_eq!(cpu.program_counter, 0x0203);
+}
+
+#[test]
+fn test_taken_branch_across_a_page() {
+  // arrange
+  let mut program = vec![0xEA; 0xFE];
+  program.extend([0xD0, 0xE0]); // $02FE: BNE $02E0
+  let (mut cpu, mut bus) = cpu_with_program(&program);
+  cpu.program_counter = 0x02FE;
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 4);
+  assert_eq!(bus.cycles[2..], [r(0x0300, 0x00), r(0x03E0, 0x00)]);
+  assert_eq!(cpu.program_counter, 0x02E0);
+}
+
+#[test]
+fn test_brk_pushes_the_break_flag_and_rti_returns_after_the_padding_byte() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0x00, 0xFF, 0xE8]); // BRK; (padding); INX
+  bus.load(0xFFFE, &[0x00, 0x30]);
+  bus.memory[0x3000] = 0x40; // RTI
+
+  // act
+  let brk = cpu.step(&mut bus).unwrap();
+  let pushed_status = bus.memory[0x01FB];
+  let rti = cpu.step(&mut bus).unwrap();
+  cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!((brk, rti), (7, 6));
+  assert_eq!(pushed_status & 0x30, 0x30);
+  assert_eq!(cpu.register_x, 1);
+}
+
+#[test]
+fn test_nmi_is_taken_after_the_current_instruction() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0xE8, 0xE8]); // INX; INX
+  bus.load(0xFFFA, &[0x00, 0x40]);
+  bus.nmi = true;
+
+  // act
+  cpu.step(&mut bus).unwrap();
+  bus.cycles.clear();
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 7);
+  assert_eq!(cpu.program_counter, 0x4000);
+  assert_eq!(cpu.register_x, 1);
+  assert_eq!(bus.cycles[0], r(0x0201, 0xE8)); // fetched, then dropped
+  assert_eq!(bus.memory[0x01FB] & 0x10, 0); // no B flag
+  assert_eq!(bus.memory[0x01FC], 0x01); // returns to the second INX
+}
+
+#[test]
+fn test_cli_lets_an_irq_in_only_after_the_next_instruction() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0x58, 0xE8, 0xE8]); // CLI; INX; INX
+  bus.load(0xFFFE, &[0x00, 0x40]);
+  bus.irq = true;
+
+  // act
+  cpu.step(&mut bus).unwrap(); // CLI
+  cpu.step(&mut bus).unwrap(); // INX
+  cpu.step(&mut bus).unwrap(); // IRQ
+
+  // assert
+  assert_eq!(cpu.register_x, 1);
+  assert_eq!(cpu.program_counter, 0x4000);
+}
+
+#[test]
+fn test_jmp_indirect_does_not_carry_into_the_page() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0x6C, 0xFF, 0x10]); // JMP ($10FF)
+  bus.memory[0x10FF] = 0x34;
+  bus.memory[0x1000] = 0x12;
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 5);
+  assert_eq!(cpu.program_counter, 0x1234);
+}
+
+#[test]
+fn test_decimal_mode_adc_and_sbc() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[
+    0xF8, // SED
+    0x18, // CLC
+    0xA9, 0x58, // LDA #$58
+    0x69, 0x46, // ADC #$46
+    0x85, 0x10, // STA $10
+    0x38, // SEC
+    0xE9, 0x05, // SBC #$05
+  ]);
+
+  // act
+  for _ in 0..5 {
+    cpu.step(&mut bus).unwrap();
+  }
+  let sum = (bus.memory[0x10], cpu.status & 0x01);
+  cpu.step(&mut bus).unwrap();
+  cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(sum, (0x04, 0x01)); // 58 + 46 = 104
+  assert_eq!(cpu.accumulator, 0x99); // 04 - 05 = 99, with a borrow
+  assert_eq!(cpu.status & 0x01, 0x00);
+}
+
+#[test]
+fn test_unknown_and_jam_opcodes_are_errors() {
+  // arrange
+  let (mut cpu, mut bus) = cpu_with_program(&[0x02]);
+  let (mut other, mut other_bus) = cpu_with_program(&[0xFF]);
+
+  // act
+  let jam = cpu.step(&mut bus);
+  let unknown = other.step(&mut other_bus);
+
+  // assert
+  assert_eq!(
+    jam.unwrap_err().to_string(),
+    "the CPU jammed on opcode 02 at $0200"
+  );
+  assert_eq!(
+    unknown.unwrap_err().to_string(),
+    "OP code ff not found at $0200"
+  );
+}
+
+#[test]
+fn test_console_bus_advances_one_cycle_per_access() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.load(0x0600, &[0xFE, 0x00, 0x02]); // INC $0200,X
+  bus.load(0xFFFC, &[0x00, 0x06]);
+  let mut cpu = CycleCpu::new();
+  cpu.reset(&mut bus);
+  let start = bus.cycles();
+
+  // act
+  let cycles = cpu.step(&mut bus).unwrap();
+
+  // assert
+  assert_eq!(cycles, 7);
+  assert_eq!(bus.cycles() - start, 7);
+  assert_eq!(bus.peek(0x0200), 1);
+}
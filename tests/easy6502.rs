@@ -0,0 +1,119 @@
+use nes_emulator_rust::cpu::StepOutcome;
+use nes_emulator_rust::easy6502::{color, Easy6502Machine, PALETTE};
+
+fn machine_with_program(program: &[u8]) -> Easy6502Machine {
+  let mut machine = Easy6502Machine::with_seed(6502);
+  machine.load(program).unwrap();
+  machine
+}
+
+#[test]
+fn test_palette_uses_the_low_nibble() {
+  // act / assert
+  assert_eq!(color(0x00), (0x00, 0x00, 0x00));
+  assert_eq!(color(0x01), (0xFF, 0xFF, 0xFF));
+  assert_eq!(color(0x1E), (0x00, 0x88, 0xFF));
+  assert_eq!(color(0xF2), PALETTE[2]);
+}
+
+#[test]
+fn test_frame_shows_the_screen_memory() {
+  // arrange
+  let mut machine = machine_with_program(&[
+    0xA9, 0x01, // LDA #$01
+    0x8D, 0x00, 0x02, // STA $0200
+    0xA9, 0x0E, // LDA #$0E
+    0x8D, 0xFF, 0x05, // STA $05FF
+    0x00, // BRK
+  ]);
+
+  // act
+  let outcome = machine.run_frame().unwrap();
+
+  // assert
+  assert_eq!(outcome, StepOutcome::Break);
+  assert_eq!(machine.frame()[0..3], [0xFF, 0xFF, 0xFF]);
+  assert_eq!(machine.frame()[3..6], [0x00, 0x00, 0x00]);
+  assert_eq!(machine.frame()[32 * 32 * 3 - 3..], [0x00, 0x88, 0xFF]);
+  assert_eq!(machine.pixel(31, 31), 0x0E);
+  assert!(!machine.update_frame());
+}
+
+#[test]
+fn test_pressed_key_is_at_ff() {
+  // arrange
+  let mut machine = machine_with_program(&[
+    0xA5, 0xFF, // LDA $FF
+    0x8D, 0x21, 0x02, // STA $0221
+    0x00, // BRK
+  ]);
+
+  // act
+  machine.press_key(b'w');
+  machine.run_frame().unwrap();
+
+  // assert
+  assert_eq!(machine.cpu.mem_read(0x0221), 0x77);
+  assert_eq!(machine.pixel(1, 1), 0x07);
+}
+
+#[test]
+fn test_random_byte_changes_every_instruction_and_follows_the_seed() {
+  // arrange
+  let program = [
+    0xA5, 0xFE, // LDA $FE
+    0x85, 0x10, // STA $10
+    0xA5, 0xFE, // LDA $FE
+    0x85, 0x11, // STA $11
+    0xA5, 0xFE, // LDA $FE
+    0x85, 0x12, // STA $12
+    0x00, // BRK
+  ];
+  let mut machine = machine_with_program(&program);
+  let mut same_seed = machine_with_program(&program);
+
+  // act
+  machine.run_frame().unwrap();
+  same_seed.run_frame().unwrap();
+
+  // assert
+  let bytes = [0x10, 0x11, 0x12].map(|addr| machine.cpu.mem_read(addr));
+  assert_eq!(
+    bytes,
+    [0x10, 0x11, 0x12].map(|addr| same_seed.cpu.mem_read(addr))
+  );
+  assert!(bytes[0] != bytes[1] || bytes[1] != bytes[2]);
+}
+
+#[test]
+fn test_run_frame_runs_a_fixed_number_of_instructions() {
+  // arrange
+  let mut machine = machine_with_program(&[0xE8, 0xD0, 0xFD]); // loop: INX; BNE loop
+  machine.instructions_per_frame = 10;
+
+  // act
+  let outcome = machine.run_frame().unwrap();
+
+  // assert
+  assert_eq!(outcome, StepOutcome::Executed);
+  assert_eq!(machine.cpu.register_x, 5);
+}
+
+#[test]
+fn test_run_frame_with_callback_sees_every_instruction() {
+  // arrange
+  let mut machine = machine_with_program(&[
+    0xA9, 0x01, // LDA #$01
+    0xAA, // TAX
+    0x00, // BRK
+  ]);
+  let mut program_counters = Vec::new();
+
+  // act
+  machine
+    .run_frame_with_callback(|cpu| program_counters.push(cpu.program_counter))
+    .unwrap();
+
+  // assert
+  assert_eq!(program_counters, [0x0600, 0x0602, 0x0603]);
+}
@@ -0,0 +1,243 @@
+//! Runs Tom Harte's SingleStepTests (ProcessorTests) for the NMOS 6502 against the cycle-stepped
+//! core. Each `XX.json` file holds the cases for opcode `XX`: the registers and RAM before and
+//! after the instruction, and every bus access in between.
+//!
+//! `tests/fixtures/single_step` is meant to hold a sample of the upstream suite (MIT licensed):
+//! the first `SAMPLE_CASES` cases of every opcode the core implements, written by the ignored
+//! `test_vendor_upstream_sample` from a checkout of https://github.com/SingleStepTests/65x02.
+//! The sample isn't in the tree yet, so the suite test is ignored until it is. Set
+//! `HARTE_TESTS_DIR` to the `6502/v1` directory of a checkout to run the whole suite instead.
+//! The hand-written cases of `tests/fixtures/single_step_handwritten` (same format, but not
+//! upstream conformance data) always run.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nes_emulator_rust::cycle_cpu::{self, BusCycle, CycleCpu, RamBus};
+use serde::Deserialize;
+
+const SAMPLE: &str = "tests/fixtures/single_step";
+const HANDWRITTEN: &str = "tests/fixtures/single_step_handwritten";
+/// Cases per opcode kept in the vendored sample.
+const SAMPLE_CASES: usize = 50;
+
+#[derive(Deserialize)]
+struct TestCase {
+  name: String,
+  initial: State,
+  #[serde(rename = "final")]
+  expected: State,
+  cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Deserialize)]
+struct State {
+  pc: u16,
+  s: u8,
+  a: u8,
+  x: u8,
+  y: u8,
+  p: u8,
+  ram: Vec<(u16, u8)>,
+}
+
+#[derive(Default)]
+struct Tally {
+  passed: usize,
+  failed: usize,
+  skipped: usize,
+}
+
+/// The `6502/v1` directory of a checkout of the upstream suite, if `HARTE_TESTS_DIR` is set.
+fn upstream_dir() -> Option<PathBuf> {
+  std::env::var_os("HARTE_TESTS_DIR").map(PathBuf::from)
+}
+
+/// The fixture files of `dir`, sorted by opcode.
+fn fixture_files(dir: &Path) -> Vec<(u8, PathBuf)> {
+  let mut files: Vec<(u8, PathBuf)> = fs::read_dir(dir)
+    .unwrap_or_else(|err| panic!("can't read {}: {}", dir.display(), err))
+    .filter_map(|entry| {
+      let path = entry.ok()?.path();
+      Some((opcode_of(&path)?, path))
+    })
+    .collect();
+  files.sort();
+  assert!(!files.is_empty(), "no fixtures in {}", dir.display());
+  files
+}
+
+/// The opcode a fixture file covers, from its name (`a9.json`).
+fn opcode_of(path: &Path) -> Option<u8> {
+  if path.extension()? != "json" {
+    return None;
+  }
+  u8::from_str_radix(path.file_stem()?.to_str()?, 16).ok()
+}
+
+/// Runs one case, describing the first difference from the expected result.
+fn run_case(case: &TestCase) -> Result<(), String> {
+  let mut bus = RamBus::new();
+  for &(addr, value) in &case.initial.ram {
+    bus.memory[addr as usize] = value;
+  }
+  let mut cpu = CycleCpu::new();
+  cpu.program_counter = case.initial.pc;
+  cpu.stack_pointer = case.initial.s;
+  cpu.accumulator = case.initial.a;
+  cpu.register_x = case.initial.x;
+  cpu.register_y = case.initial.y;
+  cpu.status = case.initial.p;
+
+  cpu.step(&mut bus).map_err(|err| err.to_string())?;
+
+  let expected = &case.expected;
+  let registers = [
+    ("pc", cpu.program_counter, expected.pc),
+    ("s", cpu.stack_pointer as u16, expected.s as u16),
+    ("a", cpu.accumulator as u16, expected.a as u16),
+    ("x", cpu.register_x as u16, expected.x as u16),
+    ("y", cpu.register_y as u16, expected.y as u16),
+    ("p", cpu.status as u16, expected.p as u16),
+  ];
+  for (name, actual, expected) in registers {
+    if actual != expected {
+      return Err(format!(
+        "{} is {:02X}, expected {:02X}",
+        name, actual, expected
+      ));
+    }
+  }
+  for &(addr, value) in &expected.ram {
+    let actual = bus.memory[addr as usize];
+    if actual != value {
+      return Err(format!(
+        "${:04X} is {:02X}, expected {:02X}",
+        addr, actual, value
+      ));
+    }
+  }
+  let cycles = case
+    .cycles
+    .iter()
+    .map(|(addr, data, access)| match access.as_str() {
+      "write" => BusCycle::write(*addr, *data),
+      _ => BusCycle::read(*addr, *data),
+    })
+    .collect::<Vec<_>>();
+  if bus.cycles != cycles {
+    return Err(format!(
+      "bus activity was {:?}, expected {:?}",
+      bus.cycles, cycles
+    ));
+  }
+  Ok(())
+}
+
+/// Runs every case of the fixture files of `dir`, printing a tally per opcode, and fails if any
+/// case of an implemented opcode fails.
+fn run_suite(dir: &Path) {
+  let files = fixture_files(dir);
+
+  let mut tallies: BTreeMap<u8, Tally> = BTreeMap::new();
+  let mut failures = Vec::new();
+  for (opcode, path) in &files {
+    let text = fs::read_to_string(path).unwrap();
+    let cases: Vec<TestCase> =
+      serde_json::from_str(&text).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    let tally = tallies.entry(*opcode).or_default();
+    if !cycle_cpu::is_implemented(*opcode) {
+      tally.skipped += cases.len();
+      continue;
+    }
+    for case in &cases {
+      match run_case(case) {
+        Ok(()) => tally.passed += 1,
+        Err(reason) => {
+          tally.failed += 1;
+          failures.push(format!("{:02x} \"{}\": {}", opcode, case.name, reason));
+        }
+      }
+    }
+  }
+
+  println!("opcode  passed  failed  skipped");
+  for (opcode, tally) in &tallies {
+    println!(
+      "    {:02x}  {:>6}  {:>6}  {:>7}",
+      opcode, tally.passed, tally.failed, tally.skipped
+    );
+  }
+  for failure in failures.iter().take(20) {
+    println!("{}", failure);
+  }
+  assert!(failures.is_empty(), "{} cases failed", failures.len());
+}
+
+#[test]
+#[ignore = "the upstream sample isn't vendored yet: see test_vendor_upstream_sample"]
+fn test_harte_single_step_suite() {
+  run_suite(&upstream_dir().unwrap_or_else(|| PathBuf::from(SAMPLE)));
+}
+
+#[test]
+fn test_handwritten_cases() {
+  run_suite(Path::new(HANDWRITTEN));
+}
+
+/// Writes the sample to `tests/fixtures/single_step`, from the checkout `HARTE_TESTS_DIR` points
+/// at, along with the license of the suite:
+/// `HARTE_TESTS_DIR=../65x02/6502/v1 cargo test --test harte -- --ignored test_vendor`.
+#[test]
+#[ignore = "writes the vendored sample from a checkout of the upstream suite"]
+fn test_vendor_upstream_sample() {
+  // arrange
+  let upstream = upstream_dir().expect("HARTE_TESTS_DIR must point at 6502/v1 of a checkout");
+  let sample = Path::new(SAMPLE);
+  fs::create_dir_all(sample).unwrap();
+
+  // act
+  let mut vendored = 0;
+  for (opcode, path) in fixture_files(&upstream) {
+    if !cycle_cpu::is_implemented(opcode) {
+      continue;
+    }
+    let text = fs::read_to_string(&path).unwrap();
+    let mut cases: Vec<serde_json::Value> =
+      serde_json::from_str(&text).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    cases.truncate(SAMPLE_CASES);
+    let name = path.file_name().unwrap();
+    fs::write(sample.join(name), serde_json::to_string(&cases).unwrap()).unwrap();
+    vendored += 1;
+  }
+  let license = upstream.join("../../LICENSE");
+  fs::copy(&license, sample.join("LICENSE"))
+    .unwrap_or_else(|err| panic!("{}: {}", license.display(), err));
+
+  // assert
+  let implemented = (0..=255).filter(|&opcode| cycle_cpu::is_implemented(opcode)).count();
+  assert_eq!(vendored, implemented, "the checkout lacks some implemented opcodes");
+}
+
+#[test]
+fn test_handwritten_case_names_are_unique() {
+  // arrange
+  let mut names = HashSet::new();
+  let mut duplicates = Vec::new();
+
+  // act
+  for entry in fs::read_dir(HANDWRITTEN).unwrap() {
+    let path = entry.unwrap().path();
+    let cases: Vec<TestCase> = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+      .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    for case in cases {
+      if !names.insert(case.name.clone()) {
+        duplicates.push(case.name);
+      }
+    }
+  }
+
+  // assert
+  assert!(duplicates.is_empty(), "duplicate case names: {:?}", duplicates);
+}
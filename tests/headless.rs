@@ -0,0 +1,88 @@
+//! Runs the `headless` binary on a tiny cartridge.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use nes_emulator_rust::capture::AUDIO_SAMPLE_RATE;
+use nes_emulator_rust::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use nes_emulator_rust::region::Region;
+
+use common::{frame_counter_image, temp_dir};
+
+/// Runs the frame counter game for `frames` frames, with the extra `args`, in the directory of
+/// `rom_path` (where the screenshots go).
+fn run_headless(rom_path: &Path, frames: u64, args: &[&str]) -> Output {
+  fs::write(rom_path, frame_counter_image()).unwrap();
+  Command::new(env!("CARGO_BIN_EXE_headless"))
+    .current_dir(rom_path.parent().unwrap())
+    .arg(rom_path)
+    .args(["--frames", &frames.to_string()])
+    .args(args)
+    .output()
+    .unwrap()
+}
+
+#[test]
+fn test_headless_runs_a_cartridge_for_several_frames() {
+  // arrange
+  let dir = temp_dir("headless-frames");
+
+  // act
+  let output = run_headless(&dir.join("game.nes"), 10, &[]);
+
+  // assert
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  assert!(stdout.starts_with("ran 10 frames"), "{}", stdout);
+}
+
+#[test]
+fn test_headless_saves_a_screenshot_of_the_requested_frame() {
+  // arrange
+  let dir = temp_dir("headless-screenshot");
+  let args = ["--screenshot-at-frame", "3", "--screenshot-scale", "2"];
+
+  // act
+  let output = run_headless(&dir.join("game.nes"), 5, &args);
+
+  // assert
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  let png = fs::read(dir.join("game-frame3.png")).unwrap();
+  assert_eq!(png[..8], [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+  assert_eq!(&png[12..16], b"IHDR");
+  assert_eq!(png[16..20], 512u32.to_be_bytes()); // width
+  assert_eq!(png[20..24], 480u32.to_be_bytes()); // height
+  assert!(!dir.join("game-frame4.png").exists());
+}
+
+/// Number of frames in a `.y4m` file of NES frames, checking that they are all complete.
+fn y4m_frames(y4m: &[u8]) -> usize {
+  let header_end = y4m.iter().position(|&byte| byte == b'\n').unwrap() + 1;
+  let frame_size = b"FRAME\n".len() + SCREEN_WIDTH * SCREEN_HEIGHT * 3;
+  let frames = &y4m[header_end..];
+  assert_eq!(frames.len() % frame_size, 0);
+  assert!(frames.chunks(frame_size).all(|frame| frame.starts_with(b"FRAME\n")));
+  frames.len() / frame_size
+}
+
+#[test]
+fn test_headless_records_the_frames_after_record_from() {
+  // arrange
+  let dir = temp_dir("headless-record");
+  let (numerator, denominator) = Region::Ntsc.frame_rate();
+
+  // act
+  let output = run_headless(&dir.join("game.nes"), 10, &["--record", "run", "--record-from", "4"]);
+
+  // assert
+  assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+  // frames 5 to 10
+  assert_eq!(y4m_frames(&fs::read(dir.join("run.y4m")).unwrap()), 6);
+  let wav = fs::read(dir.join("run.wav")).unwrap();
+  let samples = 6 * AUDIO_SAMPLE_RATE as u64 * denominator / numerator;
+  assert_eq!(wav[40..44], (samples as u32 * 2).to_le_bytes());
+  assert_eq!(wav.len() as u64, 44 + samples * 2);
+}
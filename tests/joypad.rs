@@ -0,0 +1,54 @@
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::joypad::{Joypad, BUTTON_A, BUTTON_B, BUTTON_START};
+use nes_emulator_rust::ppu::PPU;
+
+#[test]
+fn test_buttons_are_read_one_by_one_after_the_strobe() {
+  // arrange
+  let mut joypad = Joypad::new();
+  joypad.buttons = BUTTON_B | BUTTON_START;
+
+  // act
+  joypad.write(1);
+  joypad.write(0);
+  let reads: Vec<u8> = (0..10).map(|_| joypad.read()).collect();
+
+  // assert
+  assert_eq!(reads, [0, 1, 0, 1, 0, 0, 0, 0, 1, 1]);
+}
+
+#[test]
+fn test_strobe_high_keeps_reading_a() {
+  // arrange
+  let mut joypad = Joypad::new();
+  joypad.buttons = BUTTON_A;
+
+  // act
+  joypad.write(1);
+  let reads: Vec<u8> = (0..3).map(|_| joypad.read()).collect();
+
+  // assert
+  assert_eq!(reads, [1, 1, 1]);
+}
+
+#[test]
+fn test_the_cpu_reads_the_joypad_at_4016() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  cpu.bus.joypad.buttons = BUTTON_B;
+  let program = vec![
+    0xA9, 0x01, 0x8D, 0x16, 0x40, // LDA #$01; STA $4016
+    0xA9, 0x00, 0x8D, 0x16, 0x40, // LDA #$00; STA $4016
+    0xAD, 0x16, 0x40, 0x85, 0x10, // LDA $4016; STA $10
+    0xAD, 0x16, 0x40, 0x85, 0x11, // LDA $4016; STA $11
+    0x00, // BRK
+  ];
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x10), 0);
+  assert_eq!(cpu.mem_read(0x11), 1);
+}
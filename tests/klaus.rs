@@ -0,0 +1,147 @@
+//! Klaus Dormann's 6502 functional and interrupt tests. The binaries aren't vendored: put
+//! `6502_functional_test.bin` and `6502_interrupt_test.bin` (from the `bin_files` directory of
+//! https://github.com/Klaus2m5/6502_65C02_functional_tests) in `tests/fixtures/klaus`, or point
+//! `KLAUS_TESTS_DIR` at that directory, and run the ignored tests with `--ignored`. They fail when
+//! the binaries are missing.
+
+use std::fs;
+use std::path::PathBuf;
+
+use nes_emulator_rust::cycle_cpu::CycleCpu;
+use nes_emulator_rust::test_roms::{run_until_trap, KlausBus, INTERRUPT_PORT, KLAUS_START};
+
+/// Where the prebuilt binaries trap when every test passed.
+const FUNCTIONAL_SUCCESS: u16 = 0x3469;
+const INTERRUPT_SUCCESS: u16 = 0x06F5;
+const MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+fn image(name: &str) -> Vec<u8> {
+  let dir = std::env::var_os("KLAUS_TESTS_DIR")
+    .map(PathBuf::from)
+    .unwrap_or_else(|| PathBuf::from("tests/fixtures/klaus"));
+  let path = dir.join(name);
+  fs::read(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+/// A bus with `program` at `KLAUS_START` and the interrupt vectors pointing at $0500 (NMI) and
+/// $0600 (IRQ).
+fn bus_with_program(program: &[u8]) -> KlausBus {
+  let mut image = vec![0; 0x10000];
+  image[KLAUS_START as usize..KLAUS_START as usize + program.len()].copy_from_slice(program);
+  image[0xFFFA..].copy_from_slice(&[0x00, 0x05, 0x00, 0x04, 0x00, 0x06]);
+  KlausBus::new(&image).unwrap()
+}
+
+fn cpu_at_start() -> CycleCpu {
+  let mut cpu = CycleCpu::new();
+  cpu.program_counter = KLAUS_START;
+  cpu
+}
+
+fn run_image(name: &str, success: u16) {
+  let image = image(name);
+  let mut bus = KlausBus::new(&image).unwrap();
+  let mut cpu = cpu_at_start();
+
+  let trap =
+    run_until_trap(&mut cpu, &mut bus, MAX_INSTRUCTIONS).unwrap_or_else(|err| panic!("{}", err));
+
+  if let Err(report) = trap.expect_at(success) {
+    panic!("{}", report);
+  }
+}
+
+#[test]
+#[ignore = "needs 6502_functional_test.bin"]
+fn test_functional_test() {
+  run_image("6502_functional_test.bin", FUNCTIONAL_SUCCESS);
+}
+
+#[test]
+#[ignore = "needs 6502_interrupt_test.bin"]
+fn test_interrupt_test() {
+  run_image("6502_interrupt_test.bin", INTERRUPT_SUCCESS);
+}
+
+#[test]
+fn test_trap_at_the_success_address() {
+  // arrange
+  let mut bus = bus_with_program(&[
+    0xA2, 0x05, // LDX #$05
+    0xCA, // loop: DEX
+    0xD0, 0xFD, // BNE loop
+    0x4C, 0x05, 0x04, // JMP * (success)
+  ]);
+  let mut cpu = cpu_at_start();
+
+  // act
+  let trap = run_until_trap(&mut cpu, &mut bus, 1000).unwrap();
+
+  // assert
+  assert_eq!(trap.pc, 0x0405);
+  assert_eq!(trap.instructions, 12);
+  assert!(trap.expect_at(0x0405).is_ok());
+}
+
+#[test]
+fn test_trap_elsewhere_reports_the_address_and_the_trace() {
+  // arrange
+  let mut bus = bus_with_program(&[
+    0xA9, 0x01, // LDA #$01
+    0xC9, 0x02, // CMP #$02
+    0xD0, 0xFE, // BNE * (failure)
+  ]);
+  let mut cpu = cpu_at_start();
+
+  // act
+  let trap = run_until_trap(&mut cpu, &mut bus, 1000).unwrap();
+  let report = trap.expect_at(0x3469).unwrap_err();
+
+  // assert
+  assert!(report.starts_with("trapped at $0404 after 3 instructions (success is $3469)"));
+  assert!(report.contains("0402  A:01 X:00 Y:00"));
+  assert_eq!(trap.trace.len(), 3);
+}
+
+#[test]
+fn test_no_trap_is_an_error() {
+  // arrange
+  let mut bus = bus_with_program(&[0xE8, 0x4C, 0x00, 0x04]); // loop: INX; JMP loop
+  let mut cpu = cpu_at_start();
+
+  // act
+  let result = run_until_trap(&mut cpu, &mut bus, 100);
+
+  // assert
+  assert!(result
+    .unwrap_err()
+    .starts_with("no trap after 100 instructions"));
+}
+
+#[test]
+fn test_interrupt_port_drives_nmi_and_irq() {
+  // arrange
+  let mut program = vec![
+    0xA9, 0x02, // LDA #$02
+    0x8D, 0xFC, 0xBF, // STA $BFFC (NMI)
+  ];
+  program.resize(0x100, 0xEA);
+  program.extend([0x4C, 0x00, 0x05]); // $0500: JMP *
+  let mut nmi_bus = bus_with_program(&program);
+  let mut irq_bus = bus_with_program(&[
+    0x58, // CLI
+    0xA9, 0x01, // LDA #$01
+    0x8D, 0xFC, 0xBF, // STA $BFFC (IRQ)
+    0xEA, // NOP
+  ]);
+  irq_bus.memory[0x0600..0x0603].copy_from_slice(&[0x4C, 0x00, 0x06]); // JMP *
+
+  // act
+  let nmi = run_until_trap(&mut cpu_at_start(), &mut nmi_bus, 100).unwrap();
+  let irq = run_until_trap(&mut cpu_at_start(), &mut irq_bus, 100).unwrap();
+
+  // assert
+  assert_eq!(nmi.pc, 0x0500);
+  assert_eq!(irq.pc, 0x0600);
+  assert_eq!(nmi_bus.memory[INTERRUPT_PORT as usize], 0x02);
+}
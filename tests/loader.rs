@@ -0,0 +1,178 @@
+use std::fs;
+use std::path::PathBuf;
+
+use nes_emulator_rust::cpu::{CpuError, CPU};
+use nes_emulator_rust::loader::{Format, Program, Segment};
+
+fn temp_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("loader-{}-{}", name, std::process::id()));
+  fs::create_dir_all(&dir).unwrap();
+  dir
+}
+
+#[test]
+fn test_load_program_puts_segments_at_their_addresses_and_sets_vectors() {
+  // arrange
+  let mut cpu = CPU::new();
+  let mut program = Program::new();
+  program.add_segment(0xC000, vec![0xE8, 0x00]).unwrap(); // INX; BRK
+  program.add_segment(0x0200, vec![0x11, 0x22]).unwrap();
+  program.reset_vector = Some(0xC000);
+  program.nmi_vector = Some(0xC100);
+
+  // act
+  cpu.load_program(&program).unwrap();
+  cpu.reset();
+  cpu.run().unwrap();
+
+  // assert
+  assert_eq!(cpu.register_x, 1);
+  assert_eq!(cpu.mem_read(0x0201), 0x22);
+  assert_eq!(cpu.mem_read_u16(0xFFFA), 0xC100);
+  assert_eq!(cpu.mem_read_u16(0xFFFE), 0x0000); // not set by the program
+}
+
+#[test]
+fn test_segments_must_fit_in_memory() {
+  // arrange
+  let mut cpu = CPU::new();
+  let mut program = Program::new();
+  program
+    .segments
+    .push(Segment::new(0xFFFF, vec![0xEA, 0xEA]));
+
+  // act
+  let result = cpu.load_program(&program);
+
+  // assert
+  assert_eq!(
+    result,
+    Err(CpuError::SegmentOutOfRange {
+      address: 0xFFFF,
+      size: 2
+    })
+  );
+  assert!(Program::new()
+    .add_segment(0xFFFF, vec![0xEA, 0xEA])
+    .is_err());
+  assert!(Program::new().add_segment(0xFFFE, vec![0xEA, 0xEA]).is_ok());
+}
+
+#[test]
+fn test_prg_starts_with_its_load_address() {
+  // act
+  let program = Program::from_prg(&[0x01, 0x08, 0xA9, 0x05]).unwrap();
+
+  // assert
+  assert_eq!(
+    program.segments,
+    vec![Segment::new(0x0801, vec![0xA9, 0x05])]
+  );
+  assert_eq!(program.reset_vector, Some(0x0801));
+  assert!(Program::from_prg(&[0x01]).is_err());
+}
+
+#[test]
+fn test_intel_hex_merges_contiguous_records() {
+  // arrange
+  let text = "\
+:03C00000A905E8A7
+:01C00300003C
+:020000040000FA
+:020200001122C9
+:00000001FF
+";
+
+  // act
+  let program = Program::from_intel_hex(text).unwrap();
+
+  // assert
+  assert_eq!(
+    program.segments,
+    vec![
+      Segment::new(0xC000, vec![0xA9, 0x05, 0xE8, 0x00]),
+      Segment::new(0x0200, vec![0x11, 0x22]),
+    ]
+  );
+  assert_eq!(program.reset_vector, None);
+}
+
+#[test]
+fn test_intel_hex_errors() {
+  // act
+  let bad_checksum = Program::from_intel_hex(":03C00000A905E8A8");
+  let past_memory = Program::from_intel_hex(":020000040001F9\n:020200001122C9");
+  let start_address = Program::from_intel_hex(":040000050000C00037").unwrap();
+
+  // assert
+  assert_eq!(bad_checksum, Err("line 1: bad checksum".to_string()));
+  assert!(past_memory.is_err());
+  assert_eq!(start_address.reset_vector, Some(0xC000));
+}
+
+#[test]
+fn test_ld65_segments_are_placed_by_the_debug_file() {
+  // arrange
+  let binary = [0xE8, 0x00, 0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0];
+  let debug_info = "\
+version\tmajor=2,minor=0
+seg\tid=0,name=\"CODE\",start=0x00C000,size=0x0002,addrsize=absolute,type=ro,oname=\"a.bin\",ooffs=0
+seg\tid=1,name=\"BSS\",start=0x000200,size=0x0010,addrsize=absolute,type=rw
+seg\tid=2,name=\"VECTORS\",start=0x00FFFA,size=0x0006,addrsize=absolute,type=ro,oname=\"a.bin\",ooffs=2
+";
+
+  // act
+  let program = Program::from_ld65(&binary, debug_info).unwrap();
+  let mut cpu = CPU::new();
+  cpu.load_program(&program).unwrap();
+  cpu.reset();
+  cpu.run().unwrap();
+
+  // assert
+  assert_eq!(program.segments.len(), 2);
+  assert_eq!(cpu.mem_read_u16(0xFFFC), 0xC000);
+  assert_eq!(cpu.register_x, 1);
+}
+
+#[test]
+fn test_ld65_segment_with_a_huge_size_is_an_error() {
+  // arrange
+  let binary = [0xE8, 0x00];
+  let debug_info = "\
+seg\tid=0,name=\"CODE\",start=0x00C000,size=0xFFFFFFFFFFFFFFFF,type=ro,oname=\"a.bin\",ooffs=1
+";
+
+  // act
+  let result = Program::from_ld65(&binary, debug_info);
+
+  // assert
+  assert_eq!(
+    result.err(),
+    Some("segment CODE is past the end of the binary".to_string())
+  );
+}
+
+#[test]
+fn test_from_file_detects_the_format() {
+  // arrange
+  let dir = temp_dir("detect");
+  let prg = dir.join("game.prg");
+  let raw = dir.join("game.bin");
+  fs::write(&prg, [0x00, 0x10, 0xEA]).unwrap();
+  fs::write(&raw, [0xEA]).unwrap();
+
+  // act
+  let from_prg = Program::from_file(&prg, None).unwrap();
+  let from_raw = Program::from_file(&raw, Some(0x0400)).unwrap();
+  let without_address = Program::from_file(&raw, None);
+
+  // assert
+  assert_eq!(Format::detect(&dir.join("a.HEX")), Format::IntelHex);
+  assert_eq!(from_prg.segments, vec![Segment::new(0x1000, vec![0xEA])]);
+  assert_eq!(from_raw.reset_vector, Some(0x0400));
+  assert!(without_address.is_err());
+
+  fs::write(raw.with_extension("dbg"), "").unwrap();
+  assert_eq!(Format::detect(&raw), Format::Ld65);
+  fs::remove_dir_all(dir).unwrap();
+}
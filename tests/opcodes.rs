@@ -1,4 +1,4 @@
-use nes_emulator_rust::{cpu::AddressingMode, opcodes::{Opcode, OPCODES_MAP}};
+use nes_emulator_rust::{cpu::AddressingMode, opcodes::{self, Handler, Opcode, CPU_OP_CODES}};
 
 #[test]
 fn test_new_opcode() {
@@ -10,7 +10,7 @@ fn test_new_opcode() {
   let addressing_mode: AddressingMode = AddressingMode::NoneAddressing;
 
   // act
-  let opcode = Opcode::new(code, name, bytes, _cycles, addressing_mode);
+  let opcode = Opcode::new(code, name, bytes, _cycles, addressing_mode, Handler::Implied(|_| {}));
   
   // assert
   assert_eq!(opcode.code, code);
@@ -27,15 +27,30 @@ fn test_brk_from_opcodes_map_has_right_data(){
   let bytes: u8 = 1;
   let _cycles: u8 = 7;
   let addressing_mode = AddressingMode::NoneAddressing;
-  let brk = Opcode::new(code, name, bytes, _cycles, addressing_mode);
+  let brk = Opcode::new(code, name, bytes, _cycles, addressing_mode, Handler::Implied(|_| {}));
 
   // act
-  let opcodes = &*OPCODES_MAP;
-  let brk_from_opcodes = opcodes.get(&0x00).unwrap();
+  let brk_from_opcodes = opcodes::decode(0x00).unwrap();
 
   // assert
   assert_eq!(brk_from_opcodes.code, brk.code);
   assert_eq!(brk_from_opcodes.name, brk.name);
   assert_eq!(brk_from_opcodes.bytes, brk.bytes);
   assert_eq!(brk_from_opcodes.addressing_mode, brk.addressing_mode);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_decode_table_has_every_listed_opcode_in_its_slot() {
+  // act
+  let decoded: Vec<u8> = (0..=255u8)
+    .filter_map(opcodes::decode)
+    .map(|opcode| opcode.code)
+    .collect();
+
+  // assert
+  assert_eq!(decoded.len(), CPU_OP_CODES.len());
+  for opcode in CPU_OP_CODES {
+    assert_eq!(opcodes::decode(opcode.code).unwrap().name, opcode.name);
+  }
+  assert!(opcodes::decode(0xFF).is_none());
+}
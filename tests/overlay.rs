@@ -0,0 +1,76 @@
+use nes_emulator_rust::overlay::Overlay;
+
+/// Draws `overlay` on a black frame and returns it as rows of '#' (lit) and '.' (black).
+fn render(overlay: &Overlay, width: usize, height: usize) -> Vec<String> {
+  let mut rgb = vec![0; width * height * 3];
+  overlay.draw(&mut rgb, width, height);
+  rgb
+    .chunks_exact(width * 3)
+    .map(|row| {
+      row
+        .chunks_exact(3)
+        .map(|pixel| if pixel == [0, 0, 0] { '.' } else { '#' })
+        .collect()
+    })
+    .collect()
+}
+
+#[test]
+fn test_text_is_drawn_in_the_font() {
+  // arrange
+  let mut overlay = Overlay::new();
+
+  // act
+  overlay.text(0, 0, "h1", 0xFFFFFF);
+
+  // assert
+  assert_eq!(
+    render(&overlay, 7, 5),
+    ["#.#..#.", "#.#.##.", "###..#.", "#.#..#.", "#.#.###"]
+  );
+}
+
+#[test]
+fn test_unknown_characters_are_question_marks() {
+  // arrange
+  let mut overlay = Overlay::new();
+  let mut question_mark = Overlay::new();
+
+  // act
+  overlay.text(0, 0, "é", 0xFFFFFF);
+  question_mark.text(0, 0, "?", 0xFFFFFF);
+
+  // assert
+  assert_eq!(render(&overlay, 3, 5), render(&question_mark, 3, 5));
+}
+
+#[test]
+fn test_boxes_are_outlined_and_filled() {
+  // arrange
+  let mut overlay = Overlay::new();
+  overlay.rect(3, 2, 0, 0, Some(0x123456), 0xFF0000);
+  let mut rgb = vec![0; 5 * 3 * 3];
+
+  // act
+  overlay.draw(&mut rgb, 5, 3);
+
+  // assert
+  let pixel = |x: usize, y: usize| &rgb[(y * 5 + x) * 3..(y * 5 + x) * 3 + 3];
+  assert_eq!(pixel(0, 0), [0xFF, 0x00, 0x00]);
+  assert_eq!(pixel(3, 2), [0xFF, 0x00, 0x00]);
+  assert_eq!(pixel(1, 1), [0x12, 0x34, 0x56]);
+  assert_eq!(pixel(4, 1), [0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_shapes_are_clipped_to_the_frame() {
+  // arrange
+  let mut overlay = Overlay::new();
+
+  // act
+  overlay.rect(-5, -5, 1_000_000, 1, None, 0xFFFFFF);
+  overlay.text(2, 2, "8", 0xFFFFFF);
+
+  // assert
+  assert_eq!(render(&overlay, 4, 4), ["....", "####", "..##", "..#."]);
+}
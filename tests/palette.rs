@@ -0,0 +1,163 @@
+use nes_emulator_rust::{
+  palette::{BuiltinPalette, Palette},
+  ppu::PPU,
+  region::Region,
+};
+
+#[test]
+fn test_builtin_palettes_have_known_colors() {
+  // arrange
+  let fceux = Palette::builtin(BuiltinPalette::Fceux);
+  let nestopia = Palette::builtin(BuiltinPalette::Nestopia);
+
+  // act / assert
+  assert_eq!(fceux.rgb(0x00), (0x74, 0x74, 0x74));
+  assert_eq!(fceux.rgb(0x30), (0xFC, 0xFC, 0xFC));
+  assert_eq!(nestopia.rgb(0x01), (0x00, 0x2A, 0x88));
+  assert_eq!(nestopia.rgb(0x0F), (0x00, 0x00, 0x00));
+}
+
+#[test]
+fn test_generated_2c02_palette_has_black_white_and_greys() {
+  // arrange
+  let palette = Palette::builtin(BuiltinPalette::Ppu2C02);
+
+  // act
+  let black = palette.rgb(0x0F);
+  let white = palette.rgb(0x30);
+  let grey = palette.rgb(0x10);
+  let red = palette.rgb(0x16);
+
+  // assert
+  assert_eq!(black, (0x00, 0x00, 0x00));
+  assert_eq!(white, (0xFF, 0xFF, 0xFF));
+  assert_eq!(grey.0, grey.1);
+  assert_eq!(grey.1, grey.2);
+  assert!(red.0 > red.1 && red.0 > red.2);
+}
+
+#[test]
+fn test_emphasis_dims_the_other_channels() {
+  for builtin in [BuiltinPalette::Ppu2C02, BuiltinPalette::Fceux, BuiltinPalette::Nestopia] {
+    // arrange
+    let palette = Palette::builtin(builtin);
+    let white = palette.rgb(0x20);
+
+    // act
+    let red_emphasis = palette.rgb(0b001 << 6 | 0x20);
+    let all_emphasis = palette.rgb(0b111 << 6 | 0x20);
+
+    // assert
+    assert!(red_emphasis.1 < white.1 && red_emphasis.2 < white.2, "{:?}", builtin);
+    assert!(red_emphasis.0 >= red_emphasis.1, "{:?}", builtin);
+    assert!(all_emphasis.0 < white.0, "{:?}", builtin);
+  }
+}
+
+#[test]
+fn test_emphasis_leaves_columns_e_and_f_alone() {
+  for builtin in [BuiltinPalette::Ppu2C02, BuiltinPalette::Fceux, BuiltinPalette::Nestopia] {
+    // arrange
+    let palette = Palette::builtin(builtin);
+
+    for color in [0x0E, 0x0F, 0x1E, 0x1F, 0x2E, 0x2F, 0x3E, 0x3F] {
+      // act
+      let variants: Vec<_> = (0..8).map(|emphasis| palette.rgb(emphasis << 6 | color)).collect();
+
+      // assert
+      assert!(
+        variants.iter().all(|&rgb| rgb == variants[0]),
+        "{:?} ${:02X}: {:?}",
+        builtin,
+        color,
+        variants
+      );
+    }
+  }
+}
+
+#[test]
+fn test_pal_file_with_64_colors() {
+  // arrange
+  let bytes: Vec<u8> = (0..64u8).flat_map(|index| [index, 0x80, 0xFF - index]).collect();
+
+  // act
+  let palette = Palette::from_pal_bytes(&bytes).unwrap();
+
+  // assert
+  assert_eq!(palette.rgb(0x05), (0x05, 0x80, 0xFA));
+  assert_eq!(palette.rgb(0b100 << 6 | 0x05), (0x04, 0x68, 0xFA)); // blue emphasis
+}
+
+#[test]
+fn test_pal_file_with_512_colors() {
+  // arrange
+  let bytes: Vec<u8> = (0..512u16).flat_map(|index| [(index >> 8) as u8, index as u8, 0x00]).collect();
+
+  // act
+  let palette = Palette::from_pal_bytes(&bytes).unwrap();
+
+  // assert
+  assert_eq!(palette.rgb(0x1FF), (0x01, 0xFF, 0x00));
+}
+
+#[test]
+fn test_pal_file_with_wrong_size() {
+  // act
+  let result = Palette::from_pal_bytes(&[0x00; 100]);
+
+  // assert
+  assert_eq!(result.err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_load_pal_file() {
+  // arrange
+  let path = std::env::temp_dir().join("nes-emulator-rust-test.pal");
+  std::fs::write(&path, [0x11; 192]).unwrap();
+
+  // act
+  let palette = Palette::load(&path);
+  std::fs::remove_file(&path).unwrap();
+
+  // assert
+  assert_eq!(palette.unwrap().rgb(0x3F), (0x11, 0x11, 0x11));
+}
+
+#[test]
+fn test_frame_to_rgb() {
+  // arrange
+  let palette = Palette::builtin(BuiltinPalette::Fceux);
+  let frame = [0x00, 0x30];
+  let mut rgb = [0u8; 6];
+
+  // act
+  palette.frame_to_rgb(&frame, &mut rgb);
+
+  // assert
+  assert_eq!(rgb, [0x74, 0x74, 0x74, 0xFC, 0xFC, 0xFC]);
+}
+
+fn render_backdrop(region: Region, mask: u8) -> u16 {
+  let mut ppu = PPU::new();
+  ppu.region = region;
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x00);
+  ppu.write_to_data(0x16);
+  ppu.write_to_mask(mask);
+  while ppu.frame_count == 0 || ppu.scanline < 1 {
+    ppu.tick(1);
+  }
+  ppu.frame[0]
+}
+
+#[test]
+fn test_ppu_output_honours_greyscale_and_emphasis() {
+  // act / assert
+  assert_eq!(render_backdrop(Region::Ntsc, 0b0000_0000), 0x16);
+  assert_eq!(render_backdrop(Region::Ntsc, 0b0000_0001), 0x10);
+  assert_eq!(render_backdrop(Region::Ntsc, 0b0010_0000), 0b001 << 6 | 0x16);
+  assert_eq!(render_backdrop(Region::Ntsc, 0b1100_0001), 0b110 << 6 | 0x10);
+  // red and green are swapped on PAL
+  assert_eq!(render_backdrop(Region::Pal, 0b0010_0000), 0b010 << 6 | 0x16);
+}
@@ -0,0 +1,547 @@
+use nes_emulator_rust::{
+  cpu::CPU,
+  ppu::{Mirroring, PPU, SCREEN_WIDTH},
+  region::Region,
+};
+
+fn tick_to(ppu: &mut PPU, scanline: u16, dot: u16) {
+  while ppu.scanline != scanline || ppu.dot != dot {
+    ppu.tick(1);
+  }
+}
+
+#[test]
+fn test_scroll_and_ppu_addr_writes_update_loopy_registers() {
+  // arrange
+  let mut ppu = PPU::new();
+
+  // act / assert
+  ppu.write_to_ctrl(0x00);
+  ppu.read_status();
+  assert!(!ppu.w);
+
+  ppu.write_to_scroll(0x7D);
+  assert_eq!(ppu.t, 0x000F);
+  assert_eq!(ppu.fine_x, 0b101);
+  assert!(ppu.w);
+
+  ppu.write_to_scroll(0x5E);
+  assert_eq!(ppu.t, 0x616F);
+  assert!(!ppu.w);
+
+  ppu.write_to_ppu_addr(0x3D);
+  assert_eq!(ppu.t, 0x3D6F);
+  assert!(ppu.w);
+
+  ppu.write_to_ppu_addr(0xF0);
+  assert_eq!(ppu.t, 0x3DF0);
+  assert_eq!(ppu.v, 0x3DF0);
+  assert!(!ppu.w);
+}
+
+#[test]
+fn test_ctrl_write_selects_nametable_in_t() {
+  // arrange
+  let mut ppu = PPU::new();
+
+  // act
+  ppu.write_to_ctrl(0b0000_0011);
+
+  // assert
+  assert_eq!(ppu.t, 0x0C00);
+}
+
+#[test]
+fn test_rendering_copies_and_increments_v() {
+  // arrange
+  let mut ppu = PPU::new();
+  ppu.write_to_mask(0b0000_1000);
+  ppu.write_to_ctrl(0b0000_0001);
+  ppu.write_to_scroll(0x08); // coarse X = 1
+  ppu.write_to_scroll(0x12); // coarse Y = 2, fine Y = 2
+
+  // act / assert
+  // pre-render line: vertical bits copied during dots 280-304
+  tick_to(&mut ppu, 261, 305);
+  assert_eq!(ppu.v & 0x7BE0, ppu.t & 0x7BE0);
+
+  // the first two tiles of the next line are prefetched at dots 321-336
+  tick_to(&mut ppu, 0, 0);
+  assert_eq!(ppu.v & 0x001F, 3);
+
+  // dot 256 increments fine Y
+  tick_to(&mut ppu, 0, 257);
+  assert_eq!(ppu.v & 0x7000, 0x3000);
+
+  // dot 257 copies the horizontal bits back from t
+  tick_to(&mut ppu, 0, 258);
+  assert_eq!(ppu.v & 0x041F, ppu.t & 0x041F);
+}
+
+#[test]
+fn test_fine_y_overflow_moves_to_next_nametable_after_row_29() {
+  // arrange
+  let mut ppu = PPU::new();
+  ppu.write_to_mask(0b0000_1000);
+  ppu.write_to_scroll(0x00);
+  ppu.write_to_scroll(0xEF); // coarse Y = 29, fine Y = 7
+
+  // act
+  tick_to(&mut ppu, 0, 257);
+
+  // assert
+  assert_eq!(ppu.v & 0x7BE0, 0x0800); // nametable Y flipped, coarse and fine Y reset
+}
+
+#[test]
+fn test_ppu_data_reads_are_buffered_except_for_palettes() {
+  // arrange
+  let mut ppu = PPU::new();
+  ppu.write_to_ppu_addr(0x23);
+  ppu.write_to_ppu_addr(0x05);
+  ppu.write_to_data(0x66);
+  ppu.write_to_data(0x77);
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x10);
+  ppu.write_to_data(0x21);
+
+  // act / assert
+  ppu.write_to_ppu_addr(0x23);
+  ppu.write_to_ppu_addr(0x05);
+  assert_eq!(ppu.read_data(), 0x00); // stale buffer
+  assert_eq!(ppu.read_data(), 0x66);
+  assert_eq!(ppu.read_data(), 0x77);
+
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x00);
+  assert_eq!(ppu.read_data(), 0x21); // $3F10 mirrors $3F00
+}
+
+#[test]
+fn test_mid_frame_nametable_switch_renders_split_screen() {
+  // arrange
+  let mut chr = vec![0; 0x2000];
+  chr[16..24].copy_from_slice(&[0xFF; 8]); // tile 1: solid colour 1
+  let mut ppu = PPU::new_with_chr(chr, Mirroring::Vertical);
+
+  ppu.write_to_ppu_addr(0x24);
+  ppu.write_to_ppu_addr(0x00);
+  for _ in 0..960 {
+    ppu.write_to_data(0x01); // second nametable is full of tile 1
+  }
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x00);
+  ppu.write_to_data(0x0F);
+  ppu.write_to_data(0x30);
+
+  ppu.write_to_ctrl(0x00);
+  ppu.write_to_scroll(0x00);
+  ppu.write_to_scroll(0x00);
+  ppu.write_to_mask(0b0000_1010);
+
+  // act
+  tick_to(&mut ppu, 100, 300);
+  ppu.write_to_ctrl(0x01);
+  tick_to(&mut ppu, 241, 0);
+
+  // assert
+  let row = |y: usize| &ppu.frame[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH];
+  assert!(row(0).iter().all(|&color| color == 0x0F));
+  assert!(row(101).iter().all(|&color| color == 0x0F));
+  assert!(row(102).iter().all(|&color| color == 0x30));
+  assert!(row(239).iter().all(|&color| color == 0x30));
+}
+
+#[test]
+fn test_cpu_reaches_ppu_registers_through_the_bus() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  let program = vec![
+    0xA9, 0x21, 0x8D, 0x06, 0x20, // LDA #$21; STA $2006
+    0xA9, 0x08, 0x8D, 0x06, 0x20, // LDA #$08; STA $2006
+    0xA9, 0x42, 0x8D, 0x07, 0x20, // LDA #$42; STA $2007
+    0x00, // BRK
+  ];
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  let ppu = cpu.bus.ppu.as_mut().unwrap();
+  assert_eq!(ppu.v, 0x2109);
+  ppu.write_to_ppu_addr(0x21);
+  ppu.write_to_ppu_addr(0x08);
+  ppu.read_data();
+  assert_eq!(ppu.read_data(), 0x42);
+}
+
+const SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+const SPRITE_OVERFLOW: u8 = 0b0010_0000;
+
+/// PPU with an opaque background everywhere (tile 1) and sprites/background enabled.
+fn ppu_with_opaque_background(mask: u8) -> PPU {
+  let mut chr = vec![0; 0x2000];
+  chr[16..24].copy_from_slice(&[0xFF; 8]); // tile 1: solid colour 1
+  let mut ppu = PPU::new_with_chr(chr, Mirroring::Vertical);
+
+  ppu.write_to_ppu_addr(0x20);
+  ppu.write_to_ppu_addr(0x00);
+  for _ in 0..960 {
+    ppu.write_to_data(0x01);
+  }
+  ppu.write_to_ppu_addr(0x00);
+  ppu.write_to_ppu_addr(0x00);
+  ppu.write_to_mask(mask);
+  ppu
+}
+
+fn place_sprite(ppu: &mut PPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+  ppu.oam_data[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+}
+
+#[test]
+fn test_sprite_zero_hit_is_set_on_first_overlapping_opaque_pixel() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  place_sprite(&mut ppu, 0, 30, 0x01, 0x00, 100);
+
+  // act / assert
+  tick_to(&mut ppu, 31, 100);
+  assert_eq!(ppu.status & SPRITE_ZERO_HIT, 0);
+  tick_to(&mut ppu, 31, 102); // dot 101 draws x = 100
+  assert_eq!(ppu.status & SPRITE_ZERO_HIT, SPRITE_ZERO_HIT);
+}
+
+#[test]
+fn test_sprite_zero_hit_is_cleared_on_pre_render_line() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  place_sprite(&mut ppu, 0, 30, 0x01, 0x00, 100);
+
+  // act
+  tick_to(&mut ppu, 0, 0);
+  tick_to(&mut ppu, 261, 1);
+  let status_before = ppu.status;
+  tick_to(&mut ppu, 261, 2);
+
+  // assert
+  assert_eq!(status_before & SPRITE_ZERO_HIT, SPRITE_ZERO_HIT);
+  assert_eq!(ppu.status & SPRITE_ZERO_HIT, 0);
+}
+
+#[test]
+fn test_sprite_zero_hit_never_happens_at_x_255() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  place_sprite(&mut ppu, 0, 30, 0x01, 0x00, 255);
+
+  // act
+  tick_to(&mut ppu, 241, 0);
+
+  // assert
+  assert_eq!(ppu.status & SPRITE_ZERO_HIT, 0);
+}
+
+#[test]
+fn test_sprite_zero_hit_respects_left_8_pixels_clipping() {
+  // arrange
+  let mut background_clipped = ppu_with_opaque_background(0b0001_1100);
+  place_sprite(&mut background_clipped, 0, 30, 0x01, 0x00, 0);
+  let mut sprites_clipped = ppu_with_opaque_background(0b0001_1010);
+  place_sprite(&mut sprites_clipped, 0, 30, 0x01, 0x00, 0);
+  let mut partially_clipped = ppu_with_opaque_background(0b0001_1010);
+  place_sprite(&mut partially_clipped, 0, 30, 0x01, 0x00, 1);
+
+  // act
+  tick_to(&mut background_clipped, 241, 0);
+  tick_to(&mut sprites_clipped, 241, 0);
+  tick_to(&mut partially_clipped, 241, 0);
+
+  // assert
+  assert_eq!(background_clipped.status & SPRITE_ZERO_HIT, 0);
+  assert_eq!(sprites_clipped.status & SPRITE_ZERO_HIT, 0);
+  assert_eq!(partially_clipped.status & SPRITE_ZERO_HIT, SPRITE_ZERO_HIT); // x = 8 is visible
+}
+
+#[test]
+fn test_sprite_zero_hit_needs_background_and_sprites_rendering() {
+  // arrange
+  let mut background_disabled = ppu_with_opaque_background(0b0001_0110);
+  place_sprite(&mut background_disabled, 0, 30, 0x01, 0x00, 100);
+  let mut sprites_disabled = ppu_with_opaque_background(0b0000_1110);
+  place_sprite(&mut sprites_disabled, 0, 30, 0x01, 0x00, 100);
+
+  // act
+  tick_to(&mut background_disabled, 241, 0);
+  tick_to(&mut sprites_disabled, 241, 0);
+
+  // assert
+  assert_eq!(background_disabled.status & SPRITE_ZERO_HIT, 0);
+  assert_eq!(sprites_disabled.status & SPRITE_ZERO_HIT, 0);
+}
+
+#[test]
+fn test_sprite_zero_hit_ignores_transparent_sprite_pixels() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  place_sprite(&mut ppu, 0, 30, 0x00, 0x00, 100); // tile 0 is fully transparent
+  place_sprite(&mut ppu, 1, 30, 0x01, 0x00, 100);
+
+  // act
+  tick_to(&mut ppu, 241, 0);
+
+  // assert
+  assert_eq!(ppu.status & SPRITE_ZERO_HIT, 0);
+}
+
+#[test]
+fn test_sprite_is_drawn_in_front_of_or_behind_background() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x01);
+  ppu.write_to_data(0x11); // background colour 1
+  ppu.write_to_ppu_addr(0x3F);
+  ppu.write_to_ppu_addr(0x11);
+  ppu.write_to_data(0x22); // sprite palette 0, colour 1
+  ppu.write_to_ppu_addr(0x00);
+  ppu.write_to_ppu_addr(0x00);
+  place_sprite(&mut ppu, 0, 30, 0x01, 0x00, 16);
+  place_sprite(&mut ppu, 1, 30, 0x01, 0b0010_0000, 32);
+
+  // act
+  tick_to(&mut ppu, 241, 0);
+
+  // assert
+  let pixel = |x: usize, y: usize| ppu.frame[y * SCREEN_WIDTH + x];
+  assert_eq!(pixel(16, 31), 0x22);
+  assert_eq!(pixel(32, 31), 0x11);
+  assert_eq!(pixel(16, 30), 0x11); // sprites show up one line below their Y
+}
+
+#[test]
+fn test_sprite_overflow_is_set_with_nine_sprites_on_a_line() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  for index in 0..64 {
+    place_sprite(&mut ppu, index, 0xFF, 0xFF, 0xFF, 0xFF);
+  }
+  for index in 0..9 {
+    place_sprite(&mut ppu, index, 50, 0x00, 0x00, index as u8 * 8);
+  }
+
+  // act / assert
+  tick_to(&mut ppu, 50, 0);
+  assert_eq!(ppu.status & SPRITE_OVERFLOW, 0);
+  tick_to(&mut ppu, 50, 258);
+  assert_eq!(ppu.status & SPRITE_OVERFLOW, SPRITE_OVERFLOW);
+}
+
+#[test]
+fn test_sprite_overflow_false_positive_from_diagonal_oam_scan() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  for index in 0..64 {
+    place_sprite(&mut ppu, index, 0xFF, 0xFF, 0xFF, 0xFF);
+  }
+  for index in 0..8 {
+    place_sprite(&mut ppu, index, 50, 0x00, 0x00, index as u8 * 8);
+  }
+  // only 8 sprites are on line 50, but the 10th sprite tile number is read as a Y coordinate
+  place_sprite(&mut ppu, 9, 0xFF, 50, 0xFF, 0xFF);
+
+  // act
+  tick_to(&mut ppu, 50, 258);
+
+  // assert
+  assert_eq!(ppu.status & SPRITE_OVERFLOW, SPRITE_OVERFLOW);
+}
+
+#[test]
+fn test_sprite_overflow_false_negative_from_diagonal_oam_scan() {
+  // arrange
+  let mut ppu = ppu_with_opaque_background(0b0001_1110);
+  for index in 0..64 {
+    place_sprite(&mut ppu, index, 0xFF, 0xFF, 0xFF, 0xFF);
+  }
+  for index in 0..8 {
+    place_sprite(&mut ppu, index, 50, 0x00, 0x00, index as u8 * 8);
+  }
+  // the 10th and 11th sprites are on line 50 too, but their Y is never looked at
+  place_sprite(&mut ppu, 9, 50, 0xFF, 0xFF, 0xFF);
+  place_sprite(&mut ppu, 10, 50, 0xFF, 0xFF, 0xFF);
+
+  // act
+  tick_to(&mut ppu, 50, 258);
+
+  // assert
+  assert_eq!(ppu.status & SPRITE_OVERFLOW, 0);
+}
+
+fn dots_until_next_frame(ppu: &mut PPU) -> u32 {
+  let frame = ppu.frame_count;
+  let mut dots = 0;
+  while ppu.frame_count == frame {
+    ppu.tick(1);
+    dots += 1;
+  }
+  dots
+}
+
+#[test]
+fn test_odd_frames_skip_a_dot_when_rendering_is_enabled() {
+  // arrange
+  let mut ppu = PPU::new();
+  ppu.write_to_mask(0b0000_1000);
+  dots_until_next_frame(&mut ppu);
+
+  // act
+  let odd_frame = dots_until_next_frame(&mut ppu);
+  let even_frame = dots_until_next_frame(&mut ppu);
+
+  // assert
+  assert_eq!(odd_frame, 341 * 262 - 1);
+  assert_eq!(even_frame, 341 * 262);
+}
+
+#[test]
+fn test_no_dot_is_skipped_when_rendering_is_disabled_or_on_pal() {
+  // arrange
+  let mut disabled = PPU::new();
+  dots_until_next_frame(&mut disabled);
+  let mut pal = PPU::new();
+  pal.region = Region::Pal;
+  pal.scanline = Region::Pal.pre_render_scanline();
+  pal.write_to_mask(0b0000_1000);
+  dots_until_next_frame(&mut pal);
+
+  // act / assert
+  assert_eq!(dots_until_next_frame(&mut disabled), 341 * 262);
+  assert_eq!(dots_until_next_frame(&mut pal), 341 * 312);
+  assert_eq!(dots_until_next_frame(&mut pal), 341 * 312);
+}
+
+#[test]
+fn test_pal_runs_sixteen_dots_every_five_cpu_cycles() {
+  // arrange
+  let mut ppu = PPU::new();
+  ppu.region = Region::Pal;
+  ppu.scanline = 0;
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(ppu);
+
+  // act
+  cpu.bus.tick(4);
+  let dots_after_four_cycles = cpu.bus.ppu.as_ref().unwrap().dot;
+  cpu.bus.tick(1);
+  let dots_after_five_cycles = cpu.bus.ppu.as_ref().unwrap().dot;
+
+  // assert
+  assert_eq!(dots_after_four_cycles, 12);
+  assert_eq!(dots_after_five_cycles, 16);
+}
+
+fn ppu_before_vblank(dot: u16) -> PPU {
+  let mut ppu = PPU::new();
+  ppu.write_to_ctrl(0b1000_0000);
+  tick_to(&mut ppu, 241, dot);
+  ppu
+}
+
+#[test]
+fn test_vblank_flag_is_set_and_nmi_fired_at_scanline_241_dot_1() {
+  // arrange
+  let mut ppu = ppu_before_vblank(1);
+
+  // act
+  ppu.tick(1);
+
+  // assert
+  assert_eq!(ppu.status & 0b1000_0000, 0b1000_0000);
+  assert!(ppu.poll_nmi());
+  assert!(!ppu.poll_nmi());
+}
+
+#[test]
+fn test_status_read_one_dot_before_vblank_suppresses_flag_and_nmi() {
+  // arrange
+  let mut ppu = ppu_before_vblank(1);
+
+  // act
+  let status = ppu.read_status();
+  ppu.tick(10);
+
+  // assert
+  assert_eq!(status & 0b1000_0000, 0);
+  assert_eq!(ppu.status & 0b1000_0000, 0);
+  assert!(!ppu.poll_nmi());
+}
+
+#[test]
+fn test_status_read_on_vblank_dot_returns_flag_but_suppresses_nmi() {
+  for dot in [2, 3] {
+    // arrange
+    let mut ppu = ppu_before_vblank(dot);
+
+    // act
+    let status = ppu.read_status();
+
+    // assert
+    assert_eq!(status & 0b1000_0000, 0b1000_0000);
+    assert_eq!(ppu.status & 0b1000_0000, 0);
+    assert!(!ppu.poll_nmi());
+  }
+}
+
+#[test]
+fn test_status_read_later_in_vblank_keeps_nmi() {
+  // arrange
+  let mut ppu = ppu_before_vblank(4);
+
+  // act
+  let status = ppu.read_status();
+
+  // assert
+  assert_eq!(status & 0b1000_0000, 0b1000_0000);
+  assert!(ppu.poll_nmi());
+}
+
+#[test]
+fn test_enabling_nmi_during_vblank_triggers_it() {
+  // arrange
+  let mut ppu = PPU::new();
+  tick_to(&mut ppu, 245, 0);
+
+  // act
+  ppu.write_to_ctrl(0b1000_0000);
+
+  // assert
+  assert!(ppu.poll_nmi());
+}
+
+#[test]
+fn test_cpu_services_nmi_at_vblank() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+  let handler = vec![0xA9, 0x42, 0x85, 0x00, 0x00]; // LDA #$42; STA $00; BRK
+  cpu.bus.load(0x0700, &handler);
+  cpu.mem_write_u16(0xFFFA, 0x0700);
+  let program = vec![
+    0xA9, 0x80, 0x8D, 0x00, 0x20, // LDA #$80; STA $2000
+    0xD0, 0xFE, // loop: BNE loop
+  ];
+
+  // act
+  cpu.load_and_run(program);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x00), 0x42);
+  let ppu = cpu.bus.ppu.as_ref().unwrap();
+  assert_eq!((ppu.scanline, ppu.frame_count), (241, 1));
+  assert_eq!(cpu.stack_pointer, 0xFC);
+  assert_eq!(cpu.mem_read(0x01FF), 0x06); // return address, high byte
+  assert_eq!(cpu.mem_read(0x01FE), 0x05); // return address, low byte
+  assert_eq!(cpu.mem_read(0x01FD), 0b1010_0000); // status (negative from LDA #$80), B flag clear
+}
@@ -0,0 +1,157 @@
+use nes_emulator_rust::bus::Bus;
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::debugger::Debugger;
+use nes_emulator_rust::ram_search::{Comparison, Operand, RamSearch, ValueType};
+
+#[test]
+fn test_filter_against_previous_snapshot_finds_the_changed_variable() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.mem_write(0x0075, 3); // lives
+  let mut search = RamSearch::new(0x0000..=0x00FF, ValueType::U8);
+  search.reset(&bus);
+
+  // act
+  bus.mem_write(0x0075, 2);
+  search.filter(&bus, Comparison::ChangedBy(-1), Operand::Previous);
+
+  // assert
+  assert_eq!(search.candidates(), &[0x0075]);
+  assert_eq!(search.value(0x0075), 2);
+}
+
+#[test]
+fn test_filter_against_constant() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.mem_write(0x0010, 0x20);
+  bus.mem_write(0x0011, 0x30);
+  let mut search = RamSearch::new(0x0000..=0x001F, ValueType::U8);
+  search.reset(&bus);
+
+  // act
+  let greater = search.filter(&bus, Comparison::Greater, Operand::Constant(0x10));
+  let equal = search.filter(&bus, Comparison::Equal, Operand::Constant(0x30));
+
+  // assert
+  assert_eq!(greater, 2);
+  assert_eq!(equal, 1);
+  assert_eq!(search.candidates(), &[0x0011]);
+}
+
+#[test]
+fn test_filter_not_equal_and_less() {
+  // arrange
+  let mut bus = Bus::new();
+  let mut search = RamSearch::new(0x0000..=0x0003, ValueType::U8);
+  search.reset(&bus);
+
+  // act
+  bus.mem_write(0x0001, 5);
+  bus.mem_write(0x0002, 5);
+  search.filter(&bus, Comparison::NotEqual, Operand::Previous);
+  bus.mem_write(0x0002, 4);
+  search.filter(&bus, Comparison::Less, Operand::Previous);
+
+  // assert
+  assert_eq!(search.candidates(), &[0x0002]);
+}
+
+#[test]
+fn test_signed_interpretation() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.mem_write(0x0000, 0xFF); // -1
+  bus.mem_write(0x0001, 0x01);
+  let mut search = RamSearch::new(0x0000..=0x0001, ValueType::I8);
+  search.reset(&bus);
+
+  // act
+  search.filter(&bus, Comparison::Less, Operand::Constant(0));
+
+  // assert
+  assert_eq!(search.candidates(), &[0x0000]);
+  assert_eq!(search.value(0x0000), -1);
+}
+
+#[test]
+fn test_16_bit_values_are_little_endian() {
+  // arrange
+  let mut bus = Bus::new();
+  bus.mem_write(0x0020, 0xE8); // 1000
+  bus.mem_write(0x0021, 0x03);
+  let mut search = RamSearch::new(0x0000..=0x00FF, ValueType::U16);
+  search.reset(&bus);
+
+  // act
+  bus.mem_write(0x0020, 0xF2); // 1010
+  search.filter(&bus, Comparison::ChangedBy(10), Operand::Previous);
+
+  // assert
+  assert_eq!(search.candidates(), &[0x0020]);
+  assert_eq!(search.value(0x0020), 1010);
+}
+
+#[test]
+fn test_16_bit_search_does_not_read_past_the_range() {
+  // arrange
+  let bus = Bus::new();
+  let mut search = RamSearch::new(0x0000..=0x000F, ValueType::I16);
+
+  // act
+  search.reset(&bus);
+  search.filter(&bus, Comparison::Equal, Operand::Previous);
+
+  // assert
+  assert_eq!(search.candidates().len(), 15);
+}
+
+#[test]
+fn test_16_bit_search_of_a_single_byte_range_has_no_candidates() {
+  // arrange
+  let bus = Bus::new();
+  let mut search = RamSearch::new(0x0000..=0x0000, ValueType::U16);
+
+  // act
+  search.reset(&bus);
+  let left = search.filter(&bus, Comparison::Equal, Operand::Previous);
+
+  // assert
+  assert_eq!(left, 0);
+  assert!(search.candidates().is_empty());
+}
+
+#[test]
+fn test_debugger_search_commands() {
+  // arrange
+  let mut cpu = CPU::new();
+  let mut debugger = Debugger::new();
+  cpu.mem_write(0x0042, 10);
+
+  // act
+  debugger
+    .execute(&mut cpu, "search start u8 $0000-$00FF")
+    .unwrap();
+  cpu.mem_write(0x0042, 13);
+  let output = debugger.execute(&mut cpu, "search changed 3").unwrap();
+
+  // assert
+  assert_eq!(output, "1 candidates\n$0042 = 13");
+  assert!(debugger.execute(&mut cpu, "search sideways").is_err());
+}
+
+#[test]
+fn test_debugger_requires_a_started_search() {
+  // arrange
+  let mut cpu = CPU::new();
+  let mut debugger = Debugger::new();
+
+  // act
+  let result = debugger.execute(&mut cpu, "search eq 5");
+
+  // assert
+  assert_eq!(
+    result,
+    Err("no search in progress, use search start".to_string())
+  );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use nes_emulator_rust::region::{Region, RegionSetting};
+
+/// An image whose header has the given flags 7 (format), 9 (TV system) and 12 (NES 2.0 timing).
+fn ines_header(byte_7: u8, byte_9: u8, byte_12: u8) -> Vec<u8> {
+  let mut image = common::nrom_image(0, &[]);
+  image[7] = byte_7;
+  image[9] = byte_9;
+  image[12] = byte_12;
+  image
+}
+
+#[test]
+fn test_region_from_nes2_timing_field() {
+  // arrange
+  let ntsc = ines_header(0b0000_1000, 0x00, 0x00);
+  let pal = ines_header(0b0000_1000, 0x00, 0x01);
+  let multi_region = ines_header(0b0000_1000, 0x00, 0x02);
+  let dendy = ines_header(0b0000_1000, 0x00, 0x03);
+
+  // act / assert
+  assert_eq!(Region::from_ines_header(&ntsc), Some(Region::Ntsc));
+  assert_eq!(Region::from_ines_header(&pal), Some(Region::Pal));
+  assert_eq!(Region::from_ines_header(&multi_region), Some(Region::Ntsc));
+  assert_eq!(Region::from_ines_header(&dendy), Some(Region::Dendy));
+}
+
+#[test]
+fn test_region_from_ines_1_tv_system_bit() {
+  // arrange
+  let pal = ines_header(0x00, 0x01, 0x03);
+
+  // act
+  let region = Region::from_ines_header(&pal);
+
+  // assert
+  assert_eq!(region, Some(Region::Pal));
+}
+
+#[test]
+fn test_region_from_invalid_header() {
+  // arrange
+  let header = vec![0x00; 16];
+
+  // act
+  let region = Region::from_ines_header(&header);
+
+  // assert
+  assert_eq!(region, None);
+}
+
+#[test]
+fn test_region_setting_resolve() {
+  // arrange
+  let dendy = ines_header(0b0000_1000, 0x00, 0x03);
+
+  // act / assert
+  assert_eq!(RegionSetting::Auto.resolve(Some(&dendy)), Region::Dendy);
+  assert_eq!(RegionSetting::Auto.resolve(None), Region::Ntsc);
+  assert_eq!(RegionSetting::Manual(Region::Pal).resolve(Some(&dendy)), Region::Pal);
+}
+
+#[test]
+fn test_region_setting_parse() {
+  // act / assert
+  assert_eq!(RegionSetting::parse("auto"), Ok(RegionSetting::Auto));
+  assert_eq!(RegionSetting::parse("PAL"), Ok(RegionSetting::Manual(Region::Pal)));
+  assert_eq!(RegionSetting::parse("dendy"), Ok(RegionSetting::Manual(Region::Dendy)));
+  assert!(RegionSetting::parse("secam").is_err());
+}
+
+#[test]
+fn test_region_timings() {
+  // act / assert
+  assert_eq!(Region::Ntsc.ppu_dots_per_cpu_cycle(), 3.0);
+  assert_eq!(Region::Pal.ppu_dots_per_cpu_cycle(), 3.2);
+  assert_eq!(Region::Dendy.ppu_dots_per_cpu_cycle(), 3.0);
+
+  assert_eq!(Region::Ntsc.scanlines_per_frame(), 262);
+  assert_eq!(Region::Pal.scanlines_per_frame(), 312);
+  assert_eq!(Region::Dendy.pre_render_scanline(), 311);
+
+  assert_eq!(Region::Pal.vblank_scanlines(), 70);
+  assert_eq!(Region::Dendy.vblank_start_scanline(), 291);
+
+  assert!((Region::Ntsc.frames_per_second() - 60.0988).abs() < 0.001);
+  assert!((Region::Pal.frames_per_second() - 50.0070).abs() < 0.001);
+  assert_eq!(Region::Ntsc.frame_rate(), (39_375_000, 655_171));
+  assert_eq!(Region::Pal.frame_rate(), (3_325_214, 66_495));
+  assert_eq!(Region::Dendy.frame_rate(), Region::Pal.frame_rate());
+}
+
+#[test]
+fn test_region_apu_tables() {
+  // act / assert
+  assert_eq!(Region::Ntsc.noise_periods()[15], 4068);
+  assert_eq!(Region::Pal.noise_periods()[15], 3778);
+  assert_eq!(Region::Dendy.noise_periods(), Region::Ntsc.noise_periods());
+  assert_eq!(Region::Ntsc.dmc_rates()[0], 428);
+  assert_eq!(Region::Pal.dmc_rates()[0], 398);
+  assert_eq!(Region::Pal.frame_counter_four_step()[3], 33253);
+  assert_eq!(Region::Ntsc.frame_counter_five_step()[4], 37281);
+}
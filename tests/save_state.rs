@@ -0,0 +1,67 @@
+mod common;
+
+use std::fs;
+
+use nes_emulator_rust::cartridge::Cartridge;
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::save_state::SaveState;
+
+#[test]
+fn test_restore_goes_back_to_the_saved_machine() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.accumulator = 0x12;
+  cpu.mem_write(0x0300, 0x34);
+  let state = SaveState::capture(&cpu);
+  cpu.accumulator = 0x56;
+  cpu.mem_write(0x0300, 0x78);
+
+  // act
+  state.restore(&mut cpu);
+
+  // assert
+  assert_eq!(cpu.accumulator, 0x12);
+  assert_eq!(cpu.mem_read(0x0300), 0x34);
+}
+
+#[test]
+fn test_restore_keeps_the_cheats() {
+  // arrange
+  let mut cpu = CPU::new();
+  let state = SaveState::capture(&cpu);
+  cpu.bus.cheats.add("0300:99").unwrap();
+
+  // act
+  state.restore(&mut cpu);
+
+  // assert
+  assert_eq!(cpu.bus.cheats.cheats.len(), 1);
+  assert_eq!(cpu.bus.cheats.cheats[0].code, "0300:99");
+}
+
+#[test]
+fn test_only_the_running_cartridge_writes_the_save_file() {
+  // arrange
+  let path = std::env::temp_dir().join(format!("save-state-{}.sav", std::process::id()));
+  let mut cartridge = Cartridge::from_ines(&common::nrom_image(0b0000_0010, &[])).unwrap();
+  cartridge.set_save_path(Some(path.clone()));
+  let mut cpu = CPU::new();
+  cpu.bus.insert_cartridge(cartridge);
+  cpu.mem_write(0x6000, 1);
+  let state = SaveState::capture(&cpu);
+  cpu.mem_write(0x6000, 2);
+
+  // act
+  state.restore(&mut cpu);
+  let after_restore = fs::read(&path).ok();
+  drop(state);
+  let after_drop = fs::read(&path).ok();
+  drop(cpu);
+  let on_exit = fs::read(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  // assert
+  assert_eq!(after_restore, None);
+  assert_eq!(after_drop, None);
+  assert_eq!(on_exit[0], 1);
+}
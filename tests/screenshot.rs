@@ -0,0 +1,85 @@
+use std::fs;
+
+use nes_emulator_rust::easy6502::{Easy6502Machine, SCREEN_HEIGHT, SCREEN_WIDTH};
+use nes_emulator_rust::screenshot::{encode_png, save_png, scale_frame};
+
+/// Decodes a PNG into (width, height, RGB24 pixels).
+fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+  let mut reader = png::Decoder::new(png).read_info().unwrap();
+  let mut pixels = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut pixels).unwrap();
+  assert_eq!(info.color_type, png::ColorType::Rgb);
+  pixels.truncate(info.buffer_size());
+  (info.width, info.height, pixels)
+}
+
+#[test]
+fn test_scale_frame_repeats_pixels_and_rows() {
+  // arrange
+  let rgb = [1, 1, 1, 2, 2, 2];
+
+  // act
+  let scaled = scale_frame(&rgb, 2, 2);
+
+  // assert
+  assert_eq!(
+    scaled,
+    [1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2]
+  );
+}
+
+#[test]
+fn test_png_at_native_size() {
+  // arrange
+  let rgb: Vec<u8> = (0..4 * 2 * 3).map(|i| i as u8 * 10).collect();
+
+  // act
+  let png = encode_png(&rgb, 4, 2, 1).unwrap();
+
+  // assert
+  assert_eq!(decode(&png), (4, 2, rgb));
+}
+
+#[test]
+fn test_png_of_the_easy6502_screen_scaled_up() {
+  // arrange
+  let mut machine = Easy6502Machine::with_seed(1);
+  machine.load(&[0xA9, 0x02, 0x8D, 0x21, 0x02, 0x00]).unwrap(); // LDA #$02; STA $0221; BRK
+  machine.run_frame().unwrap();
+
+  // act
+  let png = encode_png(machine.frame(), SCREEN_WIDTH, SCREEN_HEIGHT, 10).unwrap();
+
+  // assert
+  let (width, height, pixels) = decode(&png);
+  assert_eq!((width, height), (320, 320));
+  let pixel = |x: usize, y: usize| &pixels[(y * 320 + x) * 3..(y * 320 + x) * 3 + 3];
+  assert_eq!(pixel(10, 10), [0x88, 0x00, 0x00]);
+  assert_eq!(pixel(19, 19), [0x88, 0x00, 0x00]);
+  assert_eq!(pixel(20, 19), [0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_invalid_frames_are_errors() {
+  // act
+  let zero_scale = encode_png(&[0; 3], 1, 1, 0);
+  let short = encode_png(&[0; 5], 1, 2, 1);
+
+  // assert
+  assert_eq!(zero_scale.unwrap_err(), "the scale must be at least 1");
+  assert_eq!(short.unwrap_err(), "a 1x2 frame is 6 bytes, not 5");
+}
+
+#[test]
+fn test_save_png_writes_the_file() {
+  // arrange
+  let path = std::env::temp_dir().join(format!("screenshot-{}.png", std::process::id()));
+
+  // act
+  save_png(&path, &[255, 0, 0], 1, 1, 2).unwrap();
+  let png = fs::read(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  // assert
+  assert_eq!(decode(&png), (2, 2, [255, 0, 0].repeat(4)));
+}
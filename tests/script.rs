@@ -0,0 +1,176 @@
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::joypad::{BUTTON_A, BUTTON_RIGHT};
+use nes_emulator_rust::ppu::PPU;
+use nes_emulator_rust::script::ScriptHost;
+
+/// Runs `program` (at $0600) until BRK, with the script callbacks.
+fn run(cpu: &mut CPU, host: &mut ScriptHost, program: Vec<u8>) {
+  cpu.load(program).unwrap();
+  cpu.reset();
+  cpu
+    .run_with_callback(|cpu| host.before_instruction(cpu).unwrap())
+    .unwrap();
+}
+
+#[test]
+fn test_script_reads_and_writes_memory_when_loaded() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.mem_write(0x10, 41);
+
+  // act
+  ScriptHost::new("memory::write(0x11, memory::read(0x10) + 1);", &mut cpu).unwrap();
+
+  // assert
+  assert_eq!(cpu.mem_read(0x11), 42);
+}
+
+#[test]
+fn test_exec_callback_runs_before_the_instruction() {
+  // arrange
+  let mut cpu = CPU::new();
+  let script = r#"memory::on_exec(0x0602, |addr| memory::set_register("a", 0x55));"#;
+  let mut host = ScriptHost::new(script, &mut cpu).unwrap();
+
+  // act
+  run(&mut cpu, &mut host, vec![0xA9, 0x01, 0x85, 0x20, 0x00]); // LDA #$01; STA $20; BRK
+
+  // assert
+  assert_eq!(cpu.mem_read(0x20), 0x55);
+}
+
+#[test]
+fn test_access_callbacks_get_the_address_and_value() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.mem_write(0x21, 0x30);
+  let script = "
+    memory::on_write(0x20, |addr, value| memory::write(0x40, value + 1));
+    memory::on_read(0x21, |addr, value| memory::write(0x41, addr));
+  ";
+  let mut host = ScriptHost::new(script, &mut cpu).unwrap();
+  let program = vec![0xA9, 0x07, 0x85, 0x20, 0xA5, 0x21, 0x00]; // LDA #$07; STA $20; LDA $21; BRK
+
+  // act
+  run(&mut cpu, &mut host, program);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x40), 0x08);
+  assert_eq!(cpu.mem_read(0x41), 0x21);
+}
+
+#[test]
+fn test_the_scripts_own_accesses_do_not_trigger_callbacks() {
+  // arrange
+  let mut cpu = CPU::new();
+  let script = "
+    memory::on_write(0x20, |addr, value| memory::write(0x40, memory::read(0x40) + 1));
+    memory::write(0x20, 1);
+  ";
+  let mut host = ScriptHost::new(script, &mut cpu).unwrap();
+
+  // act
+  host.end_frame(&mut cpu).unwrap();
+
+  // assert
+  assert_eq!(cpu.mem_read(0x40), 0);
+}
+
+#[test]
+fn test_frame_callbacks_draw_the_overlay() {
+  // arrange
+  let mut cpu = CPU::new();
+  let script = r#"emu::on_frame(|| {
+    gui::box(0, 0, 3, 3, 0x0000FF, 0xFF0000);
+    gui::text(4, 0, "1", 0x00FF00);
+  });"#;
+  let mut host = ScriptHost::new(script, &mut cpu).unwrap();
+  let mut rgb = vec![0; 8 * 5 * 3];
+
+  // act
+  host.end_frame(&mut cpu).unwrap();
+  host.draw_overlay(&mut rgb, 8, 5);
+
+  // assert
+  let pixel = |x: usize, y: usize| &rgb[(y * 8 + x) * 3..(y * 8 + x) * 3 + 3];
+  assert_eq!(host.frame_count(), 1);
+  assert_eq!(pixel(0, 0), [0xFF, 0x00, 0x00]);
+  assert_eq!(pixel(1, 1), [0x00, 0x00, 0xFF]);
+  assert_eq!(pixel(5, 0), [0x00, 0xFF, 0x00]);
+  assert_eq!(pixel(4, 0), [0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_the_overlay_only_shows_the_last_frame() {
+  // arrange
+  let mut cpu = CPU::new();
+  let script = "emu::on_frame(|| if emu::frame_count() == 1 { gui::box(0, 0, 0, 0, 0xFFFFFF) });";
+  let mut host = ScriptHost::new(script, &mut cpu).unwrap();
+  let mut rgb = vec![0; 3];
+
+  // act
+  host.end_frame(&mut cpu).unwrap();
+  host.end_frame(&mut cpu).unwrap();
+  host.draw_overlay(&mut rgb, 1, 1);
+
+  // assert
+  assert_eq!(rgb, [0, 0, 0]);
+}
+
+#[test]
+fn test_save_states_go_back_in_time() {
+  // arrange
+  let mut cpu = CPU::new();
+  let script = "
+    memory::write(0x10, 1);
+    savestate::save(1);
+    memory::write(0x10, 2);
+    memory::set_register(\"x\", 9);
+    savestate::load(1);
+  ";
+
+  // act
+  ScriptHost::new(script, &mut cpu).unwrap();
+
+  // assert
+  assert_eq!(cpu.mem_read(0x10), 1);
+  assert_eq!(cpu.register_x, 0);
+}
+
+#[test]
+fn test_script_sets_the_joypad() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.bus.ppu = Some(PPU::new());
+
+  // act
+  ScriptHost::new(
+    "joypad::set(#{ a: true, right: true, b: false });",
+    &mut cpu,
+  )
+  .unwrap();
+
+  // assert
+  assert_eq!(cpu.bus.joypad.buttons, BUTTON_A | BUTTON_RIGHT);
+}
+
+#[test]
+fn test_script_errors() {
+  // arrange
+  let mut cpu = CPU::new();
+
+  // act
+  let syntax = ScriptHost::new("memory::write(", &mut cpu);
+  let button = ScriptHost::new("joypad::set(#{ turbo: true });", &mut cpu);
+  let slot = ScriptHost::new("savestate::load(3);", &mut cpu);
+  let address = ScriptHost::new("memory::read(0x10000);", &mut cpu);
+  let mut host = ScriptHost::new("emu::on_frame(|| memory::write(0, 256));", &mut cpu).unwrap();
+  let callback = host.end_frame(&mut cpu);
+
+  // assert
+  assert!(syntax.is_err());
+  assert!(button.err().unwrap().contains("unknown button \"turbo\""));
+  assert!(slot.err().unwrap().contains("nothing saved in slot 3"));
+  assert!(address.err().unwrap().contains("invalid address 65536"));
+  assert!(callback.unwrap_err().contains("invalid byte 256"));
+}
@@ -0,0 +1,197 @@
+use nes_emulator_rust::cpu::{BreakPolicy, StackFrame, StepOutcome, CPU};
+use nes_emulator_rust::debugger::Debugger;
+
+fn cpu_with_program(program: &[u8]) -> CPU {
+  let mut cpu = CPU::new();
+  cpu.load(program.to_vec()).unwrap();
+  cpu.reset();
+  cpu
+}
+
+#[test]
+fn test_push_writes_to_page_one_below_sp_0x10() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.stack_pointer = 0x0F;
+
+  // act
+  cpu.push(0x42);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x010F), 0x42);
+  assert_eq!(cpu.stack_pointer, 0x0E);
+}
+
+#[test]
+fn test_stack_pointer_wraps_around() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.stack_pointer = 0x00;
+
+  // act
+  cpu.push(0x12);
+  cpu.push(0x34);
+
+  // assert
+  assert_eq!(cpu.stack_pointer, 0xFE);
+  assert_eq!(cpu.mem_read(0x0100), 0x12);
+  assert_eq!(cpu.mem_read(0x01FF), 0x34);
+  assert_eq!(cpu.pop(), 0x34);
+  assert_eq!(cpu.pop(), 0x12);
+  assert_eq!(cpu.stack_pointer, 0x00);
+}
+
+#[test]
+fn test_push_u16_is_little_endian_in_memory() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.stack_pointer = 0xFF;
+
+  // act
+  cpu.push_u16(0x1234);
+
+  // assert
+  assert_eq!(cpu.mem_read(0x01FF), 0x12);
+  assert_eq!(cpu.mem_read(0x01FE), 0x34);
+  assert_eq!(cpu.pop_u16(), 0x1234);
+}
+
+#[test]
+fn test_jsr_and_rts_round_trip() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0x20, 0x04, 0x06, // JSR $0604
+    0x00, // BRK
+    0xE8, // $0604: INX
+    0x60, // RTS
+  ]);
+
+  // act
+  cpu.run().unwrap();
+
+  // assert
+  assert_eq!(cpu.register_x, 1);
+  assert_eq!(cpu.program_counter, 0x0604); // past the BRK
+  assert_eq!(cpu.stack_pointer, 0xFF);
+}
+
+#[test]
+fn test_pha_and_pla() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0xA9, 0x80, // LDA #$80
+    0x48, // PHA
+    0xA9, 0x00, // LDA #$00
+    0x68, // PLA
+    0x00, // BRK
+  ]);
+
+  // act
+  cpu.run().unwrap();
+
+  // assert
+  assert_eq!(cpu.accumulator, 0x80);
+  assert_eq!(cpu.status & 0b1000_0010, 0b1000_0000);
+  assert_eq!(cpu.stack_pointer, 0xFF);
+}
+
+#[test]
+fn test_php_pushes_the_break_flag_and_plp_drops_it() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0x08, 0x28, 0x00]); // PHP; PLP; BRK
+  cpu.status = 0b0000_0001;
+
+  // act
+  cpu.step().unwrap();
+  let pushed = cpu.mem_read(0x01FF);
+  cpu.step().unwrap();
+
+  // assert
+  assert_eq!(pushed, 0b0011_0001);
+  assert_eq!(cpu.status, 0b0010_0001);
+}
+
+#[test]
+fn test_brk_as_interrupt_and_rti() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0x00, 0xEA, // BRK (and its padding byte)
+    0xE8, // INX
+  ]);
+  cpu.break_policy = BreakPolicy::Interrupt;
+  cpu.mem_write_u16(0xFFFE, 0x0700);
+  cpu.mem_write(0x0700, 0x40); // RTI
+
+  // act
+  let brk = cpu.step();
+  let pushed_status = cpu.mem_read(0x01FD);
+  let handler = cpu.program_counter;
+  cpu.step().unwrap();
+  cpu.step().unwrap();
+
+  // assert
+  assert_eq!(brk, Ok(StepOutcome::Executed));
+  assert_eq!(handler, 0x0700);
+  assert_eq!(pushed_status & 0b0001_0000, 0b0001_0000);
+  assert_eq!(cpu.register_x, 1);
+  assert_eq!(cpu.stack_pointer, 0xFF);
+}
+
+#[test]
+fn test_call_stack_lists_nested_return_addresses() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0x20, 0x04, 0x06, // JSR $0604
+    0x00, // BRK
+    0x48, // $0604: PHA
+    0x20, 0x09, 0x06, // JSR $0609
+    0x60, // RTS
+    0xEA, // $0609: NOP
+    0x60, // RTS
+  ]);
+
+  // act
+  cpu.run_until_pc(0x0609).unwrap();
+  let frames = cpu.call_stack();
+
+  // assert
+  assert_eq!(
+    frames,
+    vec![
+      StackFrame {
+        stack_addr: 0x01FB,
+        caller: 0x0605,
+        subroutine: 0x0609,
+        return_addr: 0x0608,
+      },
+      StackFrame {
+        stack_addr: 0x01FE,
+        caller: 0x0600,
+        subroutine: 0x0604,
+        return_addr: 0x0603,
+      },
+    ]
+  );
+  assert_eq!(cpu.stack_contents(), vec![0x07, 0x06, 0x00, 0x02, 0x06]);
+}
+
+#[test]
+fn test_debugger_stack_command() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0x20, 0x04, 0x06, // JSR $0604
+    0x00, // BRK
+    0xEA, // $0604: NOP
+  ]);
+  let mut debugger = Debugger::new();
+  cpu.step().unwrap();
+
+  // act
+  let output = debugger.execute(&mut cpu, "stack").unwrap();
+
+  // assert
+  assert_eq!(
+    output,
+    "SP:FD [02 06]\n$01FE: returns to $0603 (JSR $0604 at $0600)"
+  );
+}
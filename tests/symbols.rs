@@ -0,0 +1,136 @@
+use std::fs;
+
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::debugger::Debugger;
+use nes_emulator_rust::disassembler::disassemble_with_symbols;
+use nes_emulator_rust::symbols::SymbolTable;
+use nes_emulator_rust::tracer::Tracer;
+
+fn cpu_with_program(program: &[u8]) -> CPU {
+  let mut cpu = CPU::new();
+  cpu.load(program.to_vec()).unwrap();
+  cpu.reset();
+  cpu
+}
+
+fn snake_symbols() -> SymbolTable {
+  let mut symbols = SymbolTable::new();
+  symbols.add(0x068D, "update_snake");
+  symbols.add(0x0010, "snake_head");
+  symbols
+}
+
+#[test]
+fn test_ld65_debug_file_labels() {
+  // arrange
+  let text = "\
+version\tmajor=2,minor=0
+sym\tid=0,name=\"update_snake\",addrsize=absolute,scope=0,def=3,ref=7,val=0x68D,seg=0,type=lab
+sym\tid=1,name=\"SPEED\",addrsize=zeropage,scope=0,def=4,val=0x4,type=equ
+sym\tid=2,name=\"snake_head\",addrsize=zeropage,scope=0,def=5,val=0x10,seg=1,type=lab
+";
+
+  // act
+  let symbols = SymbolTable::from_ld65_dbg(text).unwrap();
+
+  // assert
+  assert_eq!(symbols.len(), 2);
+  assert_eq!(symbols.label(0x068D), Some("update_snake"));
+  assert_eq!(symbols.address("snake_head"), Some(0x0010));
+  assert_eq!(symbols.address("SPEED"), None);
+}
+
+#[test]
+fn test_fceux_name_list() {
+  // act
+  let symbols = SymbolTable::from_fceux_nl("$068D#update_snake#moves it\n$0010#snake_head#\n");
+
+  // assert
+  let symbols = symbols.unwrap();
+  assert_eq!(symbols.label(0x068D), Some("update_snake"));
+  assert_eq!(symbols.label(0x0010), Some("snake_head"));
+  assert!(SymbolTable::from_fceux_nl("068D#oops#").is_err());
+}
+
+#[test]
+fn test_mesen_labels_are_mapped_to_cpu_addresses() {
+  // act
+  let symbols = SymbolTable::from_mesen_mlb(
+    "R:0010-0011:snake_head\nP:0123:reset:entry point\nS:0000:save_slot\nC:0000:tiles\n",
+  )
+  .unwrap();
+
+  // assert
+  assert_eq!(symbols.label(0x0010), Some("snake_head"));
+  assert_eq!(symbols.label(0x8123), Some("reset"));
+  assert_eq!(symbols.label(0x6000), Some("save_slot"));
+  assert_eq!(symbols.len(), 3);
+}
+
+#[test]
+fn test_load_picks_the_format_from_the_extension() {
+  // arrange
+  let path = std::env::temp_dir().join(format!("symbols-{}.nl", std::process::id()));
+  fs::write(&path, "$068D#update_snake#\n").unwrap();
+
+  // act
+  let symbols = SymbolTable::load(&path).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  // assert
+  assert_eq!(symbols.address("update_snake"), Some(0x068D));
+  assert!(SymbolTable::load("labels.txt").is_err());
+}
+
+#[test]
+fn test_disassembler_shows_labels() {
+  // arrange
+  let cpu = cpu_with_program(&[
+    0x20, 0x8D, 0x06, // JSR $068D
+    0xB5, 0x10, // LDA $10,X
+    0xD0, 0xF9, // BNE $0600
+  ]);
+  let mut symbols = snake_symbols();
+  symbols.add(0x0600, "loop");
+
+  // act
+  let jsr = disassemble_with_symbols(&cpu.bus, 0x0600, &symbols);
+  let lda = disassemble_with_symbols(&cpu.bus, 0x0603, &symbols);
+  let bne = disassemble_with_symbols(&cpu.bus, 0x0605, &symbols);
+
+  // assert
+  assert_eq!(jsr, ("JSR update_snake".to_string(), 3));
+  assert_eq!(lda, ("LDA snake_head,X".to_string(), 2));
+  assert_eq!(bne, ("BNE loop".to_string(), 2));
+}
+
+#[test]
+fn test_tracer_shows_labels() {
+  // arrange
+  let cpu = cpu_with_program(&[0x20, 0x8D, 0x06]); // JSR $068D
+
+  // act
+  let line = Tracer::format_line_with_symbols(&cpu, &snake_symbols());
+
+  // assert
+  assert!(line.starts_with("0600  20 8D 06  JSR update_snake "));
+}
+
+#[test]
+fn test_debugger_uses_labels() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0x20, 0x04, 0x06, 0x00, 0xE8]); // JSR $0604; BRK; INX
+  let mut debugger = Debugger::new();
+  debugger.symbols.add(0x0604, "count");
+  cpu.step().unwrap();
+
+  // act
+  let disasm = debugger.execute(&mut cpu, "disasm $0600 2").unwrap();
+  let mem = debugger.execute(&mut cpu, "mem count 1").unwrap();
+  let stack = debugger.execute(&mut cpu, "stack").unwrap();
+
+  // assert
+  assert_eq!(disasm, "$0600  JSR count\n$0603  BRK");
+  assert_eq!(mem, "0604: E8");
+  assert!(stack.contains("(JSR count at $0600)"));
+}
@@ -0,0 +1,60 @@
+use nes_emulator_rust::cpu::CPU;
+use nes_emulator_rust::terminal::{half_block_lines, register_lines};
+
+#[test]
+fn test_half_blocks_pair_up_rows() {
+  // arrange
+  let frame = [
+    255, 0, 0, 255, 0, 0, // red, red
+    0, 0, 255, 0, 255, 0, // blue, green
+  ];
+
+  // act
+  let lines = half_block_lines(&frame, 2, 2);
+
+  // assert
+  assert_eq!(
+    lines,
+    vec![
+      "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[38;2;255;0;0m\x1b[48;2;0;255;0m▀\x1b[0m"
+        .to_string()
+    ]
+  );
+}
+
+#[test]
+fn test_half_blocks_set_the_colours_only_when_they_change() {
+  // arrange
+  let frame = [255; 4 * 3 * 3];
+
+  // act
+  let lines = half_block_lines(&frame, 4, 3);
+
+  // assert
+  assert_eq!(lines.len(), 2);
+  assert_eq!(
+    lines[0],
+    "\x1b[38;2;255;255;255m\x1b[48;2;255;255;255m▀▀▀▀\x1b[0m"
+  );
+  assert_eq!(
+    lines[1],
+    "\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m▀▀▀▀\x1b[0m"
+  );
+}
+
+#[test]
+fn test_register_panel() {
+  // arrange
+  let mut cpu = CPU::new();
+  cpu.program_counter = 0x0612;
+  cpu.accumulator = 0x0A;
+  cpu.status = 0b1000_0011;
+
+  // act
+  let lines = register_lines(&cpu);
+
+  // assert
+  assert_eq!(lines[0], "PC  $0612");
+  assert_eq!(lines[1], "A   $0A");
+  assert_eq!(lines[5..7], ["    NV-BDIZC", "P   10000011"]);
+}
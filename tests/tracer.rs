@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use nes_emulator_rust::cpu::{JamPolicy, CPU};
+use nes_emulator_rust::disassembler::disassemble;
+use nes_emulator_rust::tracer::Tracer;
+
+/// An output the test can read back after giving it to the tracer.
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.borrow_mut().write(buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl SharedOutput {
+  fn lines(&self) -> Vec<String> {
+    String::from_utf8(self.0.borrow().clone())
+      .unwrap()
+      .lines()
+      .map(String::from)
+      .collect()
+  }
+}
+
+fn cpu_with_program(program: &[u8]) -> CPU {
+  let mut cpu = CPU::new();
+  cpu.load(program.to_vec()).unwrap();
+  cpu.reset();
+  cpu
+}
+
+fn traced_pcs(lines: &[String]) -> Vec<&str> {
+  lines.iter().map(|line| &line[0..4]).collect()
+}
+
+#[test]
+fn test_disassemble_formats_operands() {
+  // arrange
+  let cpu = cpu_with_program(&[
+    0xA9, 0x01, // LDA #$01
+    0xB1, 0x10, // LDA ($10),Y
+    0x9D, 0x00, 0x02, // STA $0200,X
+    0xD0, 0xF7, // BNE $0600
+    0x0A, // ASL A
+    0x02, // not an opcode
+  ]);
+
+  // act
+  let lines: Vec<(String, u16)> = [0x0600, 0x0602, 0x0604, 0x0607, 0x0609, 0x060A]
+    .iter()
+    .map(|addr| disassemble(&cpu.bus, *addr))
+    .collect();
+
+  // assert
+  assert_eq!(lines[0], ("LDA #$01".to_string(), 2));
+  assert_eq!(lines[1], ("LDA ($10),Y".to_string(), 2));
+  assert_eq!(lines[2], ("STA $0200,X".to_string(), 3));
+  assert_eq!(lines[3], ("BNE $0600".to_string(), 2));
+  assert_eq!(lines[4], ("ASL A".to_string(), 1));
+  assert_eq!(lines[5], (".byte $02".to_string(), 1));
+}
+
+#[test]
+fn test_format_line_shows_bytes_instruction_and_registers() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0x8D, 0x00, 0x02]); // STA $0200
+  cpu.accumulator = 0x42;
+
+  // act
+  let line = Tracer::format_line(&cpu);
+
+  // assert
+  assert_eq!(
+    line,
+    "0600  8D 00 02  STA $0200                       A:42 X:00 Y:00 P:00 SP:FF CYC:0"
+  );
+}
+
+#[test]
+fn test_trace_writes_every_instruction_in_the_pc_range() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.pc_range = Some(0x0601..=0x0602);
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert_eq!(traced_pcs(&output.lines()), vec!["0601", "0602"]);
+}
+
+#[test]
+fn test_trace_only_when_condition_holds() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.condition = Some(Box::new(|cpu| cpu.register_x >= 2));
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert_eq!(traced_pcs(&output.lines()), vec!["0602", "0603"]);
+}
+
+#[test]
+fn test_trace_only_subroutine_entries() {
+  // arrange
+  let mut cpu = cpu_with_program(&[
+    0x20, 0x05, 0x06, // JSR $0605
+    0x00, 0x00, // BRK
+    0xE8, // $0605: INX
+    0x60, // RTS
+  ]);
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.subroutine_entries_only = true;
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert_eq!(traced_pcs(&output.lines()), vec!["0605"]);
+}
+
+#[test]
+fn test_ring_buffer_keeps_the_last_instructions() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.set_ring_buffer(2);
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert!(output.lines().is_empty());
+  let pcs: Vec<&str> = tracer.ring_buffer().map(|line| &line[0..4]).collect();
+  assert_eq!(pcs, vec!["0602", "0603"]);
+}
+
+#[test]
+fn test_ring_buffer_is_dumped_on_unknown_opcode() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0xFF]); // INX; INX; INX; (unknown)
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.set_ring_buffer(2);
+
+  // act
+  let result = tracer.run(&mut cpu);
+
+  // assert
+  assert!(result.is_err());
+  let lines = output.lines();
+  assert_eq!(lines[0], "OP code ff not found at $0603, last 2 instructions:");
+  assert_eq!(traced_pcs(&lines[1..]), vec!["0602", "0603"]);
+  assert!(lines[2].contains(".byte $FF"));
+}
+
+#[test]
+fn test_ring_buffer_is_not_dumped_on_ignored_jams() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xF2, 0xE8, 0x00]); // (jam); INX; BRK
+  cpu.jam_policy = JamPolicy::Ignore;
+  let output = SharedOutput::default();
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(output.clone()));
+  tracer.set_ring_buffer(4);
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert!(output.lines().is_empty());
+  assert_eq!(tracer.ring_buffer().count(), 3);
+}
+
+#[test]
+fn test_ring_buffer_with_no_capacity_keeps_nothing() {
+  // arrange
+  let mut cpu = cpu_with_program(&[0xE8, 0xE8, 0xE8, 0x00]); // INX; INX; INX; BRK
+  let mut tracer = Tracer::new();
+  tracer.set_output(Box::new(SharedOutput::default()));
+  tracer.set_ring_buffer(0);
+
+  // act
+  tracer.run(&mut cpu).unwrap();
+
+  // assert
+  assert_eq!(tracer.ring_buffer().count(), 0);
+}